@@ -0,0 +1,3 @@
+#!/bin/sh
+# Runs a child shell that writes USR1 to the file given when it gets that signal.
+sh -c "trap 'echo USR1 > \"$1\"; exit' USR1; while true; do sleep 0.1; done"
@@ -1,48 +1,116 @@
+use client::events::print_events;
 use client::local::RestManagerClient;
 use client::status::status;
 use client::ManagerClient;
+use daemons::{create_dir, list_daemons};
+use env;
+use env::EnvFile;
+use export::{export, ExportOptions};
+use formation::Formation;
+use import::import;
+use oneoff::run_oneoff;
 use procfile::Procfile;
 use server::local::RestManagerServer;
+use server::events::EventFilter;
 use server::start::start;
-use server::ServerRunMode;
+use server::{ServerOptions, ServerRunMode};
 use std::io::Write;
 use std::path::PathBuf;
 use tasks::TaskSpec;
+use top;
 use yamlize::yamlize;
 use Result;
 
 /// An action that the straw boss can do.
 #[derive(Debug)]
 pub enum Action {
-    Start(Procfile, ServerRunMode, PathBuf),
+    Start(Procfile, Vec<EnvFile>, ServerRunMode, ServerOptions, PathBuf),
+    Run(Procfile, Vec<EnvFile>, Vec<String>),
+    Export(Procfile, Vec<EnvFile>, String, PathBuf, ExportOptions),
+    Import(String, PathBuf, String),
     Status(PathBuf),
+    Top(PathBuf),
+    Events(PathBuf, EventFilter),
+    ListDaemons(PathBuf),
     Stop(PathBuf, TaskSpec),
+    Signal(PathBuf, TaskSpec, i32),
+    Pause(PathBuf, TaskSpec),
+    Resume(PathBuf, TaskSpec),
+    Scale(PathBuf, Formation),
     Yamlize(Procfile),
 }
 
 impl Action {
     /// Execute an action. This dispatches to the appropriate function to take the action
-    /// described. It writes its output to the `Write` implementor passed in.
-    pub fn execute<W: Write>(self, writer: &mut W) -> Result<()> {
+    /// described. It writes its output to the `Write` implementor passed in, and it returns the
+    /// exit code for the process.
+    pub fn execute<W: Write>(self, writer: &mut W) -> Result<i32> {
         match self {
-            Action::Start(procfile, run_mode, socket_domain) => {
-                let mut server = RestManagerServer::at_path(socket_domain);
-                let services = procfile.read_services()?;
+            Action::Start(procfile, env_files, run_mode, options, socket_domain) => {
+                if let Some(dir) = socket_domain.parent() {
+                    create_dir(dir)?;
+                }
+                let mut server = RestManagerServer::with_options(socket_domain, options);
+                let services = env::read_services(&procfile, &env_files)?;
                 start(&mut server, run_mode, services)
             }
+            Action::Run(procfile, env_files, command) => {
+                run_oneoff(&procfile, &env_files, &command)
+            }
+            Action::Export(procfile, env_files, format, dir, options) => {
+                let services = env::read_services(&procfile, &env_files)?;
+                let warnings = export(&format, &services, &options, &dir)?;
+                for warning in warnings {
+                    eprintln!("warning: {}", &warning);
+                }
+                Ok(0)
+            }
+            Action::Import(format, input, output) => {
+                let warnings = import(&format, &input, &output, writer)?;
+                for warning in warnings {
+                    eprintln!("warning: {}", &warning);
+                }
+                Ok(0)
+            }
             Action::Status(socket_domain) => {
                 let client = RestManagerClient::at_path(socket_domain);
-                status(&client).and_then(|ms| {
-                    writer
-                        .write_all(ms.get_message().as_bytes())
-                        .map_err(|err| format_err!("Unable to write output: {:?}", &err))
-                })
+                status(&client)
+                    .and_then(|ms| {
+                        writer
+                            .write_all(ms.get_message().as_bytes())
+                            .map_err(|err| format_err!("Unable to write output: {:?}", &err))
+                    }).and(Ok(0))
+            }
+            Action::Top(socket_domain) => {
+                let client = RestManagerClient::at_path(socket_domain);
+                top::run(&client)
+            }
+            Action::Events(socket_domain, filter) => {
+                let client = RestManagerClient::at_path(socket_domain);
+                print_events(&client, filter, writer).and(Ok(0))
             }
+            Action::ListDaemons(runtime_dir) => list_daemons(&runtime_dir, writer).and(Ok(0)),
             Action::Stop(socket_domain, tasks) => {
                 let client = RestManagerClient::at_path(socket_domain);
-                client.stop(tasks)
+                client.stop(tasks).and(Ok(0))
+            }
+            Action::Signal(socket_domain, tasks, signal) => {
+                let client = RestManagerClient::at_path(socket_domain);
+                client.signal(tasks, signal).and(Ok(0))
+            }
+            Action::Pause(socket_domain, tasks) => {
+                let client = RestManagerClient::at_path(socket_domain);
+                client.pause(tasks).and(Ok(0))
+            }
+            Action::Resume(socket_domain, tasks) => {
+                let client = RestManagerClient::at_path(socket_domain);
+                client.resume(tasks).and(Ok(0))
+            }
+            Action::Scale(socket_domain, formation) => {
+                let client = RestManagerClient::at_path(socket_domain);
+                client.scale(formation).and(Ok(0))
             }
-            Action::Yamlize(ref procfile) => yamlize(procfile, writer),
+            Action::Yamlize(ref procfile) => yamlize(procfile, writer).and(Ok(0)),
         }
     }
 }
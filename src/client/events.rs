@@ -0,0 +1,97 @@
+use client::ManagerClient;
+use server::events::EventFilter;
+use std::io;
+use std::io::Write;
+use Result;
+
+/// Print the server's events that pass the filter as JSON, one per line, until the server stops.
+/// Each line's flushed as it's written, so scripts reading the output see it right away. Closing
+/// the output, like piping it to `head` does, ends this quietly.
+pub fn print_events<C, W>(client: &C, filter: EventFilter, writer: &mut W) -> Result<()>
+where
+    C: ManagerClient,
+    W: Write,
+{
+    let mut written = Ok(());
+    client.subscribe(filter, |event| match event.to_json() {
+        Ok(json) => {
+            written = writeln!(writer, "{}", json).and_then(|_| writer.flush());
+            written.is_ok()
+        }
+        Err(err) => {
+            eprintln!("{}", &err);
+            true
+        }
+    })?;
+    match written {
+        Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => written.map_err(|err| format_err!("Unable to write output: {:?}", &err)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::print_events;
+    use client::fake::FakeManagerClient;
+    use server::events::{Event, EventFilter, EventKind};
+    use spectral::prelude::*;
+    use std::io;
+    use std::io::Write;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tasks::TaskSpec;
+
+    /// Output that's closed after a number of writes.
+    struct ClosedPipe(usize);
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+            }
+            self.0 -= 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn events() -> Vec<Event> {
+        let mut started = Event::new(EventKind::Started, "web");
+        started.time = UNIX_EPOCH + Duration::from_millis(1_538_395_200_250);
+        started.pid = Some(4242);
+        let mut exited = Event::new(EventKind::Exited, "web");
+        exited.time = UNIX_EPOCH + Duration::from_secs(1_538_395_260);
+        exited.code = Some(1);
+        vec![started, exited]
+    }
+
+    #[test]
+    fn test_prints_json_lines() {
+        let mut client = FakeManagerClient::new(vec![]);
+        client.events = events();
+        let filter = EventFilter {
+            tasks: TaskSpec::List(vec![String::from("web")]),
+            kinds: vec![EventKind::Started, EventKind::Exited],
+        };
+        let mut output = Vec::new();
+        assert_that(&print_events(&client, filter.clone(), &mut output)).is_ok();
+
+        assert_that(&String::from_utf8(output).unwrap()).is_equal_to(String::from(
+            "{\"event\":\"started\",\"service\":\"web\",\"time\":\"2018-10-01T12:00:00.250Z\",\
+             \"pid\":4242}\n\
+             {\"event\":\"exited\",\"service\":\"web\",\"time\":\"2018-10-01T12:01:00.000Z\",\
+             \"code\":1}\n",
+        ));
+        assert_that(&*client.filters.borrow()).is_equal_to(vec![filter]);
+    }
+
+    #[test]
+    fn test_stops_quietly_when_output_closes() {
+        let mut client = FakeManagerClient::new(vec![]);
+        client.events = events();
+        let mut output = ClosedPipe(1);
+        assert_that(&print_events(&client, EventFilter::default(), &mut output)).is_ok();
+    }
+}
@@ -0,0 +1,133 @@
+use client::ManagerClient;
+use formation::Formation;
+use server::events::{Event, EventFilter};
+use server::{DaemonInfo, ProcessScale};
+use service::exit::ExitInfo;
+use service::worker::WorkerStatus;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use tasks::TaskSpec;
+use Result;
+
+/// A client for tests. It answers with canned workers, scale and events, and remembers what it was
+/// asked to do.
+pub struct FakeManagerClient {
+    pub running: bool,
+    pub workers: Vec<WorkerStatus>,
+    pub scale: Vec<ProcessScale>,
+    pub events: Vec<Event>,
+    /// The requests that change something, like `restart web`.
+    pub calls: RefCell<Vec<String>>,
+    /// The filters that were subscribed with.
+    pub filters: RefCell<Vec<EventFilter>>,
+}
+
+impl FakeManagerClient {
+    /// A client for a server that's running the workers.
+    pub fn new(workers: Vec<WorkerStatus>) -> FakeManagerClient {
+        FakeManagerClient {
+            running: true,
+            workers,
+            scale: vec![],
+            events: vec![],
+            calls: RefCell::new(vec![]),
+            filters: RefCell::new(vec![]),
+        }
+    }
+
+    /// A client that can't find a server.
+    pub fn not_running() -> FakeManagerClient {
+        FakeManagerClient {
+            running: false,
+            ..FakeManagerClient::new(vec![])
+        }
+    }
+
+    fn record(&self, call: String) -> Result<()> {
+        self.calls.borrow_mut().push(call);
+        Ok(())
+    }
+
+    fn check_running(&self) -> Result<()> {
+        if self.running {
+            Ok(())
+        } else {
+            Err(format_err!("not running"))
+        }
+    }
+}
+
+fn describe(tasks: &TaskSpec) -> String {
+    match tasks {
+        TaskSpec::All => String::from("all"),
+        TaskSpec::List(names) => names.join(","),
+    }
+}
+
+impl ManagerClient for FakeManagerClient {
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_info(&self) -> Result<DaemonInfo> {
+        unimplemented!()
+    }
+
+    fn get_workers(&self) -> Result<Vec<WorkerStatus>> {
+        self.check_running().map(|_| self.workers.clone())
+    }
+
+    fn get_exit_info(&self) -> Result<BTreeMap<String, ExitInfo>> {
+        unimplemented!()
+    }
+
+    fn stop(&self, tasks: TaskSpec) -> Result<()> {
+        self.record(format!("stop {}", describe(&tasks)))
+    }
+
+    fn signal(&self, tasks: TaskSpec, signal: i32) -> Result<()> {
+        self.record(format!("signal {} {}", describe(&tasks), signal))
+    }
+
+    fn pause(&self, tasks: TaskSpec) -> Result<()> {
+        self.record(format!("pause {}", describe(&tasks)))
+    }
+
+    fn resume(&self, tasks: TaskSpec) -> Result<()> {
+        self.record(format!("resume {}", describe(&tasks)))
+    }
+
+    fn restart(&self, tasks: TaskSpec) -> Result<()> {
+        self.record(format!("restart {}", describe(&tasks)))
+    }
+
+    fn get_logs(&self, name: &str, _count: usize) -> Result<Vec<String>> {
+        Ok(vec![format!("{} says hello", name), format!("{} says bye", name)])
+    }
+
+    fn get_scale(&self) -> Result<Vec<ProcessScale>> {
+        self.check_running().map(|_| self.scale.clone())
+    }
+
+    fn scale(&self, formation: Formation) -> Result<()> {
+        let counts = formation
+            .names()
+            .iter()
+            .map(|name| format!("{}={}", name, formation.count(name)))
+            .collect::<Vec<_>>();
+        self.record(format!("scale {}", counts.join(",")))
+    }
+
+    fn subscribe<F>(&self, filter: EventFilter, mut on_event: F) -> Result<()>
+    where
+        F: FnMut(Event) -> bool,
+    {
+        self.filters.borrow_mut().push(filter);
+        for event in &self.events {
+            if !on_event(event.clone()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
 use client::ManagerClient;
+use formation::Formation;
 use messaging::{connect, Receiver, Sender};
 use server::local::DOMAIN_SOCKET;
-use server::{RequestMessage, ResponseMessage};
-use service::Service;
+use server::events::{Event, EventFilter};
+use server::{DaemonInfo, ProcessScale, RequestMessage, ResponseMessage};
+use service::exit::ExitInfo;
+use service::worker::WorkerStatus;
+use std::collections::BTreeMap;
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
 use tasks::TaskSpec;
@@ -22,23 +26,62 @@ impl RestManagerClient {
         RestManagerClient { socket_path }
     }
 
+    /// Connect to the server. If the socket's there but nothing answers, the daemon probably
+    /// crashed, and that's reported instead of the raw connection error.
     fn connect(&self) -> Result<UnixStream> {
-        connect(&self.socket_path)
+        if !self.socket_path.exists() {
+            return Err(format_err!(
+                "Straw-boss not running. There's no socket at {:?}.",
+                &self.socket_path
+            ));
+        }
+        connect(&self.socket_path).map_err(|_| {
+            format_err!(
+                "Straw-boss daemon not responding on {:?}. It may have crashed; starting it again \
+                 will clean up.",
+                &self.socket_path
+            )
+        })
     }
 }
 
 impl ManagerClient for RestManagerClient {
+    /// Is a server listening and answering? A socket file that's left over from a crashed
+    /// daemon doesn't count.
     fn is_running(&self) -> bool {
-        self.socket_path.exists()
+        self.connect()
+            .and_then(|mut stream| {
+                stream.send(RequestMessage::Ping)?;
+                stream.recv::<ResponseMessage>()
+            }).map(|response| response == ResponseMessage::Pong)
+            .unwrap_or(false)
+    }
+
+    fn get_info(&self) -> Result<DaemonInfo> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::GetInfo)?;
+        match stream.recv()? {
+            ResponseMessage::Info(info) => Ok(info),
+            response => Err(format_err!("Unexpected response: {:?}", &response)),
+        }
     }
 
-    fn get_workers(&self) -> Result<Vec<Service>> {
+    fn get_workers(&self) -> Result<Vec<WorkerStatus>> {
         let mut stream = self.connect()?;
         stream.send(RequestMessage::GetWorkers)?;
-        stream.recv().map(|response: ResponseMessage| {
-            let ResponseMessage::Workers(workers) = response;
-            workers
-        })
+        match stream.recv()? {
+            ResponseMessage::Workers(workers) => Ok(workers),
+            response => Err(format_err!("Unexpected response: {:?}", &response)),
+        }
+    }
+
+    fn get_exit_info(&self) -> Result<BTreeMap<String, ExitInfo>> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::GetExitInfo)?;
+        match stream.recv()? {
+            ResponseMessage::ExitInfo(exits) => Ok(exits),
+            response => Err(format_err!("Unexpected response: {:?}", &response)),
+        }
     }
 
     fn stop(&self, tasks: TaskSpec) -> Result<()> {
@@ -49,6 +92,77 @@ impl ManagerClient for RestManagerClient {
         };
         stream.send(message)
     }
+
+    fn signal(&self, tasks: TaskSpec, signal: i32) -> Result<()> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::Signal(tasks, signal))
+    }
+
+    fn pause(&self, tasks: TaskSpec) -> Result<()> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::Pause(tasks))
+    }
+
+    fn resume(&self, tasks: TaskSpec) -> Result<()> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::Resume(tasks))
+    }
+
+    fn restart(&self, tasks: TaskSpec) -> Result<()> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::Restart(tasks))
+    }
+
+    /// The last `count` lines that a service wrote to stdout or stderr, oldest first.
+    fn get_logs(&self, name: &str, count: usize) -> Result<Vec<String>> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::GetLogs(String::from(name), count))?;
+        match stream.recv()? {
+            ResponseMessage::Logs(lines) => Ok(lines),
+            ResponseMessage::Error(message) => Err(format_err!("{}", message)),
+            response => Err(format_err!("Unexpected response: {:?}", &response)),
+        }
+    }
+
+    fn get_scale(&self) -> Result<Vec<ProcessScale>> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::GetScale)?;
+        match stream.recv()? {
+            ResponseMessage::ProcessScales(scale) => Ok(scale),
+            response => Err(format_err!("Unexpected response: {:?}", &response)),
+        }
+    }
+
+    /// Change how many instances of each process type in the formation the server runs. Process
+    /// types that the formation doesn't mention are left alone.
+    fn scale(&self, formation: Formation) -> Result<()> {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::Scale(formation))?;
+        match stream.recv()? {
+            ResponseMessage::Scaled => Ok(()),
+            ResponseMessage::Error(message) => Err(format_err!("{}", message)),
+            response => Err(format_err!("Unexpected response: {:?}", &response)),
+        }
+    }
+
+    fn subscribe<F>(&self, filter: EventFilter, mut on_event: F) -> Result<()>
+    where
+        F: FnMut(Event) -> bool,
+    {
+        let mut stream = self.connect()?;
+        stream.send(RequestMessage::Subscribe(filter))?;
+        loop {
+            match stream.recv()? {
+                ResponseMessage::Event(event) => {
+                    if !on_event(event) {
+                        return Ok(());
+                    }
+                }
+                ResponseMessage::Stopping => return Ok(()),
+                response => return Err(format_err!("Unexpected response: {:?}", &response)),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
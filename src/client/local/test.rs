@@ -2,7 +2,9 @@ use messaging::{Receiver, Sender};
 use server::RequestMessage;
 use server::RequestMessage::*;
 use server::ResponseMessage::*;
+use service::worker::{WorkerState, WorkerStatus};
 use service::Service;
+use std::collections::BTreeMap;
 use std::fs;
 use std::os::unix::net::UnixListener;
 use std::path::{Path, PathBuf};
@@ -49,12 +51,46 @@ impl MockServer {
             let mut stream = stream.unwrap();
             let request: RequestMessage = stream.recv().unwrap();
             match request {
+                Ping => stream.send(Pong).unwrap(),
                 GetWorkers => {
                     {
                         let mut calls = self.calls.write().unwrap();
                         calls.push(GetWorkers);
                     }
-                    let response = Workers(self.workers.clone());
+                    let response = Workers(
+                        self.workers
+                            .iter()
+                            .map(|w| WorkerStatus::new(w.clone(), WorkerState::Running))
+                            .collect(),
+                    );
+                    stream.send(response).unwrap();
+                }
+                GetExitInfo => {
+                    {
+                        let mut calls = self.calls.write().unwrap();
+                        calls.push(GetExitInfo);
+                    }
+                    stream.send(ExitInfo(BTreeMap::new())).unwrap();
+                }
+                GetScale => stream.send(ProcessScales(vec![])).unwrap(),
+                GetLogs(name, count) => {
+                    let response = if self.workers.iter().any(|w| w.name == name) {
+                        Logs((0..count).map(|i| format!("{} line {}", name, i)).collect())
+                    } else {
+                        Error(format!("There's no service named {}.", name))
+                    };
+                    stream.send(response).unwrap();
+                }
+                Scale(formation) => {
+                    let response = if formation.names().contains(&"missing") {
+                        Error(String::from("Unable to scale missing."))
+                    } else {
+                        Scaled
+                    };
+                    {
+                        let mut calls = self.calls.write().unwrap();
+                        calls.push(Scale(formation));
+                    }
                     stream.send(response).unwrap();
                 }
                 stop_message => {
@@ -79,9 +115,14 @@ fn make_socket_name(name: &str) -> PathBuf {
 mod is_running {
     use super::super::super::ManagerClient;
     use super::super::RestManagerClient;
-    use super::{make_socket_name, DisappearingFile};
+    use super::{make_socket_name, DisappearingFile, MockServer};
+    use messaging::Sender;
+    use server::RequestMessage::*;
     use spectral::prelude::*;
-    use std::fs;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+    use std::time::Duration;
 
     #[test]
     fn test_returns_false_if_no_server() {
@@ -93,10 +134,44 @@ mod is_running {
     #[test]
     fn test_returns_true_if_server() {
         let socket_path = make_socket_name("test_returns_true_if_server");
-        let socket = DisappearingFile(socket_path.clone());
-        let _ = fs::File::create(&socket.0).unwrap();
+        let server_socket_path = socket_path.clone();
+        let calls = Arc::new(RwLock::new(vec![]));
+        let server_calls = calls.clone();
+
+        let handle = thread::spawn(move || {
+            let mut server = MockServer::new(server_socket_path, vec![], server_calls);
+            server.run();
+        });
+
+        thread::sleep(Duration::from_secs(1));
         let client = RestManagerClient::at_path(socket_path.clone());
         assert_that(&client.is_running()).is_true();
+
+        let mut stream = UnixStream::connect(&socket_path).unwrap();
+        stream.send(StopServer).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_returns_false_if_socket_is_stale() {
+        let socket_path = make_socket_name("test_returns_false_if_socket_is_stale");
+        let socket = DisappearingFile(socket_path.clone());
+        drop(UnixListener::bind(&socket.0).unwrap());
+
+        let client = RestManagerClient::at_path(socket_path.clone());
+        assert_that(&socket_path).exists();
+        assert_that(&client.is_running()).is_false();
+    }
+
+    #[test]
+    fn test_reports_stale_socket_as_not_responding() {
+        let socket_path = make_socket_name("test_reports_stale_socket_as_not_responding");
+        let socket = DisappearingFile(socket_path.clone());
+        drop(UnixListener::bind(&socket.0).unwrap());
+
+        let client = RestManagerClient::at_path(socket_path.clone());
+        let message = client.get_workers().unwrap_err().to_string();
+        assert_that(&message).contains("not responding");
     }
 }
 
@@ -107,6 +182,7 @@ mod get_workers {
     use rmp_serde::Serializer;
     use serde::Serialize;
     use server::RequestMessage::*;
+    use service::worker::{WorkerState, WorkerStatus};
     use service::Service;
     use spectral::prelude::*;
     use std::os::unix::net::UnixStream;
@@ -172,8 +248,10 @@ mod get_workers {
         });
 
         thread::sleep(Duration::from_secs(1));
-        assert_that(&client.get_workers())
-            .is_ok_containing(vec![Service::new("web", "spawn server")]);
+        assert_that(&client.get_workers()).is_ok_containing(vec![WorkerStatus::new(
+            Service::new("web", "spawn server"),
+            WorkerState::Running,
+        )]);
         {
             let calls = calls.read().unwrap();
             assert_that(&calls[0]).is_equal_to(&GetWorkers);
@@ -184,6 +262,95 @@ mod get_workers {
     }
 }
 
+mod get_exit_info {
+    use super::{make_socket_name, MockServer};
+    use client::local::RestManagerClient;
+    use client::ManagerClient;
+    use server::RequestMessage::*;
+    use spectral::prelude::*;
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+    use std::time::Duration;
+    use tasks::TaskSpec;
+
+    #[test]
+    fn test_returns_err_if_no_server() {
+        let socket_path = make_socket_name("test_get_exit_info_err_if_no_server");
+        let client = RestManagerClient::at_path(socket_path);
+        assert_that(&client.get_exit_info()).is_err();
+    }
+
+    #[test]
+    fn test_calls_server() {
+        let socket_path = make_socket_name("test_get_exit_info_calls_server");
+        let server_socket_path = socket_path.clone();
+        let calls = Arc::new(RwLock::new(vec![]));
+        let server_calls = calls.clone();
+        let client = RestManagerClient::at_path(socket_path.clone());
+
+        let handle = thread::spawn(move || {
+            let mut server = MockServer::new(server_socket_path, vec![], server_calls);
+            server.run();
+        });
+
+        thread::sleep(Duration::from_secs(1));
+        assert_that(&client.get_exit_info()).is_ok();
+        {
+            let calls = calls.read().unwrap();
+            assert_that(&calls[0]).is_equal_to(&GetExitInfo);
+        }
+
+        assert_that(&client.stop(TaskSpec::All)).is_ok();
+        assert_that(&handle.join()).is_ok();
+    }
+}
+
+mod scale {
+    use super::{make_socket_name, MockServer};
+    use client::local::RestManagerClient;
+    use client::ManagerClient;
+    use server::RequestMessage::*;
+    use spectral::prelude::*;
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+    use std::time::Duration;
+    use tasks::TaskSpec;
+
+    #[test]
+    fn test_returns_err_if_no_server() {
+        let socket_path = make_socket_name("test_scale_err_if_no_server");
+        let client = RestManagerClient::at_path(socket_path);
+        assert_that(&client.scale("web=2".parse().unwrap())).is_err();
+    }
+
+    #[test]
+    fn test_sends_formation() {
+        let socket_path = make_socket_name("test_scale_sends_formation");
+        let server_socket_path = socket_path.clone();
+        let calls = Arc::new(RwLock::new(vec![]));
+        let server_calls = calls.clone();
+        let client = RestManagerClient::at_path(socket_path.clone());
+
+        let handle = thread::spawn(move || {
+            let mut server = MockServer::new(server_socket_path, vec![], server_calls);
+            server.run();
+        });
+
+        thread::sleep(Duration::from_secs(1));
+        assert_that(&client.scale("web=2".parse().unwrap())).is_ok();
+        let result = client.scale("missing=2".parse().unwrap());
+        assert_that(&result.unwrap_err().to_string())
+            .is_equal_to(String::from("Unable to scale missing."));
+        {
+            let calls = calls.read().unwrap();
+            assert_that(&calls[0]).is_equal_to(&Scale("web=2".parse().unwrap()));
+        }
+
+        assert_that(&client.stop(TaskSpec::All)).is_ok();
+        assert_that(&handle.join()).is_ok();
+    }
+}
+
 mod stop {
     use super::{make_socket_name, MockServer};
     use client::local::RestManagerClient;
@@ -242,4 +409,95 @@ mod stop {
         assert_that(&calls[0])
             .is_equal_to(&StopTasks(vec![String::from("web1"), String::from("web2")]));
     }
+
+    #[test]
+    fn test_sends_signal() {
+        let socket_path = make_socket_name("test_sends_signal");
+        let server_socket_path = socket_path.clone();
+        let calls = Arc::new(RwLock::new(vec![]));
+        let server_calls = calls.clone();
+        let client = RestManagerClient::at_path(socket_path.clone());
+        let workers = vec![];
+
+        let handle = thread::spawn(move || {
+            let mut server = MockServer::new(server_socket_path, workers, server_calls);
+            server.run();
+        });
+
+        thread::sleep(Duration::from_secs(1));
+        let tasks = TaskSpec::List(vec![String::from("web")]);
+        assert_that(&client.signal(tasks.clone(), 1)).is_ok();
+        assert_that(&handle.join()).is_ok();
+
+        let calls = calls.read().unwrap();
+        assert_that(&calls[0]).is_equal_to(&Signal(tasks, 1));
+    }
+
+    #[test]
+    fn test_sends_restart() {
+        let socket_path = make_socket_name("test_sends_restart");
+        let server_socket_path = socket_path.clone();
+        let calls = Arc::new(RwLock::new(vec![]));
+        let server_calls = calls.clone();
+        let client = RestManagerClient::at_path(socket_path.clone());
+        let workers = vec![];
+
+        let handle = thread::spawn(move || {
+            let mut server = MockServer::new(server_socket_path, workers, server_calls);
+            server.run();
+        });
+
+        thread::sleep(Duration::from_secs(1));
+        let tasks = TaskSpec::List(vec![String::from("web")]);
+        assert_that(&client.restart(tasks.clone())).is_ok();
+        assert_that(&handle.join()).is_ok();
+
+        let calls = calls.read().unwrap();
+        assert_that(&calls[0]).is_equal_to(&Restart(tasks));
+    }
+}
+
+mod get_logs {
+    use super::{make_socket_name, MockServer};
+    use client::local::RestManagerClient;
+    use client::ManagerClient;
+    use service::Service;
+    use spectral::prelude::*;
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+    use std::time::Duration;
+    use tasks::TaskSpec;
+
+    #[test]
+    fn test_returns_err_if_no_server() {
+        let socket_path = make_socket_name("test_get_logs_err_if_no_server");
+        let client = RestManagerClient::at_path(socket_path);
+        assert_that(&client.get_logs("web", 10)).is_err();
+    }
+
+    #[test]
+    fn test_returns_lines() {
+        let socket_path = make_socket_name("test_get_logs_returns_lines");
+        let server_socket_path = socket_path.clone();
+        let calls = Arc::new(RwLock::new(vec![]));
+        let client = RestManagerClient::at_path(socket_path.clone());
+        let workers = vec![Service::new("web", "spawn server")];
+
+        let handle = thread::spawn(move || {
+            let mut server = MockServer::new(server_socket_path, workers, calls);
+            server.run();
+        });
+
+        thread::sleep(Duration::from_secs(1));
+        assert_that(&client.get_logs("web", 2)).is_ok().is_equal_to(vec![
+            String::from("web line 0"),
+            String::from("web line 1"),
+        ]);
+        let result = client.get_logs("missing", 2);
+        assert_that(&result.unwrap_err().to_string())
+            .is_equal_to(String::from("There's no service named missing."));
+
+        assert_that(&client.stop(TaskSpec::All)).is_ok();
+        assert_that(&handle.join()).is_ok();
+    }
 }
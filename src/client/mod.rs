@@ -1,21 +1,42 @@
-use service::Service;
-use std::collections::HashMap;
+use chrono::prelude::*;
+use formation::Formation;
+use server::events::{Event, EventFilter};
+use server::{DaemonInfo, ProcessScale};
+use service::exit::ExitInfo;
+use service::worker::{WorkerState, WorkerStatus};
+use std::collections::BTreeMap;
 use tasks::TaskSpec;
 use Result;
 
+pub mod events;
+#[cfg(test)]
+pub mod fake;
 pub mod local;
 pub mod status;
 
 pub trait ManagerClient {
     fn is_running(&self) -> bool;
-    fn get_workers(&self) -> Result<Vec<Service>>;
+    fn get_info(&self) -> Result<DaemonInfo>;
+    fn get_workers(&self) -> Result<Vec<WorkerStatus>>;
+    fn get_exit_info(&self) -> Result<BTreeMap<String, ExitInfo>>;
     fn stop(&self, TaskSpec) -> Result<()>;
+    fn signal(&self, TaskSpec, i32) -> Result<()>;
+    fn pause(&self, TaskSpec) -> Result<()>;
+    fn resume(&self, TaskSpec) -> Result<()>;
+    fn restart(&self, TaskSpec) -> Result<()>;
+    fn get_logs(&self, &str, usize) -> Result<Vec<String>>;
+    fn get_scale(&self) -> Result<Vec<ProcessScale>>;
+    fn scale(&self, Formation) -> Result<()>;
+    /// Pass the server's events to `on_event` as they happen, until it returns `false` or the
+    /// server stops.
+    fn subscribe<F: FnMut(Event) -> bool>(&self, EventFilter, F) -> Result<()>;
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ManagerStatus {
     NotFound,
-    RunningTasks(HashMap<String, String>),
+    /// The server's tasks, and how many instances of each process type it's running.
+    RunningTasks(Vec<WorkerStatus>, Vec<ProcessScale>),
 }
 
 impl ManagerStatus {
@@ -24,10 +45,81 @@ impl ManagerStatus {
             ManagerStatus::NotFound => String::from(
                 "Straw-boss not running. Why don't you try `straw-boss start --daemon`",
             ),
-            ManagerStatus::RunningTasks(tasks) => tasks
-                .into_iter()
-                .map(|(k, v)| format!("{}: {}\n", &k, &v))
-                .fold(String::new(), |a, b| a + &b),
+            ManagerStatus::RunningTasks(tasks, scale) => {
+                let message = tasks
+                    .into_iter()
+                    .map(describe_task)
+                    .fold(String::new(), |a, b| a + &b);
+                if scale.is_empty() {
+                    return message;
+                }
+                scale.iter().fold(message + "scale:\n", |message, process| {
+                    message + &format!(
+                        "    {}: {} running, {} wanted\n",
+                        &process.name, process.running, process.desired
+                    )
+                })
+            }
         }
     }
 }
+
+fn describe_task(status: &WorkerStatus) -> String {
+    let service = &status.service;
+    let mut line = format!("{}: {}\n", &service.name, &service.command);
+    if let Some(ref cwd) = service.cwd {
+        line += &format!("    cwd: {}\n", cwd.display());
+    }
+    if let Some(ref user) = service.user {
+        line += &format!("    user: {}\n", user);
+    }
+    if let Some(ref group) = service.group {
+        line += &format!("    group: {}\n", group);
+    }
+    if let Some(ref schedule) = service.schedule {
+        line += &format!("    schedule: {}\n", schedule);
+    }
+    let limits = service
+        .limits
+        .to_annotations()
+        .into_iter()
+        .chain(service.cgroup.to_annotations())
+        .collect::<Vec<_>>();
+    if !limits.is_empty() {
+        line += &format!("    limits: {}\n", limits.join(" "));
+    }
+    match status.state {
+        WorkerState::Running => {}
+        WorkerState::Paused => line += "    state: paused\n",
+        WorkerState::Stopped => line += "    state: stopped\n",
+        WorkerState::Failed => line += "    state: failed\n",
+        WorkerState::Completed => line += "    state: completed\n",
+        WorkerState::Scheduled => line += "    state: scheduled\n",
+    }
+    if let Some(usage) = status.usage {
+        line += &format!("    usage: {}\n", usage.describe());
+    }
+    if let Some(next_run) = status.next_run {
+        let at: DateTime<Local> = next_run.into();
+        line += &format!("    next run: {}\n", at.format("%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(ref error) = status.error {
+        line += &format!("    error: {}\n", error);
+    }
+    if let Some(ref exit) = status.exit {
+        line += &format!("    exited: {}\n", exit.describe());
+        if !exit.stderr.is_empty() {
+            line += "    stderr:\n";
+            for stderr in &exit.stderr {
+                line += &format!("        {}\n", stderr);
+            }
+        }
+    }
+    if !status.runs.is_empty() {
+        line += "    runs:\n";
+        for run in &status.runs {
+            line += &format!("        {}\n", run.describe());
+        }
+    }
+    line
+}
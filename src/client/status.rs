@@ -1,18 +1,16 @@
 use client::{ManagerClient, ManagerStatus};
-use std::collections::HashMap;
 use Result;
 
 /// Query a daemonized server to get the status of all of the tasks it's running.
 pub fn status<C: ManagerClient>(client: &C) -> Result<ManagerStatus> {
     if client.is_running() {
-        client
+        let workers = client
             .get_workers()
-            .map(|ws| {
-                ws.into_iter()
-                    .map(|w| (w.name, w.command))
-                    .collect::<HashMap<String, String>>()
-            }).map(ManagerStatus::RunningTasks)
-            .map_err(|err| format_err!("Unable to query workers: {:?}", &err))
+            .map_err(|err| format_err!("Unable to query workers: {:?}", &err))?;
+        let scale = client
+            .get_scale()
+            .map_err(|err| format_err!("Unable to query scale: {:?}", &err))?;
+        Ok(ManagerStatus::RunningTasks(workers, scale))
     } else {
         Ok(ManagerStatus::NotFound)
     }
@@ -21,41 +19,22 @@ pub fn status<C: ManagerClient>(client: &C) -> Result<ManagerStatus> {
 #[cfg(test)]
 mod test {
     use super::status;
-    use client::ManagerClient;
+    use chrono::prelude::*;
+    use client::fake::FakeManagerClient;
+    use client::ManagerStatus;
+    use server::ProcessScale;
+    use service::exit::ExitInfo;
+    use service::usage::Usage;
+    use service::worker::{WorkerState, WorkerStatus};
     use service::Service;
     use spectral::prelude::*;
-    use std::collections::HashMap;
-    use tasks::TaskSpec;
-    use Result;
-
-    struct FakeManagerClient {
-        running: bool,
-        workers: Result<Vec<Service>>,
-    }
-
-    impl ManagerClient for FakeManagerClient {
-        fn is_running(&self) -> bool {
-            self.running
-        }
-
-        fn get_workers(&self) -> Result<Vec<Service>> {
-            match self.workers {
-                Ok(ref w) => Ok(w.clone()),
-                Err(ref e) => Err(format_err!("{:?}", &e)),
-            }
-        }
-
-        fn stop(&self, _task: TaskSpec) -> Result<()> {
-            unimplemented!()
-        }
-    }
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::time::Duration;
 
     #[test]
     fn test_no_response() {
-        let client = FakeManagerClient {
-            running: false,
-            workers: Err(format_err!("not running")),
-        };
+        let client = FakeManagerClient::not_running();
 
         let actual = status(&client);
         assert_that(&actual).is_ok();
@@ -63,16 +42,170 @@ mod test {
 
     #[test]
     fn test_gets_worker_list() {
-        let worker = Service::new("web", "run all the web");
-        let client = FakeManagerClient {
-            running: true,
-            workers: Ok(vec![worker]),
+        let worker = WorkerStatus::new(
+            Service::new("web", "run all the web"),
+            WorkerState::Running,
+        );
+        let scale = ProcessScale {
+            name: String::from("web"),
+            desired: 1,
+            running: 1,
         };
+        let mut client = FakeManagerClient::new(vec![worker.clone()]);
+        client.scale = vec![scale.clone()];
 
-        let mut task_map: HashMap<String, String> = HashMap::new();
-        task_map.insert("web".into(), "run all the web".into());
         let actual = status(&client);
 
-        assert_that(&actual).is_ok();
+        assert_that(&actual)
+            .is_ok()
+            .is_equal_to(ManagerStatus::RunningTasks(vec![worker], vec![scale]));
+    }
+
+    #[test]
+    fn test_message_shows_cwd() {
+        let mut worker = Service::new("web", "run all the web");
+        worker.cwd = Some(PathBuf::from("/srv/web"));
+        let worker = WorkerStatus::new(worker, WorkerState::Running);
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: run all the web\n    cwd: /srv/web\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_user() {
+        let mut worker = Service::new("web", "run all the web");
+        worker.user = Some(String::from("www-data"));
+        worker.group = Some(String::from("www"));
+        let worker = WorkerStatus::new(worker, WorkerState::Running);
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: run all the web\n    user: www-data\n    group: www\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_scale() {
+        let workers = vec![
+            WorkerStatus::new(Service::new("web", "run all the web"), WorkerState::Running),
+            WorkerStatus::new(Service::new("web.2", "run all the web"), WorkerState::Running),
+        ];
+        let scale = vec![ProcessScale {
+            name: String::from("web"),
+            desired: 3,
+            running: 2,
+        }];
+        let message = ManagerStatus::RunningTasks(workers, scale).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: run all the web\nweb.2: run all the web\nscale:\n    web: 2 running, 3 wanted\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_paused() {
+        let worker = WorkerStatus::new(
+            Service::new("web", "run all the web"),
+            WorkerState::Paused,
+        );
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: run all the web\n    state: paused\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_failed() {
+        let worker = WorkerStatus::failed(
+            Service::new("web", "run all the web"),
+            "Unable to run `run all the web`: No such file or directory",
+        );
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: run all the web\n    state: failed\n    error: Unable to run `run all the web`: \
+             No such file or directory\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_completed() {
+        let worker = WorkerStatus::new(
+            Service::new("migrate", "rake db:migrate"),
+            WorkerState::Completed,
+        );
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "migrate: rake db:migrate\n    state: completed\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_schedule() {
+        let mut service = Service::new("report", "send-report");
+        service.schedule = Some("@daily".parse().unwrap());
+        let mut worker = WorkerStatus::new(service, WorkerState::Scheduled);
+        let status = Command::new("sh").args(&["-c", "exit 0"]).status().unwrap();
+        let run = ExitInfo::new(&status, vec![]);
+        let next_run = Local.ymd(2018, 10, 2).and_hms(0, 0, 0);
+        worker.next_run = Some(next_run.into());
+        worker.runs = vec![run.clone(), run.clone()];
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(format!(
+            "report: send-report\n    schedule: @daily\n    state: scheduled\n    \
+             next run: 2018-10-02 00:00:00\n    runs:\n        {0}\n        {0}\n",
+            run.describe()
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_limits() {
+        let mut service = Service::new("web", "start web-server");
+        service.limits.set("nofile", "1024").unwrap();
+        service.limits.set_nice("5").unwrap();
+        let worker = WorkerStatus::new(service, WorkerState::Running);
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: start web-server\n    limits: limit.nofile=1024 nice=5\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_cgroup_usage() {
+        let mut service = Service::new("web", "start web-server");
+        service.cgroup.set("memory.max", "512M").unwrap();
+        let mut worker = WorkerStatus::new(service, WorkerState::Running);
+        worker.usage = Some(Usage {
+            memory: Some(3 << 20),
+            cpu: Some(Duration::from_millis(500)),
+        });
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(String::from(
+            "web: start web-server\n    limits: memory.max=512M\n    \
+             usage: 3.0M memory, 0.50s CPU\n",
+        ));
+    }
+
+    #[test]
+    fn test_message_shows_exit() {
+        let mut worker = WorkerStatus::new(Service::new("ls", "ls fixtures"), WorkerState::Stopped);
+        let status = Command::new("sh").args(&["-c", "exit 2"]).status().unwrap();
+        let exit = ExitInfo::new(&status, vec![String::from("ls: fixtures: No such file")]);
+        let exited = format!("    exited: {}\n", exit.describe());
+        worker.exit = Some(exit);
+        let message = ManagerStatus::RunningTasks(vec![worker], vec![]).get_message();
+
+        assert_that(&message).is_equal_to(
+            String::from("ls: ls fixtures\n    state: stopped\n")
+                + &exited
+                + "    stderr:\n        ls: fixtures: No such file\n",
+        );
     }
 }
@@ -0,0 +1,192 @@
+use client::local::RestManagerClient;
+use client::ManagerClient;
+use procfile::Procfile;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use Result;
+
+/// The name of the socket in a daemon's directory.
+const SOCKET_FILE: &str = "socket";
+
+/// The name of the PID file in a daemon's directory.
+const PID_FILE: &str = "pid";
+
+/// The directory that holds a directory for each daemon. This is `straw-boss` in
+/// `$XDG_RUNTIME_DIR`, or in the temp directory if that isn't set.
+pub fn runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir).join("straw-boss"),
+        _ => env::temp_dir().join(format!("straw-boss-{}", unsafe { ::libc::getuid() })),
+    }
+}
+
+/// Where the daemon for one `Procfile` keeps its socket and PID file. Each `Procfile` gets its own
+/// directory under the runtime directory, named for a hash of its canonical path, so projects
+/// don't step on each other.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DaemonPaths {
+    dir: PathBuf,
+}
+
+impl DaemonPaths {
+    /// The paths for the daemon running a `Procfile`, in `runtime_dir`.
+    pub fn for_procfile(procfile: &Procfile, runtime_dir: &Path) -> Result<DaemonPaths> {
+        let path = procfile.path();
+        let path = path.canonicalize().or_else(|_| {
+            env::current_dir()
+                .map(|cwd| cwd.join(path))
+                .map_err(|err| format_err!("Unable to get current directory: {:?}", &err))
+        })?;
+        let dir = runtime_dir.join(format!("{:016x}", hash(&path.to_string_lossy())));
+        Ok(DaemonPaths { dir })
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        self.dir.join(SOCKET_FILE)
+    }
+
+    pub fn pid_file(&self) -> PathBuf {
+        self.dir.join(PID_FILE)
+    }
+}
+
+/// Create a daemon's directory. Only the current user can get into it.
+pub fn create_dir(dir: &Path) -> Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|err| format_err!("Unable to create {:?}: {:?}", &dir, &err))
+}
+
+/// FNV-1a. This is used instead of `DefaultHasher` because the directory names need to stay the
+/// same across builds.
+fn hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Write out every daemon with a socket in `runtime_dir`, with the `Procfile` it's running and its
+/// process ID. Sockets left behind by daemons that aren't answering are listed as not responding.
+pub fn list_daemons<W: Write>(runtime_dir: &Path, writer: &mut W) -> Result<()> {
+    if !runtime_dir.exists() {
+        return Ok(());
+    }
+
+    let mut sockets = fs::read_dir(runtime_dir)
+        .map_err(|err| format_err!("Unable to read {:?}: {:?}", &runtime_dir, &err))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(SOCKET_FILE))
+        .filter(|socket| socket.exists())
+        .collect::<Vec<_>>();
+    sockets.sort();
+
+    for socket in sockets {
+        let client = RestManagerClient::at_path(socket.clone());
+        let line = match client.get_info() {
+            Ok(info) => format!(
+                "{} (pid {})\n    socket: {}\n",
+                info.procfile.display(),
+                info.pid,
+                socket.display()
+            ),
+            Err(_) => format!("{}: not responding\n", socket.display()),
+        };
+        writer
+            .write_all(line.as_bytes())
+            .map_err(|err| format_err!("Unable to write output: {:?}", &err))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    mod for_procfile {
+        use daemons::DaemonPaths;
+        use procfile::Procfile;
+        use spectral::prelude::*;
+        use std::path::Path;
+
+        fn paths(procfile: &str) -> DaemonPaths {
+            let runtime_dir = Path::new("/run/user/1000/straw-boss");
+            DaemonPaths::for_procfile(&Procfile::new(procfile.into()), runtime_dir).unwrap()
+        }
+
+        #[test]
+        fn test_same_procfile_same_paths() {
+            let a = paths("fixtures/Procfile");
+            let b = paths("fixtures/../fixtures/Procfile");
+            assert_that(&a).is_equal_to(b);
+        }
+
+        #[test]
+        fn test_different_procfiles_different_paths() {
+            let a = paths("fixtures/Procfile");
+            let b = paths("fixtures/env/Procfile");
+            assert_that(&a.socket_path()).is_not_equal_to(b.socket_path());
+            assert_that(&a.socket_path().starts_with("/run/user/1000/straw-boss")).is_true();
+        }
+    }
+
+    mod list_daemons {
+        use client::local::RestManagerClient;
+        use client::ManagerClient;
+        use daemons::{create_dir, list_daemons, DaemonPaths};
+        use procfile::Procfile;
+        use server::local::RestManagerServer;
+        use server::{ManagerServer, ServerOptions};
+        use spectral::prelude::*;
+        use std::env;
+        use std::fs;
+        use std::os::unix::net::UnixListener;
+        use std::process;
+        use std::thread;
+        use std::time::Duration;
+        use tasks::TaskSpec;
+
+        #[test]
+        fn test_lists_running_and_stale_daemons() {
+            let runtime_dir = env::temp_dir().join(format!("straw-boss.list.{}", process::id()));
+            if runtime_dir.exists() {
+                fs::remove_dir_all(&runtime_dir).unwrap();
+            }
+
+            let procfile = Procfile::new("fixtures/Procfile".into());
+            let paths = DaemonPaths::for_procfile(&procfile, &runtime_dir).unwrap();
+            let socket_path = paths.socket_path();
+            create_dir(socket_path.parent().unwrap()).unwrap();
+            let handle = thread::spawn(move || {
+                let options = ServerOptions {
+                    procfile: Some("fixtures/Procfile".into()),
+                    ..ServerOptions::default()
+                };
+                let mut server = RestManagerServer::with_options(socket_path, options);
+                server.start_server().unwrap();
+            });
+
+            let stale = runtime_dir.join("stale");
+            fs::create_dir_all(&stale).unwrap();
+            drop(UnixListener::bind(stale.join("socket")).unwrap());
+
+            thread::sleep(Duration::from_secs(1));
+            let mut output = Vec::new();
+            assert_that(&list_daemons(&runtime_dir, &mut output)).is_ok();
+            let output = String::from_utf8(output).unwrap();
+
+            let running = format!("fixtures/Procfile (pid {})", process::id());
+            let stale = format!("{}: not responding", stale.join("socket").display());
+            assert_that(&output).contains(running.as_str());
+            assert_that(&output).contains(stale.as_str());
+
+            let client = RestManagerClient::at_path(paths.socket_path());
+            client.stop(TaskSpec::All).unwrap();
+            handle.join().unwrap();
+            fs::remove_dir_all(&runtime_dir).unwrap();
+        }
+    }
+}
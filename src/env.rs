@@ -0,0 +1,349 @@
+use procfile::Procfile;
+use service::Service;
+use shellwords;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::path::PathBuf;
+use Result;
+
+/// The port given to the first service. Each following service gets the next hundred.
+pub const BASE_PORT: u16 = 5000;
+
+/// The name of the environment file that's read from the `Procfile`'s directory when no others
+/// are given.
+pub const DEFAULT_ENV_FILE: &str = ".env";
+
+/// A file of `KEY=value` environment variable settings. This is a newtype for a `PathBuf`.
+#[derive(Debug, Clone)]
+pub struct EnvFile(PathBuf);
+
+impl EnvFile {
+    /// Create a new `EnvFile` from a `PathBuf`.
+    pub fn new(env_file: PathBuf) -> EnvFile {
+        EnvFile(env_file)
+    }
+
+    /// Read the variables defined in the file, in order.
+    pub fn read_vars(&self) -> Result<Vec<(String, String)>> {
+        let &EnvFile(ref env_file) = self;
+        let f = File::open(&env_file)
+            .map_err(|err| format_err!("Unable to open env file: {:?}\n{}", &env_file, &err))?;
+        parse_env(f).map_err(|err| {
+            format_err!(
+                "Unable to read data from env file: {:?}\n{}",
+                &env_file,
+                &err
+            )
+        })
+    }
+}
+
+/// Parse the contents of an env file.
+///
+/// Blank lines and lines starting with `#` are skipped, and a leading `export` is allowed. Values
+/// can be wrapped in single or double quotes. Double-quoted values understand `\n` escapes.
+///
+/// # Example
+///
+/// ```rust
+/// use straw_boss::env::parse_env;
+///
+/// let input = b"# settings\nRACK_ENV=development\nexport GREETING=\"hello, world\"\n";
+/// let vars = parse_env(&input[..]).unwrap();
+/// assert_eq!(vars, vec![
+///     (String::from("RACK_ENV"), String::from("development")),
+///     (String::from("GREETING"), String::from("hello, world")),
+/// ]);
+/// ```
+pub fn parse_env<R: io::Read>(input: R) -> Result<Vec<(String, String)>> {
+    io::BufReader::new(input)
+        .lines()
+        .filter_map(|result| result.ok())
+        .filter(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        }).map(|line| parse_env_line(&line))
+        .collect()
+}
+
+fn parse_env_line(line: &str) -> Result<(String, String)> {
+    let line = line.trim();
+    let line = if line.starts_with("export ") {
+        line["export ".len()..].trim_left()
+    } else {
+        line
+    };
+    let mut parts = line.splitn(2, '=');
+    let key = parts
+        .next()
+        .map(|key| key.trim())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| format_err!("Invalid env line: {:?}", &line))?;
+    let value = parts
+        .next()
+        .ok_or_else(|| format_err!("Invalid env line: {:?}", &line))?
+        .trim();
+
+    let value = if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        String::from(&value[1..value.len() - 1])
+    } else if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1]
+            .replace("\\n", "\n")
+            .replace("\\\"", "\"")
+    } else {
+        String::from(value)
+    };
+
+    Ok((String::from(key), value))
+}
+
+/// Give each service its environment. Every service gets the variables from the env files, and
+/// its own settings override those. Unless it's already set, `PORT` is assigned from `base_port`,
+/// with each service getting the next hundred ports.
+///
+/// # Example
+///
+/// ```rust
+/// use straw_boss::env::{resolve, BASE_PORT};
+/// use straw_boss::service::Service;
+///
+/// let services = vec![Service::new("web", "start web"), Service::new("worker", "start worker")];
+/// let vars = vec![(String::from("RACK_ENV"), String::from("test"))];
+/// let services = resolve(services, &vars, BASE_PORT);
+///
+/// assert_eq!(Some(&String::from("5000")), services[0].env.get("PORT"));
+/// assert_eq!(Some(&String::from("5100")), services[1].env.get("PORT"));
+/// assert_eq!(Some(&String::from("test")), services[1].env.get("RACK_ENV"));
+/// ```
+pub fn resolve(services: Vec<Service>, vars: &[(String, String)], base_port: u16) -> Vec<Service> {
+    services
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut service)| {
+            let mut env = vars.iter().cloned().collect::<BTreeMap<String, String>>();
+            env.extend(service.env.into_iter());
+            env.entry(String::from("PORT"))
+                .or_insert_with(|| (u32::from(base_port) + 100 * i as u32).to_string());
+            service.env = env;
+            service
+        }).collect()
+}
+
+/// Read the environment variables from all of the env files, in order, so that later files
+/// override earlier ones. If no files are given, the `.env` in the `Procfile`'s directory is used
+/// if it's there.
+pub fn read_vars(procfile: &Procfile, env_files: &[EnvFile]) -> Result<Vec<(String, String)>> {
+    if env_files.is_empty() {
+        let default = procfile.directory().join(DEFAULT_ENV_FILE);
+        if default.exists() {
+            return EnvFile::new(default).read_vars();
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut vars = Vec::new();
+    for env_file in env_files {
+        vars.extend(env_file.read_vars()?);
+    }
+    Ok(vars)
+}
+
+/// Read the services from the `Procfile` and give them their environments.
+pub fn read_services(procfile: &Procfile, env_files: &[EnvFile]) -> Result<Vec<Service>> {
+    let vars = read_vars(procfile, env_files)?;
+    let services = procfile.read_services()?;
+    Ok(resolve(services, &vars, BASE_PORT))
+}
+
+/// Expand `$NAME` and `${NAME}` references in a command line, before it's split into words.
+/// Variables are looked up in `vars` first and then in straw boss's own environment. References
+/// in single quotes, escaped with a backslash, or to variables that aren't set are left as they
+/// are, so that a shell that the command runs can expand them itself. Like the shell, a value
+/// stays one word in double quotes and is split on whitespace outside of them.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use straw_boss::env::expand;
+///
+/// let mut vars = BTreeMap::new();
+/// vars.insert(String::from("PORT"), String::from("5000"));
+/// vars.insert(String::from("GREETING"), String::from("hello \"world\""));
+///
+/// assert_eq!("--port=5000", expand("--port=$PORT", &vars));
+/// assert_eq!("5000s", expand("${PORT}s", &vars));
+/// assert_eq!("echo \"hello \\\"world\\\"\"", expand("echo \"$GREETING\"", &vars));
+/// assert_eq!("sh -c 'echo $PORT'", expand("sh -c 'echo $PORT'", &vars));
+/// assert_eq!("$", expand("$", &vars));
+/// assert_eq!("exit $1", expand("exit $1", &vars));
+/// ```
+pub fn expand(command: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut expanded = String::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+    let mut single_quoted = false;
+    let mut double_quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if !double_quoted => single_quoted = !single_quoted,
+            '"' if !single_quoted => double_quoted = !double_quoted,
+            '\\' if !single_quoted => {
+                expanded.push(c);
+                if let Some(next) = chars.next() {
+                    expanded.push(next);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if c != '$' || single_quoted {
+            expanded.push(c);
+            continue;
+        }
+
+        let mut reference = String::from("$");
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+            reference.push('{');
+        }
+        let mut name = String::new();
+        match chars.peek() {
+            Some(&next) if next.is_ascii_alphabetic() || next == '_' => {}
+            _ => {
+                expanded.push_str(&reference);
+                continue;
+            }
+        }
+        while let Some(&next) = chars.peek() {
+            if next.is_ascii_alphanumeric() || next == '_' {
+                name.push(next);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        reference.push_str(&name);
+        if braced && chars.peek() == Some(&'}') {
+            chars.next();
+            reference.push('}');
+        }
+
+        match vars.get(&name).cloned().or_else(|| env::var(&name).ok()) {
+            Some(ref value) if double_quoted => {
+                for c in value.chars() {
+                    if "\"\\$`".contains(c) {
+                        expanded.push('\\');
+                    }
+                    expanded.push(c);
+                }
+            }
+            Some(value) => {
+                let words = value.split_whitespace().map(shellwords::escape);
+                expanded.push_str(&words.collect::<Vec<_>>().join(" "));
+            }
+            None => expanded.push_str(&reference),
+        }
+    }
+
+    expanded
+}
+
+#[cfg(test)]
+mod test {
+    mod parse_env {
+        use env::parse_env;
+        use spectral::prelude::*;
+
+        #[test]
+        fn test_skips_blank_lines_and_comments() {
+            let input = b"\n# comment\nA=1\n\n";
+            assert_that(&parse_env(&input[..]))
+                .is_ok()
+                .is_equal_to(vec![(String::from("A"), String::from("1"))]);
+        }
+
+        #[test]
+        fn test_strips_quotes() {
+            let input = b"A='single $quoted'\nB=\"two\\nlines\"\n";
+            assert_that(&parse_env(&input[..])).is_ok().is_equal_to(vec![
+                (String::from("A"), String::from("single $quoted")),
+                (String::from("B"), String::from("two\nlines")),
+            ]);
+        }
+
+        #[test]
+        fn test_keeps_equals_in_values() {
+            let input = b"DATABASE_URL=postgres://db?sslmode=disable\n";
+            assert_that(&parse_env(&input[..])).is_ok().is_equal_to(vec![(
+                String::from("DATABASE_URL"),
+                String::from("postgres://db?sslmode=disable"),
+            )]);
+        }
+
+        #[test]
+        fn test_errors_without_equals() {
+            let input = b"JUST_A_NAME\n";
+            assert_that(&parse_env(&input[..])).is_err();
+        }
+    }
+
+    mod resolve {
+        use env::resolve;
+        use service::Service;
+        use spectral::prelude::*;
+
+        #[test]
+        fn test_keeps_service_port() {
+            let mut service = Service::new("web", "start web");
+            service.env.insert(String::from("PORT"), String::from("8080"));
+            let services = resolve(vec![service], &[], 5000);
+            assert_that(&services[0].env.get("PORT"))
+                .is_some()
+                .is_equal_to(&String::from("8080"));
+        }
+
+        #[test]
+        fn test_service_env_overrides_files() {
+            let mut service = Service::new("web", "start web");
+            service.env.insert(String::from("MODE"), String::from("web"));
+            let vars = vec![(String::from("MODE"), String::from("file"))];
+            let services = resolve(vec![service], &vars, 5000);
+            assert_that(&services[0].env.get("MODE"))
+                .is_some()
+                .is_equal_to(&String::from("web"));
+        }
+    }
+
+    mod read_services {
+        use env::{read_services, EnvFile};
+        use procfile::Procfile;
+        use spectral::prelude::*;
+
+        #[test]
+        fn test_reads_default_env_file() {
+            let procfile = Procfile::new("fixtures/env/Procfile".into());
+            let services = read_services(&procfile, &[]).unwrap();
+            assert_that(&services[0].env.get("GREETING"))
+                .is_some()
+                .is_equal_to(&String::from("hello from .env"));
+        }
+
+        #[test]
+        fn test_later_env_files_override() {
+            let procfile = Procfile::new("fixtures/env/Procfile".into());
+            let env_files = vec![
+                EnvFile::new("fixtures/env/.env".into()),
+                EnvFile::new("fixtures/env/other.env".into()),
+            ];
+            let services = read_services(&procfile, &env_files).unwrap();
+            assert_that(&services[0].env.get("GREETING"))
+                .is_some()
+                .is_equal_to(&String::from("hello from other.env"));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use export::{user, ExportFile, ExportOptions, Exporter};
+use serde_yaml;
+use service::Service;
+use std::collections::BTreeMap;
+use Result;
+
+/// The version of the compose file format that's written.
+const COMPOSE_VERSION: &str = "3";
+
+/// Exports a skeleton `docker-compose.yml`.
+pub struct DockerCompose;
+
+#[derive(Debug, Serialize)]
+struct ComposeFile {
+    version: String,
+    services: BTreeMap<String, ComposeService>,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeService {
+    build: String,
+    command: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    environment: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<String>,
+    restart: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+impl Exporter for DockerCompose {
+    /// Render a compose file with a service for each instance, named `<service>-<number>`. Every
+    /// service builds from the current directory and publishes its `PORT`. This is a starting
+    /// point: images, volumes and the like are left to fill in. Commands and environment values
+    /// are escaped so that Compose leaves their variables for the container's shell.
+    fn render(&self, services: &[Service], options: &ExportOptions) -> Result<Vec<ExportFile>> {
+        let services = options
+            .formation
+            .instances(services)
+            .into_iter()
+            .map(|instance| {
+                let name = format!("{}-{}", &instance.service.name, instance.number);
+                let ports = instance
+                    .service
+                    .env
+                    .get("PORT")
+                    .map(|port| vec![format!("{}:{}", port, port)])
+                    .unwrap_or_else(Vec::new);
+                let service = ComposeService {
+                    build: String::from("."),
+                    command: vec![
+                        String::from("/bin/sh"),
+                        String::from("-c"),
+                        escape(&instance.service.command),
+                    ],
+                    environment: instance
+                        .service
+                        .env
+                        .iter()
+                        .map(|(key, value)| (key.clone(), escape(value)))
+                        .collect(),
+                    ports,
+                    restart: String::from(options.restart.for_service(&instance.service).name()),
+                    user: compose_user(&instance.service, options),
+                };
+                (name, service)
+            }).collect();
+        let compose = ComposeFile {
+            version: String::from(COMPOSE_VERSION),
+            services,
+        };
+
+        let yaml = serde_yaml::to_string(&compose)
+            .map_err(|err| format_err!("Cannot convert services to YAML: {}", &err))?;
+        Ok(vec![ExportFile::new("docker-compose.yml", yaml)])
+    }
+}
+
+/// The service's `user`, with its group if it has both. Compose can't set a group on its own.
+fn compose_user(service: &Service, options: &ExportOptions) -> Option<String> {
+    user(service, options).map(|user| match service.group {
+        Some(ref group) => format!("{}:{}", user, group),
+        None => String::from(user),
+    })
+}
+
+/// Escape `$`, which Compose would otherwise fill in from the environment it's run in.
+fn escape(value: &str) -> String {
+    value.replace('$', "$$")
+}
@@ -0,0 +1,261 @@
+use failure::Error;
+use formation::Formation;
+use procfile::Procfile;
+use service::{Service, ServiceKind};
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use Result;
+
+pub mod compose;
+pub mod runit;
+pub mod supervisord;
+pub mod systemd;
+
+/// The names of the formats that can be exported to.
+pub const FORMATS: &[&str] = &["systemd", "supervisord", "runit", "docker-compose"];
+
+/// Something that renders services in another process manager's format.
+pub trait Exporter {
+    /// Render the services into the files that make up the export.
+    fn render(&self, services: &[Service], options: &ExportOptions) -> Result<Vec<ExportFile>>;
+}
+
+/// Get the exporter for a format. The names are the ones in `FORMATS`.
+pub fn exporter(format: &str) -> Result<Box<dyn Exporter>> {
+    match format {
+        "systemd" => Ok(Box::new(systemd::Systemd)),
+        "supervisord" => Ok(Box::new(supervisord::Supervisord)),
+        "runit" => Ok(Box::new(runit::Runit)),
+        "docker-compose" => Ok(Box::new(compose::DockerCompose)),
+        _ => Err(format_err!("Unknown export format: {:?}", &format)),
+    }
+}
+
+/// Settings for exporting services to another process manager's format.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// The application name. This prefixes the names of everything that's exported.
+    pub app: String,
+    /// How many instances of each service to export.
+    pub formation: Formation,
+    /// The user to run the services as, unless they say otherwise.
+    pub user: Option<String>,
+    /// A directory to write the services' logs to. Without this, the logs go wherever the
+    /// process manager sends them by default.
+    pub log_dir: Option<PathBuf>,
+    /// When to restart services that exit.
+    pub restart: RestartPolicy,
+}
+
+impl ExportOptions {
+    /// Create options for an application with the defaults for everything else.
+    pub fn new(app: &str) -> ExportOptions {
+        ExportOptions {
+            app: String::from(app),
+            formation: Formation::new(),
+            user: None,
+            log_dir: None,
+            restart: RestartPolicy::Always,
+        }
+    }
+}
+
+/// When to restart a service after it exits.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RestartPolicy {
+    Always,
+    OnFailure,
+    Never,
+}
+
+impl RestartPolicy {
+    /// The policy's name, as it's given on the command line. systemd and Compose use the same
+    /// names.
+    pub fn name(self) -> &'static str {
+        match self {
+            RestartPolicy::Always => "always",
+            RestartPolicy::OnFailure => "on-failure",
+            RestartPolicy::Never => "no",
+        }
+    }
+
+    /// The policy for one service. One-shot tasks run to completion once, so they're never
+    /// restarted.
+    pub fn for_service(self, service: &Service) -> RestartPolicy {
+        match service.kind {
+            ServiceKind::Oneshot => RestartPolicy::Never,
+            ServiceKind::Service => self,
+        }
+    }
+}
+
+impl FromStr for RestartPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<RestartPolicy> {
+        match s {
+            "always" => Ok(RestartPolicy::Always),
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            "no" | "never" => Ok(RestartPolicy::Never),
+            _ => Err(format_err!("Unknown restart policy: {:?}", &s)),
+        }
+    }
+}
+
+/// A file produced by an exporter.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ExportFile {
+    /// The path to the file, relative to the export directory.
+    pub path: PathBuf,
+    /// What goes in the file.
+    pub contents: String,
+    /// Whether the file is a script that needs to be executable.
+    pub executable: bool,
+}
+
+impl ExportFile {
+    pub fn new<P: AsRef<Path>>(path: P, contents: String) -> ExportFile {
+        ExportFile {
+            path: path.as_ref().to_path_buf(),
+            contents,
+            executable: false,
+        }
+    }
+
+    pub fn script<P: AsRef<Path>>(path: P, contents: String) -> ExportFile {
+        ExportFile {
+            executable: true,
+            ..ExportFile::new(path, contents)
+        }
+    }
+}
+
+/// The application name to use when none is given. This is the name of the directory the
+/// `Procfile` is in.
+pub fn default_app_name(procfile: &Procfile) -> String {
+    procfile
+        .directory()
+        .canonicalize()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| String::from("app"))
+}
+
+/// Make the services' working directories absolute, resolving relative ones against `base`. The
+/// exported services are run from wherever the process manager runs them, and systemd doesn't
+/// accept relative paths at all.
+pub fn absolute_cwds(services: &[Service], base: &Path) -> Vec<Service> {
+    services
+        .iter()
+        .cloned()
+        .map(|mut service| {
+            service.cwd = service.cwd.map(|cwd| normalize(&base.join(cwd)));
+            service
+        }).collect()
+}
+
+/// The user to run a service as: its own, or the one in the options.
+fn user<'a>(service: &'a Service, options: &'a ExportOptions) -> Option<&'a str> {
+    service
+        .user
+        .as_ref()
+        .or(options.user.as_ref())
+        .map(|user| user.as_str())
+}
+
+/// Escape `%`, which systemd would otherwise expand as a specifier and supervisord would use for
+/// string interpolation.
+fn escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Escape a value to go between double quotes in a systemd unit or a supervisord configuration
+/// file.
+fn quote(value: &str) -> String {
+    escape(&value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Take the `.` and `..` out of an absolute path, without looking at the file system.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .fold(PathBuf::new(), |mut normalized, component| {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component.as_os_str()),
+            }
+            normalized
+        })
+}
+
+/// Render the services in the named format. Scheduled services are skipped, because the formats
+/// only run services all the time, and one that's meant to run now and then would be restarted
+/// each time it exits.
+///
+/// # Returns
+///
+/// The rendered files, and warnings about the services that were skipped.
+pub fn render(
+    format: &str,
+    services: &[Service],
+    options: &ExportOptions,
+) -> Result<(Vec<ExportFile>, Vec<String>)> {
+    let (scheduled, services): (Vec<Service>, Vec<Service>) = services
+        .iter()
+        .cloned()
+        .partition(|service| service.schedule.is_some());
+    let warnings = scheduled
+        .iter()
+        .map(|service| format!("{}: skipped because it runs on a schedule", &service.name))
+        .collect();
+    let files = exporter(format)?.render(&services, options)?;
+    Ok((files, warnings))
+}
+
+/// Render the services in the named format and write the files into a directory. The directory
+/// is created if it's missing. The services' working directories are resolved against the current
+/// directory first.
+///
+/// # Returns
+///
+/// The warnings about services that couldn't be exported.
+pub fn export<P: AsRef<Path>>(
+    format: &str,
+    services: &[Service],
+    options: &ExportOptions,
+    dir: P,
+) -> Result<Vec<String>> {
+    let dir = dir.as_ref();
+    let base = env::current_dir()
+        .and_then(|base| base.canonicalize())
+        .map_err(|err| format_err!("Cannot get current directory: {:?}", &err))?;
+    let services = absolute_cwds(services, &base);
+    let (files, warnings) = render(format, &services, options)?;
+
+    fs::create_dir_all(dir)
+        .map_err(|err| format_err!("Unable to create directory {:?}: {:?}", &dir, &err))?;
+    for file in files {
+        let path = dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| {
+                format_err!("Unable to create directory {:?}: {:?}", &parent, &err)
+            })?;
+        }
+        fs::write(&path, file.contents.as_bytes())
+            .map_err(|err| format_err!("Unable to write {:?}: {:?}", &path, &err))?;
+        if file.executable {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .map_err(|err| format_err!("Unable to make {:?} executable: {:?}", &path, &err))?;
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,91 @@
+use export::{user, ExportFile, ExportOptions, Exporter, RestartPolicy};
+use formation::Instance;
+use service::Service;
+use std::path::PathBuf;
+use Result;
+
+/// Exports runit service directories.
+pub struct Runit;
+
+impl Exporter for Runit {
+    /// Render a service directory for each instance, named `<app>-<service>-<number>`. Each has a
+    /// `run` script, a `log/run` script, and a `finish` script if the service shouldn't always
+    /// be restarted.
+    fn render(&self, services: &[Service], options: &ExportOptions) -> Result<Vec<ExportFile>> {
+        let mut files = Vec::new();
+
+        for instance in options.formation.instances(services) {
+            let dir = PathBuf::from(format!(
+                "{}-{}-{}",
+                &options.app, &instance.service.name, instance.number
+            ));
+            files.push(ExportFile::script(
+                dir.join("run"),
+                render_run(&instance, options),
+            ));
+            files.push(ExportFile::script(
+                dir.join("log").join("run"),
+                render_log(&instance, options),
+            ));
+            if let Some(finish) = render_finish(options.restart.for_service(&instance.service)) {
+                files.push(ExportFile::script(dir.join("finish"), finish));
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+fn render_run(instance: &Instance, options: &ExportOptions) -> String {
+    let service = &instance.service;
+
+    let mut run = String::from("#!/bin/sh\nexec 2>&1\n");
+    if let Some(ref cwd) = service.cwd {
+        run += &format!("cd {}\n", quote(&cwd.to_string_lossy()));
+    }
+    for (key, value) in &service.env {
+        run += &format!("export {}={}\n", key, quote(value));
+    }
+    // chpst needs a user to switch to, so a service's group only counts with one.
+    let chpst = match (user(service, options), service.group.as_ref()) {
+        (Some(user), Some(group)) => format!("chpst -u {} ", quote(&format!("{}:{}", user, group))),
+        (Some(user), None) => format!("chpst -u {} ", quote(user)),
+        (None, _) => String::new(),
+    };
+    run += &format!("exec {}/bin/sh -c {}\n", chpst, quote(&service.command));
+
+    run
+}
+
+fn render_log(instance: &Instance, options: &ExportOptions) -> String {
+    match options.log_dir {
+        Some(ref log_dir) => {
+            let log_dir = quote(&log_dir.join(instance.name()).to_string_lossy());
+            format!(
+                "#!/bin/sh\nmkdir -p {dir}\nexec svlogd -tt {dir}\n",
+                dir = &log_dir
+            )
+        }
+        None => format!(
+            "#!/bin/sh\nexec logger -t {}\n",
+            quote(&format!("{}-{}", &options.app, instance.name()))
+        ),
+    }
+}
+
+/// runit always restarts a service when it exits. The `finish` script stops that by telling
+/// runit to leave the service down.
+fn render_finish(policy: RestartPolicy) -> Option<String> {
+    match policy {
+        RestartPolicy::Always => None,
+        RestartPolicy::OnFailure => Some(String::from(
+            "#!/bin/sh\nif [ \"$1\" = 0 ]; then\n    exec sv once .\nfi\n",
+        )),
+        RestartPolicy::Never => Some(String::from("#!/bin/sh\nexec sv once .\n")),
+    }
+}
+
+/// Quote a value for the shell.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -0,0 +1,92 @@
+use export::{escape, quote, user, ExportFile, ExportOptions, Exporter, RestartPolicy};
+use formation::Instance;
+use service::{Service, ServiceKind};
+use Result;
+
+/// Exports a supervisord configuration file.
+pub struct Supervisord;
+
+impl Exporter for Supervisord {
+    /// Render one `<app>.conf` with a `[program:...]` section for each instance and a `[group:...]`
+    /// section that ties them together.
+    fn render(&self, services: &[Service], options: &ExportOptions) -> Result<Vec<ExportFile>> {
+        let instances = options.formation.instances(services);
+
+        let mut conf = instances
+            .iter()
+            .map(|instance| render_program(instance, options))
+            .collect::<Vec<String>>();
+        conf.push(format!(
+            "[group:{}]\nprograms={}\n",
+            &options.app,
+            instances
+                .iter()
+                .map(|instance| program_name(instance, options))
+                .collect::<Vec<String>>()
+                .join(",")
+        ));
+
+        Ok(vec![ExportFile::new(
+            format!("{}.conf", &options.app),
+            conf.join("\n"),
+        )])
+    }
+}
+
+fn program_name(instance: &Instance, options: &ExportOptions) -> String {
+    format!("{}-{}", &options.app, instance.name())
+}
+
+fn render_program(instance: &Instance, options: &ExportOptions) -> String {
+    let service = &instance.service;
+
+    let mut program = format!("[program:{}]\n", program_name(instance, options));
+    program += &format!("command=/bin/sh -c \"{}\"\n", quote(&service.command));
+    if let Some(ref cwd) = service.cwd {
+        program += &format!("directory={}\n", escape(&cwd.to_string_lossy()));
+    }
+    if !service.env.is_empty() {
+        let environment = service
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, quote(value)))
+            .collect::<Vec<String>>()
+            .join(",");
+        program += &format!("environment={}\n", environment);
+    }
+    // supervisord runs a program with its user's own group, so there's no setting for a service's
+    // group.
+    if let Some(user) = user(service, options) {
+        program += &format!("user={}\n", user);
+    }
+    program += "autostart=true\n";
+    program += &format!(
+        "autorestart={}\n",
+        autorestart(options.restart.for_service(service))
+    );
+    // A one-shot is meant to exit, so exiting right away doesn't count as failing to start.
+    if service.kind == ServiceKind::Oneshot {
+        program += "startsecs=0\n";
+    }
+    program += "stopsignal=TERM\n";
+    program += "stopasgroup=true\n";
+    program += "killasgroup=true\n";
+    if let Some(ref log_dir) = options.log_dir {
+        let log = |suffix: &str| {
+            let log = log_dir.join(format!("{}{}.log", instance.name(), suffix));
+            escape(&log.to_string_lossy())
+        };
+        program += &format!("stdout_logfile={}\n", log(""));
+        program += &format!("stderr_logfile={}\n", log(".error"));
+    }
+
+    program
+}
+
+fn autorestart(policy: RestartPolicy) -> &'static str {
+    match policy {
+        RestartPolicy::Always => "true",
+        RestartPolicy::OnFailure => "unexpected",
+        RestartPolicy::Never => "false",
+    }
+}
@@ -0,0 +1,127 @@
+use export::{escape, quote, user, ExportFile, ExportOptions, Exporter};
+use formation::Instance;
+use service::{Service, ServiceKind};
+use Result;
+
+/// Exports systemd units.
+pub struct Systemd;
+
+impl Exporter for Systemd {
+    /// Render a `.target` for the application and a `.service` unit for each instance. Starting
+    /// the target starts every service, and each service is `PartOf` the target so that stopping
+    /// it stops them all. One-shot tasks are `oneshot` units, and the other services wait for them
+    /// to finish successfully.
+    fn render(&self, services: &[Service], options: &ExportOptions) -> Result<Vec<ExportFile>> {
+        let instances = options.formation.instances(services);
+        let target = format!("{}.target", &options.app);
+        let oneshots = instances
+            .iter()
+            .filter(|instance| instance.service.kind == ServiceKind::Oneshot)
+            .map(|instance| unit_name(instance, options))
+            .collect::<Vec<String>>();
+
+        let mut files = vec![ExportFile::new(&target, render_target(&instances, options))];
+        files.extend(instances.iter().map(|instance| {
+            ExportFile::new(
+                unit_name(instance, options),
+                render_service(instance, &target, &oneshots, options),
+            )
+        }));
+
+        Ok(files)
+    }
+}
+
+fn unit_name(instance: &Instance, options: &ExportOptions) -> String {
+    format!("{}-{}.service", &options.app, instance.name())
+}
+
+fn render_target(instances: &[Instance], options: &ExportOptions) -> String {
+    let wants = instances
+        .iter()
+        .map(|instance| unit_name(instance, options))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    format!(
+        "[Unit]\n\
+         Description={app}\n\
+         Wants={wants}\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        app = &options.app,
+        wants = &wants,
+    )
+}
+
+fn render_service(
+    instance: &Instance,
+    target: &str,
+    oneshots: &[String],
+    options: &ExportOptions,
+) -> String {
+    let name = format!("{}-{}", &options.app, instance.name());
+    let service = &instance.service;
+    let oneshot = service.kind == ServiceKind::Oneshot;
+
+    let mut unit = format!(
+        "[Unit]\n\
+         Description={app} {instance}\n\
+         PartOf={target}\n\
+         After=network.target\n",
+        app = &options.app,
+        instance = instance.name(),
+        target = target,
+    );
+    if !oneshot && !oneshots.is_empty() {
+        unit += &format!("After={}\n", oneshots.join(" "));
+        unit += &format!("Requires={}\n", oneshots.join(" "));
+    }
+    unit += "\n[Service]\n";
+    if oneshot {
+        // The unit stays active once the task's finished, so that what requires it can start.
+        unit += "Type=oneshot\n";
+        unit += "RemainAfterExit=yes\n";
+    } else {
+        unit += "Type=simple\n";
+    }
+    if let Some(user) = user(service, options) {
+        unit += &format!("User={}\n", user);
+    }
+    if let Some(ref group) = service.group {
+        unit += &format!("Group={}\n", group);
+    }
+    if let Some(ref cwd) = service.cwd {
+        unit += &format!("WorkingDirectory={}\n", escape(&cwd.to_string_lossy()));
+    }
+    for (key, value) in &service.env {
+        unit += &format!("Environment=\"{}={}\"\n", key, quote(value));
+    }
+    // `$` is doubled so that the shell expands the variables instead of systemd.
+    unit += &format!(
+        "ExecStart=/bin/sh -c \"{}\"\n",
+        quote(&service.command).replace('$', "$$")
+    );
+    // systemd doesn't restart `oneshot` units.
+    if !oneshot {
+        unit += &format!("Restart={}\n", options.restart.name());
+    }
+    unit += "KillMode=mixed\n";
+    unit += "TimeoutStopSec=5\n";
+    match options.log_dir {
+        Some(ref log_dir) => {
+            let log = log_dir.join(format!("{}.log", instance.name()));
+            let log = escape(&log.to_string_lossy());
+            unit += &format!("StandardOutput=append:{}\n", &log);
+            unit += &format!("StandardError=append:{}\n", &log);
+        }
+        None => {
+            unit += "StandardOutput=journal\n";
+            unit += "StandardError=journal\n";
+        }
+    }
+    unit += &format!("SyslogIdentifier={}\n", &name);
+
+    unit
+}
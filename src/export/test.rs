@@ -0,0 +1,378 @@
+use env;
+use export::{absolute_cwds, export, ExportFile, ExportOptions, RestartPolicy};
+use procfile::Procfile;
+use service::Service;
+use spectral::prelude::*;
+use std::env as std_env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set this to rewrite the golden files from the current output.
+const UPDATE_GOLDEN_VAR: &str = "STRAWBOSS_UPDATE_GOLDEN";
+
+fn read_fixture(procfile: &str) -> Vec<Service> {
+    let procfile = Procfile::new(PathBuf::from("fixtures").join(procfile));
+    env::read_services(&procfile, &[]).unwrap()
+}
+
+/// Read a fixture with its working directories resolved like `export` does, as if the project were
+/// in `/srv/app`.
+fn read_deployed(procfile: &str) -> Vec<Service> {
+    absolute_cwds(&read_fixture(procfile), Path::new("/srv/app"))
+}
+
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            list_files(root, &path, files);
+        } else {
+            files.push(path.strip_prefix(root).unwrap().to_path_buf());
+        }
+    }
+}
+
+/// Compare rendered files against the golden copies in `fixtures/export/<golden>`.
+fn assert_golden(files: Vec<ExportFile>, golden: &str) {
+    let golden_dir = PathBuf::from("fixtures/export").join(golden);
+    if std_env::var(UPDATE_GOLDEN_VAR).is_ok() {
+        if golden_dir.exists() {
+            fs::remove_dir_all(&golden_dir).unwrap();
+        }
+        for file in &files {
+            let path = golden_dir.join(&file.path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file.contents.as_bytes()).unwrap();
+        }
+    }
+
+    let mut expected = Vec::new();
+    list_files(&golden_dir, &golden_dir, &mut expected);
+    expected.sort();
+    let mut actual = files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+    actual.sort();
+    assert_that(&actual).is_equal_to(&expected);
+
+    for file in files {
+        let contents = fs::read_to_string(golden_dir.join(&file.path)).unwrap();
+        assert_that(&file.contents).is_equal_to(&contents);
+    }
+}
+
+fn options_with_everything() -> ExportOptions {
+    let mut options = ExportOptions::new("http");
+    options.formation = "web1=2".parse().unwrap();
+    options.user = Some(String::from("www-data"));
+    options.log_dir = Some(PathBuf::from("/var/log/http"));
+    options.restart = RestartPolicy::OnFailure;
+    options
+}
+
+/// Options with a user for the services that don't have their own.
+fn options_with_user() -> ExportOptions {
+    let mut options = ExportOptions::new("app");
+    options.user = Some(String::from("www-data"));
+    options
+}
+
+mod systemd {
+    use super::{assert_golden, options_with_everything, options_with_user, read_deployed};
+    use export::systemd::Systemd;
+    use export::{render, ExportOptions, Exporter};
+
+    #[test]
+    fn test_renders_procfile() {
+        let services = read_deployed("Procfile");
+        let options = ExportOptions::new("app");
+        let files = Systemd.render(&services, &options).unwrap();
+        assert_golden(files, "systemd/Procfile");
+    }
+
+    #[test]
+    fn test_renders_instances_user_and_logs() {
+        let services = read_deployed("Procfile.two-http");
+        let files = Systemd
+            .render(&services, &options_with_everything())
+            .unwrap();
+        assert_golden(files, "systemd/Procfile.two-http");
+    }
+
+    #[test]
+    fn test_escapes_commands() {
+        let services = read_deployed("Procfile.pipe");
+        let options = ExportOptions::new("pipe");
+        let files = Systemd.render(&services, &options).unwrap();
+        assert_golden(files, "systemd/Procfile.pipe");
+    }
+
+    #[test]
+    fn test_renders_oneshots() {
+        let services = read_deployed("Procfile.oneshot");
+        let options = ExportOptions::new("app");
+        let files = Systemd.render(&services, &options).unwrap();
+        assert_golden(files, "systemd/Procfile.oneshot");
+    }
+
+    #[test]
+    fn test_renders_service_users_and_groups() {
+        let services = read_deployed("Procfile.user");
+        let files = Systemd.render(&services, &options_with_user()).unwrap();
+        assert_golden(files, "systemd/Procfile.user");
+    }
+
+    #[test]
+    fn test_skips_scheduled_services() {
+        let services = read_deployed("Procfile.schedule");
+        let options = ExportOptions::new("app");
+        let (files, _) = render("systemd", &services, &options).unwrap();
+        assert_golden(files, "systemd/Procfile.schedule");
+    }
+}
+
+mod supervisord {
+    use super::{assert_golden, options_with_everything, options_with_user, read_deployed};
+    use export::supervisord::Supervisord;
+    use export::{render, ExportOptions, Exporter};
+
+    #[test]
+    fn test_renders_procfile() {
+        let services = read_deployed("Procfile");
+        let options = ExportOptions::new("app");
+        let files = Supervisord.render(&services, &options).unwrap();
+        assert_golden(files, "supervisord/Procfile");
+    }
+
+    #[test]
+    fn test_renders_instances_user_and_logs() {
+        let services = read_deployed("Procfile.two-http");
+        let files = Supervisord
+            .render(&services, &options_with_everything())
+            .unwrap();
+        assert_golden(files, "supervisord/Procfile.two-http");
+    }
+
+    #[test]
+    fn test_renders_oneshots() {
+        let services = read_deployed("Procfile.oneshot");
+        let options = ExportOptions::new("app");
+        let files = Supervisord.render(&services, &options).unwrap();
+        assert_golden(files, "supervisord/Procfile.oneshot");
+    }
+
+    #[test]
+    fn test_renders_service_users_and_groups() {
+        let services = read_deployed("Procfile.user");
+        let files = Supervisord.render(&services, &options_with_user()).unwrap();
+        assert_golden(files, "supervisord/Procfile.user");
+    }
+
+    #[test]
+    fn test_skips_scheduled_services() {
+        let services = read_deployed("Procfile.schedule");
+        let options = ExportOptions::new("app");
+        let (files, _) = render("supervisord", &services, &options).unwrap();
+        assert_golden(files, "supervisord/Procfile.schedule");
+    }
+}
+
+mod runit {
+    use super::{assert_golden, options_with_everything, options_with_user, read_deployed};
+    use export::runit::Runit;
+    use export::{render, ExportOptions, Exporter};
+
+    #[test]
+    fn test_renders_procfile() {
+        let services = read_deployed("Procfile");
+        let options = ExportOptions::new("app");
+        let files = Runit.render(&services, &options).unwrap();
+        assert_golden(files, "runit/Procfile");
+    }
+
+    #[test]
+    fn test_renders_instances_user_and_logs() {
+        let services = read_deployed("Procfile.two-http");
+        let files = Runit.render(&services, &options_with_everything()).unwrap();
+        assert_golden(files, "runit/Procfile.two-http");
+    }
+
+    #[test]
+    fn test_renders_oneshots() {
+        let services = read_deployed("Procfile.oneshot");
+        let options = ExportOptions::new("app");
+        let files = Runit.render(&services, &options).unwrap();
+        assert_golden(files, "runit/Procfile.oneshot");
+    }
+
+    #[test]
+    fn test_renders_service_users_and_groups() {
+        let services = read_deployed("Procfile.user");
+        let files = Runit.render(&services, &options_with_user()).unwrap();
+        assert_golden(files, "runit/Procfile.user");
+    }
+
+    #[test]
+    fn test_skips_scheduled_services() {
+        let services = read_deployed("Procfile.schedule");
+        let options = ExportOptions::new("app");
+        let (files, _) = render("runit", &services, &options).unwrap();
+        assert_golden(files, "runit/Procfile.schedule");
+    }
+
+    #[test]
+    fn test_scripts_are_executable() {
+        let services = read_deployed("Procfile.python");
+        let options = ExportOptions::new("python");
+        let files = Runit.render(&services, &options).unwrap();
+        assert!(files.iter().all(|file| file.executable));
+    }
+}
+
+mod compose {
+    use super::{assert_golden, options_with_everything, options_with_user, read_fixture};
+    use export::compose::DockerCompose;
+    use export::{render, ExportOptions, Exporter};
+    use service::Service;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_renders_procfile() {
+        let services = read_fixture("Procfile");
+        let options = ExportOptions::new("app");
+        let files = DockerCompose.render(&services, &options).unwrap();
+        assert_golden(files, "docker-compose/Procfile");
+    }
+
+    #[test]
+    fn test_renders_instances_user_and_logs() {
+        let services = read_fixture("Procfile.two-http");
+        let files = DockerCompose
+            .render(&services, &options_with_everything())
+            .unwrap();
+        assert_golden(files, "docker-compose/Procfile.two-http");
+    }
+
+    #[test]
+    fn test_renders_oneshots() {
+        let services = read_fixture("Procfile.oneshot");
+        let options = ExportOptions::new("app");
+        let files = DockerCompose.render(&services, &options).unwrap();
+        assert_golden(files, "docker-compose/Procfile.oneshot");
+    }
+
+    #[test]
+    fn test_renders_service_users_and_groups() {
+        let services = read_fixture("Procfile.user");
+        let files = DockerCompose.render(&services, &options_with_user()).unwrap();
+        assert_golden(files, "docker-compose/Procfile.user");
+    }
+
+    #[test]
+    fn test_skips_scheduled_services() {
+        let services = read_fixture("Procfile.schedule");
+        let options = ExportOptions::new("app");
+        let (files, _) = render("docker-compose", &services, &options).unwrap();
+        assert_golden(files, "docker-compose/Procfile.schedule");
+    }
+
+    #[test]
+    fn test_escapes_variables() {
+        let mut service = Service::new("greeter", "echo $GREETING");
+        service
+            .env
+            .insert(String::from("GREETING"), String::from("hello $USER"));
+        let options = ExportOptions::new("app");
+        let files = DockerCompose.render(&[service], &options).unwrap();
+        assert_that(&files[0].contents).contains("echo $$GREETING");
+        assert_that(&files[0].contents).contains("hello $$USER");
+    }
+}
+
+mod export {
+    use super::{absolute_cwds, export, read_fixture};
+    use export::ExportOptions;
+    use service::Service;
+    use spectral::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+    use std::process;
+
+    #[test]
+    fn test_writes_files_to_directory() {
+        let dir = env::temp_dir().join(format!("straw-boss.export.{}", process::id()));
+        let services = read_fixture("Procfile.python");
+        let options = ExportOptions::new("python");
+
+        assert_that(&export("systemd", &services, &options, &dir)).is_ok();
+        assert_that(&dir.join("python.target")).exists();
+        assert_that(&dir.join("python-python.1.service")).exists();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_writes_absolute_working_directories() {
+        let dir = env::temp_dir().join(format!("straw-boss.export-cwd.{}", process::id()));
+        let services = read_fixture("Procfile.python");
+        let options = ExportOptions::new("python");
+
+        assert_that(&export("systemd", &services, &options, &dir)).is_ok();
+        let unit = fs::read_to_string(dir.join("python-python.1.service")).unwrap();
+        let cwd = env::current_dir().unwrap().canonicalize().unwrap().join("fixtures");
+        assert_that(&unit).contains(format!("WorkingDirectory={}\n", cwd.display()).as_str());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolves_working_directories() {
+        let mut service = Service::new("web", "start web-server");
+        service.cwd = Some(PathBuf::from("./fixtures/../public"));
+        let services = absolute_cwds(&[service], Path::new("/srv/app"));
+        assert_that(&services[0].cwd).is_equal_to(Some(PathBuf::from("/srv/app/public")));
+    }
+
+    #[test]
+    fn test_makes_scripts_executable() {
+        let dir = env::temp_dir().join(format!("straw-boss.export-runit.{}", process::id()));
+        let services = read_fixture("Procfile.python");
+        let options = ExportOptions::new("python");
+
+        assert_that(&export("runit", &services, &options, &dir)).is_ok();
+        let mode = fs::metadata(dir.join("python-python-1").join("run"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_that(&(mode & 0o111)).is_equal_to(0o111);
+        // runsv runs the script from the service's directory, so the `cd` can't be relative.
+        let run = fs::read_to_string(dir.join("python-python-1").join("run")).unwrap();
+        let cwd = env::current_dir().unwrap().canonicalize().unwrap().join("fixtures");
+        assert_that(&run).contains(format!("cd '{}'\n", cwd.display()).as_str());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_warns_about_scheduled_services() {
+        let dir = env::temp_dir().join(format!("straw-boss.export-schedule.{}", process::id()));
+        let services = read_fixture("Procfile.schedule");
+        let options = ExportOptions::new("app");
+
+        let warnings = export("systemd", &services, &options, &dir);
+        assert_that(&warnings).is_ok().is_equal_to(vec![String::from(
+            "report: skipped because it runs on a schedule",
+        )]);
+        assert_that(&dir.join("app-report.1.service")).does_not_exist();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_errors_on_unknown_format() {
+        let services = read_fixture("Procfile.python");
+        let options = ExportOptions::new("python");
+        let dir = env::temp_dir().join("straw-boss.export.unknown");
+        assert_that(&export("upstart-ish", &services, &options, &dir)).is_err();
+    }
+}
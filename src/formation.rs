@@ -0,0 +1,192 @@
+use failure::Error;
+use service::Service;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use Result;
+
+/// The name in a formation that sets the count for every service that isn't listed.
+const ALL: &str = "all";
+
+/// How many instances of each service to run. This is parsed from specifications like
+/// `web=2,worker=1`. Services that aren't mentioned get one instance, unless `all=N` is given.
+#[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Formation(BTreeMap<String, usize>);
+
+impl Formation {
+    /// Create a formation that runs one instance of everything.
+    pub fn new() -> Formation {
+        Formation(BTreeMap::new())
+    }
+
+    /// The number of instances to run for the named service.
+    pub fn count(&self, name: &str) -> usize {
+        self.get(name).unwrap_or(1)
+    }
+
+    /// The number of instances given for the named service, either by name or with `all`. This
+    /// is `None` if the formation doesn't say.
+    pub fn get(&self, name: &str) -> Option<usize> {
+        let &Formation(ref counts) = self;
+        counts.get(name).or_else(|| counts.get(ALL)).cloned()
+    }
+
+    /// The services that are named in the formation. This doesn't include `all`.
+    pub fn names(&self) -> Vec<&str> {
+        let &Formation(ref counts) = self;
+        counts
+            .keys()
+            .map(|name| name.as_str())
+            .filter(|name| *name != ALL)
+            .collect()
+    }
+
+    /// List the instances to run for a set of services. They're ordered by service and then by
+    /// instance number.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use straw_boss::formation::Formation;
+    /// use straw_boss::service::Service;
+    ///
+    /// let formation: Formation = "web=2,worker=0".parse().unwrap();
+    /// let services = vec![Service::new("web", "start web"), Service::new("worker", "work")];
+    /// let names = formation
+    ///     .instances(&services)
+    ///     .iter()
+    ///     .map(|i| i.name())
+    ///     .collect::<Vec<String>>();
+    /// assert_eq!(vec![String::from("web.1"), String::from("web.2")], names);
+    /// ```
+    pub fn instances(&self, services: &[Service]) -> Vec<Instance> {
+        services
+            .iter()
+            .flat_map(|service| {
+                (1..=self.count(&service.name)).map(move |number| Instance::new(service, number))
+            }).collect()
+    }
+}
+
+impl FromStr for Formation {
+    type Err = Error;
+
+    /// Parse a comma-separated list of `name=count` pairs.
+    fn from_str(s: &str) -> Result<Formation> {
+        s.split(',')
+            .map(|pair| pair.trim())
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let name = parts
+                    .next()
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| format_err!("Invalid formation: {:?}", &pair))?;
+                let count = parts
+                    .next()
+                    .ok_or_else(|| format_err!("Invalid formation: {:?}", &pair))?
+                    .parse::<usize>()
+                    .map_err(|err| format_err!("Invalid count in {:?}: {}", &pair, &err))?;
+                Ok((String::from(name), count))
+            }).collect::<Result<BTreeMap<String, usize>>>()
+            .map(Formation)
+    }
+}
+
+/// One numbered copy of a service.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Instance {
+    /// The service, with the instance's environment.
+    pub service: Service,
+    /// The instance number. These start at 1.
+    pub number: usize,
+}
+
+impl Instance {
+    /// Create an instance of a service. Each instance after the first has its `PORT` moved up by
+    /// one, so that `web.2` listens one port above `web.1`.
+    pub fn new(service: &Service, number: usize) -> Instance {
+        let mut service = service.clone();
+        let port = service
+            .env
+            .get("PORT")
+            .and_then(|port| port.parse::<usize>().ok());
+        if let Some(port) = port {
+            service
+                .env
+                .insert(String::from("PORT"), (port + number - 1).to_string());
+        }
+        Instance { service, number }
+    }
+
+    /// The instance's name, which is the service's name and the instance number.
+    pub fn name(&self) -> String {
+        format!("{}.{}", &self.service.name, self.number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    mod from_str {
+        use formation::Formation;
+        use spectral::prelude::*;
+
+        #[test]
+        fn test_parses_counts() {
+            let formation = "web=2, worker=3".parse::<Formation>().unwrap();
+            assert_that(&formation.count("web")).is_equal_to(2);
+            assert_that(&formation.count("worker")).is_equal_to(3);
+        }
+
+        #[test]
+        fn test_defaults_to_one() {
+            let formation = "web=2".parse::<Formation>().unwrap();
+            assert_that(&formation.count("clock")).is_equal_to(1);
+        }
+
+        #[test]
+        fn test_all_sets_default() {
+            let formation = "all=0,web=2".parse::<Formation>().unwrap();
+            assert_that(&formation.count("clock")).is_equal_to(0);
+            assert_that(&formation.count("web")).is_equal_to(2);
+        }
+
+        #[test]
+        fn test_get_only_gives_counts_it_has() {
+            let formation = "web=2".parse::<Formation>().unwrap();
+            assert_that(&formation.get("web")).is_equal_to(Some(2));
+            assert_that(&formation.get("clock")).is_none();
+            assert_that(&formation.names()).is_equal_to(vec!["web"]);
+        }
+
+        #[test]
+        fn test_errors_on_bad_count() {
+            assert_that(&"web=lots".parse::<Formation>()).is_err();
+            assert_that(&"web".parse::<Formation>()).is_err();
+        }
+    }
+
+    mod instances {
+        use formation::Formation;
+        use service::Service;
+        use spectral::prelude::*;
+
+        #[test]
+        fn test_offsets_ports() {
+            let mut service = Service::new("web", "start web");
+            service.env.insert(String::from("PORT"), String::from("5000"));
+            let formation = "web=3".parse::<Formation>().unwrap();
+            let ports = formation
+                .instances(&[service])
+                .into_iter()
+                .map(|i| i.service.env["PORT"].clone())
+                .collect::<Vec<String>>();
+            assert_that(&ports).equals_iterator(
+                &vec![
+                    String::from("5000"),
+                    String::from("5001"),
+                    String::from("5002"),
+                ].iter(),
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use import::{Imported, Importer};
+use serde_yaml;
+use serde_yaml::Value;
+use service::Service;
+use shellwords;
+use std::io::Read;
+use Result;
+
+/// Imports the services in a `docker-compose.yml`.
+pub struct DockerCompose;
+
+impl Importer for DockerCompose {
+    /// Each compose service with a `command` becomes a service. Its `environment` is carried
+    /// over. Everything else is about the container, so it's skipped with a warning.
+    fn read(&self, input: &mut dyn Read) -> Result<Imported> {
+        let compose: Value = serde_yaml::from_reader(input)
+            .map_err(|err| format_err!("Unable to parse compose file: {}", &err))?;
+        let compose = compose
+            .as_mapping()
+            .ok_or_else(|| format_err!("Compose file isn't a mapping."))?;
+        let mut imported = Imported::default();
+
+        for (key, value) in compose {
+            match key.as_str() {
+                Some("version") => {}
+                Some("services") => {
+                    let services = value
+                        .as_mapping()
+                        .ok_or_else(|| format_err!("Compose services aren't a mapping."))?;
+                    for (name, config) in services {
+                        let name = scalar(name)
+                            .ok_or_else(|| format_err!("Invalid service name: {:?}", &name))?;
+                        read_service(&name, config, &mut imported)?;
+                    }
+                }
+                _ => imported.warn(format!("ignoring top-level {}", describe(key))),
+            }
+        }
+
+        Ok(imported)
+    }
+}
+
+fn read_service(name: &str, config: &Value, imported: &mut Imported) -> Result<()> {
+    let config = config
+        .as_mapping()
+        .ok_or_else(|| format_err!("Service {} isn't a mapping.", &name))?;
+    let mut service = Service::new(name, "");
+
+    for (key, value) in config {
+        match key.as_str() {
+            Some("command") => service.command = read_command(name, value)?,
+            Some("environment") => read_environment(&mut service, value, imported)?,
+            Some("working_dir") => imported.warn(format!(
+                "{}: ignoring `working_dir`, which is a path in the container",
+                &name
+            )),
+            _ => imported.warn(format!("{}: ignoring {}", &name, describe(key))),
+        }
+    }
+
+    if service.command.is_empty() {
+        imported.warn(format!(
+            "{}: skipped because it has no `command` (it runs the image's default)",
+            &name
+        ));
+    } else {
+        imported.services.push(service);
+    }
+
+    Ok(())
+}
+
+fn read_command(name: &str, command: &Value) -> Result<String> {
+    if let Some(command) = command.as_str() {
+        return Ok(String::from(command));
+    }
+
+    let words = command
+        .as_sequence()
+        .ok_or_else(|| format_err!("{}: `command` isn't a string or a list.", &name))?
+        .iter()
+        .map(|word| scalar(word).ok_or_else(|| format_err!("{}: invalid command word.", &name)))
+        .collect::<Result<Vec<String>>>()?;
+    let words = words.iter().map(|word| word.as_str()).collect::<Vec<_>>();
+    Ok(shellwords::join(&words))
+}
+
+fn read_environment(service: &mut Service, env: &Value, imported: &mut Imported) -> Result<()> {
+    if let Some(env) = env.as_mapping() {
+        for (key, value) in env {
+            let key = scalar(key)
+                .ok_or_else(|| format_err!("{}: invalid environment name.", &service.name))?;
+            match scalar(value) {
+                Some(value) => {
+                    service.env.insert(key, value);
+                }
+                None => imported.warn(format!(
+                    "{}: ignoring environment variable {}, which is passed through from the host",
+                    &service.name, &key
+                )),
+            }
+        }
+    } else if let Some(env) = env.as_sequence() {
+        for item in env {
+            let item = scalar(item)
+                .ok_or_else(|| format_err!("{}: invalid environment entry.", &service.name))?;
+            let mut parts = item.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => {
+                    service.env.insert(String::from(key), String::from(value));
+                }
+                _ => imported.warn(format!(
+                    "{}: ignoring environment variable {}, which is passed through from the host",
+                    &service.name, &item
+                )),
+            }
+        }
+    } else {
+        return Err(format_err!(
+            "{}: `environment` isn't a mapping or a list.",
+            &service.name
+        ));
+    }
+
+    Ok(())
+}
+
+/// Convert a YAML scalar to a string. Nulls and collections give `None`.
+fn scalar(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref s) => Some(s.clone()),
+        Value::Number(ref n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn describe(key: &Value) -> String {
+    match scalar(key) {
+        Some(key) => format!("`{}`", key),
+        None => format!("{:?}", key),
+    }
+}
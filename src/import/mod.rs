@@ -0,0 +1,91 @@
+use service::{index_services, Service};
+use serde_yaml;
+use shellwords;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use Result;
+
+pub mod compose;
+pub mod npm;
+pub mod supervisord;
+
+/// The names of the formats that can be imported.
+pub const FORMATS: &[&str] = &["docker-compose", "npm", "supervisord"];
+
+/// The formats that imported services can be written as.
+pub const OUTPUT_FORMATS: &[&str] = &["procfile", "yaml"];
+
+/// Services read from another format, with warnings about anything that couldn't be carried over.
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub services: Vec<Service>,
+    pub warnings: Vec<String>,
+}
+
+impl Imported {
+    fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+}
+
+/// Something that reads services from another process manager's format.
+pub trait Importer {
+    /// Read the services from the input.
+    fn read(&self, input: &mut dyn Read) -> Result<Imported>;
+}
+
+/// Get the importer for a format. The names are the ones in `FORMATS`.
+pub fn importer(format: &str) -> Result<Box<dyn Importer>> {
+    match format {
+        "docker-compose" => Ok(Box::new(compose::DockerCompose)),
+        "npm" => Ok(Box::new(npm::Npm)),
+        "supervisord" => Ok(Box::new(supervisord::Supervisord)),
+        _ => Err(format_err!("Unknown import format: {:?}", &format)),
+    }
+}
+
+/// Read the services from a file in another format and write them out as a `Procfile` or as the
+/// YAML that `yamlize` prints.
+///
+/// # Returns
+///
+/// The warnings about things in the input that couldn't be carried over.
+pub fn import<P: AsRef<Path>, W: Write>(
+    format: &str,
+    input: P,
+    output: &str,
+    writer: &mut W,
+) -> Result<Vec<String>> {
+    let input = input.as_ref();
+    let mut f = File::open(input)
+        .map_err(|err| format_err!("Unable to open {:?}: {:?}", &input, &err))?;
+    let imported = importer(format)?
+        .read(&mut f)
+        .map_err(|err| format_err!("Unable to import {:?}: {}", &input, &err))?;
+
+    let rendered = match output {
+        "procfile" => imported
+            .services
+            .iter()
+            .map(|service| service.to_procfile())
+            .collect::<String>(),
+        "yaml" => serde_yaml::to_string(&index_services(&imported.services))
+            .map_err(|err| format_err!("Cannot convert services to YAML: {}", &err))?,
+        _ => return Err(format_err!("Unknown output format: {:?}", &output)),
+    };
+    writer
+        .write_all(rendered.as_bytes())
+        .map_err(|err| format_err!("Unable to write output: {:?}", &err))?;
+
+    Ok(imported.warnings)
+}
+
+/// Wrap a command line so that it's run by the shell. straw boss doesn't use a shell itself, so
+/// commands written for one need this.
+fn shell_command(command: &str) -> String {
+    format!("sh -c {}", shellwords::escape(command))
+}
+
+#[cfg(test)]
+mod test;
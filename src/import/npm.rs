@@ -0,0 +1,56 @@
+use import::{shell_command, Imported, Importer};
+use serde_json;
+use serde_json::Value;
+use service::Service;
+use std::io::Read;
+use Result;
+
+/// The prefixes npm uses for scripts that run around another script.
+const HOOK_PREFIXES: &[&str] = &["pre", "post"];
+
+/// Imports the `scripts` from a `package.json`.
+pub struct Npm;
+
+impl Importer for Npm {
+    /// Each script becomes a service that runs it with the shell, like npm does. Hook scripts like
+    /// `prestart` are skipped with a warning, since nothing runs them around their script.
+    fn read(&self, input: &mut dyn Read) -> Result<Imported> {
+        let package: Value = serde_json::from_reader(input)
+            .map_err(|err| format_err!("Unable to parse package.json: {}", &err))?;
+        let scripts = package
+            .get("scripts")
+            .and_then(|scripts| scripts.as_object())
+            .ok_or_else(|| format_err!("package.json has no scripts."))?;
+        let mut imported = Imported::default();
+
+        for (name, script) in scripts {
+            let script = script
+                .as_str()
+                .ok_or_else(|| format_err!("Script {} isn't a string.", &name))?;
+            let hooks = HOOK_PREFIXES
+                .iter()
+                .find(|prefix| {
+                    name.starts_with(*prefix) && scripts.contains_key(&name[prefix.len()..])
+                });
+            match hooks {
+                Some(prefix) => imported.warn(format!(
+                    "{}: skipped because it's an npm hook for `{}`",
+                    &name,
+                    &name[prefix.len()..]
+                )),
+                None => imported
+                    .services
+                    .push(Service::new(name, &shell_command(script))),
+            }
+        }
+
+        if !imported.services.is_empty() {
+            imported.warn(String::from(
+                "npm puts node_modules/.bin on the PATH for scripts; the imported services don't \
+                 get that",
+            ));
+        }
+
+        Ok(imported)
+    }
+}
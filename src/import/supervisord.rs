@@ -0,0 +1,163 @@
+use import::{Imported, Importer};
+use service::Service;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use Result;
+
+/// The prefix for the sections that define programs.
+const PROGRAM_PREFIX: &str = "program:";
+
+/// Sections that run processes but that can't be imported.
+const UNSUPPORTED_PREFIXES: &[&str] = &["group:", "eventlistener:", "fcgi-program:"];
+
+/// Imports the programs from a supervisord configuration file.
+pub struct Supervisord;
+
+type Section = (String, Vec<(String, String)>);
+
+impl Importer for Supervisord {
+    /// Each `[program:name]` section becomes a service. `command`, `directory` and `environment`
+    /// are carried over, and other settings are skipped with a warning. Sections that configure
+    /// supervisord itself are skipped quietly.
+    fn read(&self, input: &mut dyn Read) -> Result<Imported> {
+        let mut imported = Imported::default();
+
+        for (section, settings) in parse_ini(input)? {
+            if section.starts_with(PROGRAM_PREFIX) {
+                let name = &section[PROGRAM_PREFIX.len()..];
+                read_program(name, &settings, &mut imported)?;
+            } else if UNSUPPORTED_PREFIXES
+                .iter()
+                .any(|prefix| section.starts_with(prefix))
+            {
+                imported.warn(format!("ignoring section [{}]", &section));
+            }
+        }
+
+        Ok(imported)
+    }
+}
+
+fn read_program(name: &str, settings: &[(String, String)], imported: &mut Imported) -> Result<()> {
+    let mut service = Service::new(name, "");
+
+    for (key, value) in settings {
+        let value = interpolate(name, value);
+        match key.as_str() {
+            "command" => service.command = value,
+            "directory" => service.cwd = Some(PathBuf::from(value)),
+            "environment" => {
+                for (key, value) in parse_environment(&value)? {
+                    service.env.insert(key, value);
+                }
+            }
+            "numprocs" if value == "1" => {}
+            _ => imported.warn(format!("{}: ignoring `{}`", &name, &key)),
+        }
+    }
+
+    if service.command.contains("%(") {
+        imported.warn(format!(
+            "{}: `command` uses an expansion that can't be imported",
+            &name
+        ));
+    }
+
+    if service.command.is_empty() {
+        imported.warn(format!("{}: skipped because it has no `command`", &name));
+    } else {
+        imported.services.push(service);
+    }
+
+    Ok(())
+}
+
+/// Fill in the expansions that make sense outside of supervisord.
+fn interpolate(name: &str, value: &str) -> String {
+    value
+        .replace("%(program_name)s", name)
+        .replace("%(process_num)d", "0")
+        .replace("%%", "%")
+}
+
+/// Parse an `environment` setting, which looks like `A="one",B=two`.
+fn parse_environment(env: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    let mut chars = env.chars().peekable();
+
+    loop {
+        let key = chars
+            .by_ref()
+            .take_while(|c| *c != '=')
+            .collect::<String>();
+        let key = key.trim();
+        if key.is_empty() {
+            break;
+        }
+
+        let mut value = String::new();
+        let mut quote = None;
+        while let Some(c) = chars.next() {
+            match (c, quote) {
+                ('"', None) | ('\'', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                (',', None) => break,
+                (c, _) => value.push(c),
+            }
+        }
+        if quote.is_some() {
+            return Err(format_err!("Unterminated quote in environment: {:?}", &env));
+        }
+        vars.push((String::from(key), String::from(value.trim())));
+    }
+
+    Ok(vars)
+}
+
+/// Read the sections of an INI file and their settings, in order.
+fn parse_ini(input: &mut dyn Read) -> Result<Vec<Section>> {
+    let mut sections: Vec<Section> = Vec::new();
+
+    for line in BufReader::new(input).lines() {
+        let line = line.map_err(|err| format_err!("Unable to read config: {:?}", &err))?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let name = trimmed[1..trimmed.len() - 1].trim();
+            sections.push((String::from(name), Vec::new()));
+            continue;
+        }
+
+        let settings = &mut sections
+            .last_mut()
+            .ok_or_else(|| format_err!("Setting outside of a section: {:?}", &line))?
+            .1;
+        if line.starts_with(char::is_whitespace) {
+            if let Some(&mut (_, ref mut value)) = settings.last_mut() {
+                value.push(' ');
+                value.push_str(strip_comment(trimmed));
+                continue;
+            }
+        }
+
+        let mut parts = trimmed.splitn(2, |c| c == '=' || c == ':');
+        let key = parts.next().map(str::trim).unwrap_or("");
+        let value = parts
+            .next()
+            .ok_or_else(|| format_err!("Invalid setting: {:?}", &line))?;
+        settings.push((String::from(key), String::from(strip_comment(value.trim()))));
+    }
+
+    Ok(sections)
+}
+
+/// Remove an inline comment, which starts with a semicolon after whitespace.
+fn strip_comment(value: &str) -> &str {
+    match value.find(" ;") {
+        Some(index) => value[..index].trim_right(),
+        None => value,
+    }
+}
@@ -0,0 +1,155 @@
+use import::{importer, Imported};
+use std::fs::File;
+
+fn read_fixture(format: &str, fixture: &str) -> Imported {
+    let mut f = File::open(format!("fixtures/import/{}", fixture)).unwrap();
+    importer(format).unwrap().read(&mut f).unwrap()
+}
+
+mod compose {
+    use super::read_fixture;
+    use service::Service;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_reads_services_with_commands() {
+        let imported = read_fixture("docker-compose", "docker-compose.yml");
+        let names = imported
+            .services
+            .iter()
+            .map(|s| s.name.clone())
+            .collect::<Vec<String>>();
+        assert_that(&names)
+            .equals_iterator(&vec![String::from("web"), String::from("worker")].iter());
+    }
+
+    #[test]
+    fn test_reads_commands_and_environment() {
+        let imported = read_fixture("docker-compose", "docker-compose.yml");
+        let mut web = Service::new("web", "python3 -m http.server 3040");
+        web.env.insert(String::from("MODE"), String::from("development"));
+        web.env.insert(String::from("WORKERS"), String::from("2"));
+        let mut worker = Service::new("worker", "sh -c echo\\ working\\ \\&\\&\\ sleep\\ 60");
+        worker.env.insert(String::from("QUEUE"), String::from("default"));
+
+        assert_that(&imported.services).is_equal_to(vec![web, worker]);
+    }
+
+    #[test]
+    fn test_warns_about_unmapped_fields() {
+        let imported = read_fixture("docker-compose", "docker-compose.yml");
+        let warnings = imported.warnings;
+        assert_that(&warnings).contains(String::from("web: ignoring `build`"));
+        assert_that(&warnings).contains(String::from("web: ignoring `ports`"));
+        assert_that(&warnings).contains(String::from(
+            "web: ignoring `working_dir`, which is a path in the container",
+        ));
+        assert_that(&warnings).contains(String::from(
+            "web: ignoring environment variable SECRET, which is passed through from the host",
+        ));
+        assert_that(&warnings).contains(String::from(
+            "db: skipped because it has no `command` (it runs the image's default)",
+        ));
+        assert_that(&warnings).contains(String::from("ignoring top-level `volumes`"));
+    }
+}
+
+mod npm {
+    use super::read_fixture;
+    use service::Service;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_reads_scripts_as_shell_commands() {
+        let imported = read_fixture("npm", "package.json");
+        assert_that(&imported.services).is_equal_to(vec![
+            Service::new("start", "sh -c node\\ server.js\\ --port\\ \\$PORT"),
+            Service::new("watch", "sh -c webpack\\ --watch\\ \\&\\&\\ echo\\ done"),
+        ]);
+    }
+
+    #[test]
+    fn test_warns_about_hooks() {
+        let imported = read_fixture("npm", "package.json");
+        assert_that(&imported.warnings).contains(String::from(
+            "prestart: skipped because it's an npm hook for `start`",
+        ));
+    }
+}
+
+mod supervisord {
+    use super::read_fixture;
+    use service::Service;
+    use spectral::prelude::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_reads_programs() {
+        let imported = read_fixture("supervisord", "supervisord.conf");
+        let mut web = Service::new("web", "python3 -m http.server 3040");
+        web.cwd = Some(PathBuf::from("/srv/web"));
+        web.env.insert(String::from("MODE"), String::from("production"));
+        web.env.insert(String::from("GREETING"), String::from("hello, world"));
+        let worker = Service::new("worker", "sh -c \"echo working && sleep 60\"");
+
+        assert_that(&imported.services).is_equal_to(vec![web, worker]);
+    }
+
+    #[test]
+    fn test_warns_about_unmapped_settings() {
+        let imported = read_fixture("supervisord", "supervisord.conf");
+        assert_that(&imported.warnings).is_equal_to(vec![
+            String::from("web: ignoring `autorestart`"),
+            String::from("ignoring section [group:app]"),
+        ]);
+    }
+}
+
+mod import {
+    use import::import;
+    use service::Service;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_writes_procfile_that_reads_back() {
+        let mut output = Vec::new();
+        let warnings = import(
+            "supervisord",
+            "fixtures/import/supervisord.conf",
+            "procfile",
+            &mut output,
+        );
+        assert_that(&warnings).is_ok();
+
+        let services = Service::read_procfile(&output[..]).unwrap();
+        assert_that(&services).has_length(2);
+        assert_that(&services[0].env.get("GREETING"))
+            .is_some()
+            .is_equal_to(&String::from("hello, world"));
+        assert_that(&services[1].command)
+            .is_equal_to(&String::from("sh -c \"echo working && sleep 60\""));
+    }
+
+    #[test]
+    fn test_writes_yaml() {
+        let mut output = Vec::new();
+        let warnings = import(
+            "npm",
+            "fixtures/import/package.json",
+            "yaml",
+            &mut output,
+        );
+        assert_that(&warnings).is_ok();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_that(&output).contains("start:");
+        assert_that(&output).contains("server.js");
+    }
+
+    #[test]
+    fn test_errors_on_unknown_format() {
+        let mut output = Vec::new();
+        let result = import("upstart", "fixtures/Procfile", "procfile", &mut output);
+        assert_that(&result).is_err();
+    }
+}
@@ -4,10 +4,16 @@
 
 extern crate chrono;
 extern crate clap;
+extern crate cron;
 extern crate daemonize;
 extern crate duct;
 #[macro_use]
 extern crate failure;
+extern crate globset;
+extern crate ignore;
+extern crate libc;
+extern crate notify;
+extern crate os_pipe;
 //#[macro_use]
 //extern crate failure_derive;
 #[cfg(test)]
@@ -20,8 +26,11 @@ extern crate assert_fs;
 #[cfg(test)]
 extern crate predicates;
 extern crate rmp_serde;
+extern crate serde_json;
 extern crate serde_yaml;
 extern crate shellwords;
+extern crate signal_hook;
+extern crate termion;
 #[cfg(test)]
 extern crate spectral;
 
@@ -29,11 +38,19 @@ use std::io;
 
 pub mod actions;
 pub mod client;
+pub mod daemons;
+pub mod env;
+pub mod export;
+pub mod formation;
+pub mod import;
 pub mod messaging;
+pub mod oneoff;
 pub mod procfile;
 pub mod server;
 pub mod service;
+pub mod signal;
 pub mod tasks;
+pub mod top;
 pub mod yamlize;
 
 /// A convenience type alias for a specialization of `Result` that uses `failure::Error` for
@@ -47,7 +64,11 @@ pub type Result<A> = std::result::Result<A, failure::Error>;
 /// # Arguments
 ///
 /// * `action`: The `Action` object to run.
-pub fn run(action: actions::Action) -> Result<()> {
+///
+/// # Returns
+///
+/// The exit code for the process.
+pub fn run(action: actions::Action) -> Result<i32> {
     let stdout = io::stdout();
     let mut writer = io::BufWriter::new(stdout);
     action
@@ -4,23 +4,33 @@ extern crate straw_boss;
 #[macro_use]
 extern crate failure;
 
-use clap::{Arg, ArgMatches, SubCommand};
+use clap::{AppSettings, Arg, ArgMatches, SubCommand};
 use std::env;
 use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
 use straw_boss::actions::Action;
+use straw_boss::daemons::{runtime_dir, DaemonPaths};
+use straw_boss::env::EnvFile;
+use straw_boss::export::{default_app_name, ExportOptions, FORMATS};
+use straw_boss::import;
 use straw_boss::procfile::Procfile;
-use straw_boss::server::local::DOMAIN_SOCKET;
-use straw_boss::server::ServerRunMode;
+use straw_boss::server::events::{EventFilter, EVENT_KINDS};
+use straw_boss::server::{ExitOn, ServerOptions, ServerRunMode};
+use straw_boss::service::hooks::Hooks;
+use straw_boss::signal::parse_signal;
 use straw_boss::tasks::TaskSpec;
 use straw_boss::Result;
 
 const SOCKET_PATH_VAR: &'static str = "STRAWBOSS_SOCKET_PATH";
 const PID_FILE_VAR: &'static str = "STRAWBOSS_PID_FILE";
+const CGROUP_VAR: &'static str = "STRAWBOSS_CGROUP";
 
 fn main() -> Result<()> {
     let action = parse_args()?;
-    straw_boss::run(action)
+    let code = straw_boss::run(action)?;
+    process::exit(code)
 }
 
 fn parse_args() -> Result<Action> {
@@ -30,19 +40,208 @@ fn parse_args() -> Result<Action> {
         .value_name("FILENAME")
         .default_value("Procfile")
         .help("The Procfile defining the services to run locally.");
+    let env_file = Arg::with_name("env")
+        .short("e")
+        .long("env")
+        .value_name("FILENAME")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help(
+            "An env file to read variables from. Defaults to the .env next to the Procfile, if \
+             there is one.",
+        );
+    let daemon_procfile = Arg::with_name("procfile")
+        .short("p")
+        .long("procfile")
+        .value_name("FILENAME")
+        .help(
+            "The Procfile of the daemon to talk to. Defaults to the nearest Procfile in the \
+             current directory or its parents.",
+        );
     let matches =
         app_from_crate!()
             .subcommand(
                 SubCommand::with_name("start")
                     .about("This starts all of the processes listed in the Procfile.")
                     .arg(procfile.clone())
+                    .arg(env_file.clone())
                     .arg(Arg::with_name("daemon").short("d").long("daemon").help(
                         "Run the straw boss task manager in the background as a server/daemon.",
-                    )),
-            ).subcommand(SubCommand::with_name("status").about("This queries daemonized tasks."))
-            .subcommand(
+                    )).arg(Arg::with_name("watch").short("w").long("watch").help(
+                        "Restart services when the files matching their watch annotations change.",
+                    )).arg(Arg::with_name("replace").long("replace").help(
+                        "Stop the straw boss that's already running for this Procfile and take \
+                         over from it.",
+                    )).arg(
+                        Arg::with_name("timeout")
+                            .long("timeout")
+                            .help(
+                                "With --daemon, how many seconds to wait for the daemon to start \
+                                 serving. Defaults to 30.",
+                            ).takes_value(true),
+                    ).arg(
+                        Arg::with_name("cgroup")
+                            .long("cgroup")
+                            .value_name("PATH")
+                            .help(
+                                "Run each service in a cgroup of its own under this delegated \
+                                 cgroup v2 slice. Relative paths are under /sys/fs/cgroup. \
+                                 Defaults to $STRAWBOSS_CGROUP.",
+                            ).takes_value(true),
+                    ).arg(
+                        Arg::with_name("hook")
+                            .long("hook")
+                            .value_name("EVENT=COMMAND")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help(
+                                "A command to run for the whole formation at EVENT: pre-start, \
+                                 post-start, pre-stop, or post-exit. timeout=SECONDS sets how long \
+                                 each hook gets. Defaults to 30.",
+                            ),
+                    ).arg(
+                        Arg::with_name("exit-on-first-exit")
+                            .long("exit-on-first-exit")
+                            .help(
+                                "Stop everything when any service exits, and exit with its exit \
+                                 code.",
+                            ).conflicts_with_all(&["daemon", "exit-on-failure"]),
+                    ).arg(
+                        Arg::with_name("exit-on-failure")
+                            .long("exit-on-failure")
+                            .help(
+                                "Stop everything when any service fails, and exit with its exit \
+                                 code.",
+                            ).conflicts_with("daemon"),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("run")
+                    .about(
+                        "This runs one command in the foreground with the services' environment. \
+                         If the command is a service name, that service's command is run.",
+                    ).setting(AppSettings::TrailingVarArg)
+                    .arg(procfile.clone())
+                    .arg(env_file.clone())
+                    .arg(
+                        Arg::with_name("command")
+                            .help("The command to run, or a service name and extra arguments.")
+                            .required(true)
+                            .multiple(true),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("export")
+                    .about("This exports the services to another process manager's format.")
+                    .arg(procfile.clone())
+                    .arg(env_file.clone())
+                    .arg(
+                        Arg::with_name("format")
+                            .help("The format to export to.")
+                            .required(true)
+                            .possible_values(FORMATS),
+                    ).arg(
+                        Arg::with_name("dir")
+                            .help("The directory to write the exported files to.")
+                            .required(true),
+                    ).arg(
+                        Arg::with_name("app")
+                            .short("a")
+                            .long("app")
+                            .value_name("NAME")
+                            .help("The application name. Defaults to the Procfile's directory."),
+                    ).arg(
+                        Arg::with_name("concurrency")
+                            .short("c")
+                            .long("concurrency")
+                            .value_name("FORMATION")
+                            .help("How many of each service to run, like web=2,worker=1."),
+                    ).arg(
+                        Arg::with_name("user")
+                            .short("u")
+                            .long("user")
+                            .value_name("USER")
+                            .help("The user to run the services that don't set their own as."),
+                    ).arg(
+                        Arg::with_name("log")
+                            .short("l")
+                            .long("log")
+                            .value_name("DIR")
+                            .help("The directory to write the services' logs to."),
+                    ).arg(
+                        Arg::with_name("restart")
+                            .short("r")
+                            .long("restart")
+                            .value_name("POLICY")
+                            .default_value("always")
+                            .possible_values(&["always", "on-failure", "no"])
+                            .help("When to restart services that exit. One-shots never are."),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("import")
+                    .about(
+                        "This reads the processes from another format and prints them as a \
+                         Procfile or as YAML.",
+                    ).arg(
+                        Arg::with_name("format")
+                            .help("The format to import from.")
+                            .required(true)
+                            .possible_values(import::FORMATS),
+                    ).arg(
+                        Arg::with_name("file")
+                            .help("The file to import.")
+                            .required(true),
+                    ).arg(
+                        Arg::with_name("to")
+                            .short("t")
+                            .long("to")
+                            .value_name("FORMAT")
+                            .default_value("procfile")
+                            .possible_values(import::OUTPUT_FORMATS)
+                            .help("The format to print the services in."),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("status")
+                    .about("This queries daemonized tasks.")
+                    .arg(daemon_procfile.clone()),
+            ).subcommand(
+                SubCommand::with_name("top")
+                    .about("This shows daemonized tasks live, and lets you manage them.")
+                    .arg(daemon_procfile.clone()),
+            ).subcommand(
+                SubCommand::with_name("events")
+                    .about("This prints what happens to daemonized tasks as JSON lines.")
+                    .arg(daemon_procfile.clone())
+                    .arg(
+                        Arg::with_name("task")
+                            .short("t")
+                            .long("task")
+                            .help(
+                                "One or more tasks to report on. A process type, like web, \
+                                 includes all of its instances. Defaults to every task.",
+                            ).required(false)
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1),
+                    ).arg(
+                        Arg::with_name("event")
+                            .short("e")
+                            .long("event")
+                            .value_name("EVENT")
+                            .help("One or more kinds of events to report. Defaults to all of them.")
+                            .required(false)
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .possible_values(EVENT_KINDS),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("list-daemons")
+                    .about("This lists the straw boss daemons running for every project."),
+            ).subcommand(
                 SubCommand::with_name("stop")
                     .about("This stops a running server.")
+                    .arg(daemon_procfile.clone())
                     .arg(
                         Arg::with_name("task")
                             .short("t")
@@ -52,6 +251,64 @@ fn parse_args() -> Result<Action> {
                             .takes_value(true)
                             .multiple(true),
                     ),
+            ).subcommand(
+                SubCommand::with_name("signal")
+                    .about("This sends a signal to running tasks.")
+                    .arg(daemon_procfile.clone())
+                    .arg(
+                        Arg::with_name("task")
+                            .short("t")
+                            .long("task")
+                            .help(
+                                "One or more tasks to signal. A process type, like web, includes \
+                                 all of its instances. Defaults to every task.",
+                            ).required(false)
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1),
+                    ).arg(
+                        Arg::with_name("signal")
+                            .help("The signal to send, like HUP, SIGUSR1, or 15.")
+                            .required(true),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("pause")
+                    .about("This freezes running tasks with SIGSTOP.")
+                    .arg(daemon_procfile.clone())
+                    .arg(
+                        Arg::with_name("task")
+                            .short("t")
+                            .long("task")
+                            .help("One or more tasks to pause. Defaults to every task.")
+                            .required(false)
+                            .takes_value(true)
+                            .multiple(true),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("resume")
+                    .about("This lets paused tasks carry on with SIGCONT.")
+                    .arg(daemon_procfile.clone())
+                    .arg(
+                        Arg::with_name("task")
+                            .short("t")
+                            .long("task")
+                            .help("One or more tasks to resume. Defaults to every task.")
+                            .required(false)
+                            .takes_value(true)
+                            .multiple(true),
+                    ),
+            ).subcommand(
+                SubCommand::with_name("scale")
+                    .about("This changes how many instances of each process type a server runs.")
+                    .arg(daemon_procfile.clone())
+                    .arg(
+                        Arg::with_name("formation")
+                            .help(
+                                "How many of each process type to run, like web=4 worker=1. Types \
+                                 that aren't listed are left alone.",
+                            ).required(true)
+                            .multiple(true),
+                    ),
             ).subcommand(
                 SubCommand::with_name("yamlize")
                     .about(
@@ -62,23 +319,101 @@ fn parse_args() -> Result<Action> {
 
     if let Some(sub_matches) = matches.subcommand_matches("start") {
         let procfile = get_procfile(&sub_matches)?;
-        let socket_path = get_socket_path();
+        let env_files = get_env_files(&sub_matches)?;
+        let paths = DaemonPaths::for_procfile(&procfile, &runtime_dir())?;
+        let socket_path = get_socket_path(&paths);
         let run_mode = if sub_matches.is_present("daemon") {
-            let pid_file = env::var(PID_FILE_VAR).unwrap_or_else(|_| {
-                String::from(env::temp_dir().join("straw-boss.pid").to_string_lossy())
-            });
-            ServerRunMode::Daemon(PathBuf::from(pid_file))
+            let pid_file = env::var_os(PID_FILE_VAR)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| paths.pid_file());
+            ServerRunMode::Daemon(pid_file)
         } else {
             ServerRunMode::Foreground
         };
-        Ok(Action::Start(procfile, run_mode, socket_path))
-    } else if let Some(_sub_matches) = matches.subcommand_matches("status") {
-        let socket_path = get_socket_path();
+        let options = ServerOptions {
+            watch: sub_matches.is_present("watch"),
+            replace: sub_matches.is_present("replace"),
+            procfile: Some(procfile.path().to_path_buf()),
+            startup_timeout: get_startup_timeout(&sub_matches)?,
+            exit_on: get_exit_on(&sub_matches),
+            cgroup: sub_matches
+                .value_of_os("cgroup")
+                .map(|path| path.to_os_string())
+                .or_else(|| env::var_os(CGROUP_VAR))
+                .map(PathBuf::from),
+            hooks: get_hooks(&sub_matches)?,
+        };
+        Ok(Action::Start(
+            procfile,
+            env_files,
+            run_mode,
+            options,
+            socket_path,
+        ))
+    } else if let Some(sub_matches) = matches.subcommand_matches("run") {
+        let procfile = get_procfile(&sub_matches)?;
+        let env_files = get_env_files(&sub_matches)?;
+        let command = sub_matches
+            .values_of("command")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_else(Vec::new);
+        Ok(Action::Run(procfile, env_files, command))
+    } else if let Some(sub_matches) = matches.subcommand_matches("export") {
+        let procfile = get_procfile(&sub_matches)?;
+        let env_files = get_env_files(&sub_matches)?;
+        let format = String::from(sub_matches.value_of("format").unwrap_or("systemd"));
+        let dir = PathBuf::from(sub_matches.value_of("dir").unwrap_or("."));
+        let options = get_export_options(&sub_matches, &procfile)?;
+        Ok(Action::Export(procfile, env_files, format, dir, options))
+    } else if let Some(sub_matches) = matches.subcommand_matches("import") {
+        let format = String::from(sub_matches.value_of("format").unwrap_or("docker-compose"));
+        let file = PathBuf::from(sub_matches.value_of("file").unwrap_or("docker-compose.yml"));
+        let output = String::from(sub_matches.value_of("to").unwrap_or("procfile"));
+        Ok(Action::Import(format, file, output))
+    } else if let Some(sub_matches) = matches.subcommand_matches("status") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
         Ok(Action::Status(socket_path))
+    } else if let Some(sub_matches) = matches.subcommand_matches("top") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
+        Ok(Action::Top(socket_path))
+    } else if let Some(sub_matches) = matches.subcommand_matches("events") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
+        let kinds = sub_matches
+            .values_of("event")
+            .map(|values| values.map(|kind| kind.parse()).collect::<Result<Vec<_>>>())
+            .unwrap_or_else(|| Ok(Vec::new()))?;
+        let filter = EventFilter {
+            tasks: get_tasks(sub_matches),
+            kinds,
+        };
+        Ok(Action::Events(socket_path, filter))
+    } else if let Some(_sub_matches) = matches.subcommand_matches("list-daemons") {
+        Ok(Action::ListDaemons(runtime_dir()))
     } else if let Some(sub_matches) = matches.subcommand_matches("stop") {
-        let socket_path = get_socket_path();
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
         let tasks = get_tasks(sub_matches);
         Ok(Action::Stop(socket_path, tasks))
+    } else if let Some(sub_matches) = matches.subcommand_matches("signal") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
+        let tasks = get_tasks(sub_matches);
+        let signal = parse_signal(sub_matches.value_of("signal").unwrap_or(""))?;
+        Ok(Action::Signal(socket_path, tasks, signal))
+    } else if let Some(sub_matches) = matches.subcommand_matches("pause") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
+        let tasks = get_tasks(sub_matches);
+        Ok(Action::Pause(socket_path, tasks))
+    } else if let Some(sub_matches) = matches.subcommand_matches("resume") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
+        let tasks = get_tasks(sub_matches);
+        Ok(Action::Resume(socket_path, tasks))
+    } else if let Some(sub_matches) = matches.subcommand_matches("scale") {
+        let socket_path = get_daemon_socket_path(&sub_matches)?;
+        let formation = sub_matches
+            .values_of("formation")
+            .map(|values| values.collect::<Vec<_>>().join(","))
+            .unwrap_or_default()
+            .parse()?;
+        Ok(Action::Scale(socket_path, formation))
     } else if let Some(sub_matches) = matches.subcommand_matches("yamlize") {
         let procfile = get_procfile(&sub_matches)?;
         Ok(Action::Yamlize(procfile))
@@ -98,8 +433,81 @@ fn get_procfile(matches: &ArgMatches) -> Result<Procfile> {
     Ok(procfile)
 }
 
-fn get_socket_path() -> PathBuf {
-    PathBuf::from(env::var(SOCKET_PATH_VAR).unwrap_or_else(|_| String::from(DOMAIN_SOCKET)))
+fn get_env_files(matches: &ArgMatches) -> Result<Vec<EnvFile>> {
+    let pwd = env::current_dir()
+        .map_err(|err| format_err!("Cannot get current directory: {:?}", &err))?;
+    Ok(matches
+        .values_of("env")
+        .map(|values| values.map(|env_file| EnvFile::new(pwd.join(env_file))).collect())
+        .unwrap_or_else(Vec::new))
+}
+
+fn get_export_options(matches: &ArgMatches, procfile: &Procfile) -> Result<ExportOptions> {
+    let app = matches
+        .value_of("app")
+        .map(String::from)
+        .unwrap_or_else(|| default_app_name(procfile));
+    let mut options = ExportOptions::new(&app);
+    if let Some(formation) = matches.value_of("concurrency") {
+        options.formation = formation.parse()?;
+    }
+    options.user = matches.value_of("user").map(String::from);
+    options.log_dir = matches.value_of("log").map(PathBuf::from);
+    options.restart = matches.value_of("restart").unwrap_or("always").parse()?;
+    Ok(options)
+}
+
+fn get_exit_on(matches: &ArgMatches) -> Option<ExitOn> {
+    if matches.is_present("exit-on-first-exit") {
+        Some(ExitOn::AnyExit)
+    } else if matches.is_present("exit-on-failure") {
+        Some(ExitOn::Failure)
+    } else {
+        None
+    }
+}
+
+fn get_hooks(matches: &ArgMatches) -> Result<Hooks> {
+    let mut hooks = Hooks::default();
+    for hook in matches.values_of("hook").into_iter().flatten() {
+        let mut parts = hook.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(event), Some(command)) => hooks.set(event, command)?,
+            _ => return Err(format_err!("Invalid hook: {:?}", hook)),
+        }
+    }
+    Ok(hooks)
+}
+
+fn get_startup_timeout(matches: &ArgMatches) -> Result<Option<Duration>> {
+    match matches.value_of("timeout") {
+        Some(timeout) => timeout
+            .parse()
+            .map(|secs| Some(Duration::from_secs(secs)))
+            .map_err(|err| format_err!("Invalid timeout {:?}: {}", timeout, &err)),
+        None => Ok(None),
+    }
+}
+
+/// The socket for a daemon. `STRAWBOSS_SOCKET_PATH` overrides the one in its directory.
+fn get_socket_path(paths: &DaemonPaths) -> PathBuf {
+    env::var_os(SOCKET_PATH_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| paths.socket_path())
+}
+
+/// The socket for the daemon that the client commands talk to. This is the daemon for the
+/// `Procfile` given or, without one, for the nearest `Procfile` to the current directory.
+fn get_daemon_socket_path(matches: &ArgMatches) -> Result<PathBuf> {
+    let procfile = if matches.is_present("procfile") {
+        get_procfile(matches)?
+    } else {
+        let pwd = env::current_dir()
+            .map_err(|err| format_err!("Cannot get current directory: {:?}", &err))?;
+        Procfile::find(&pwd)
+    };
+    let paths = DaemonPaths::for_procfile(&procfile, &runtime_dir())?;
+    Ok(get_socket_path(&paths))
 }
 
 fn get_tasks(matches: &ArgMatches) -> TaskSpec {
@@ -0,0 +1,154 @@
+use duct::{cmd, Expression};
+use env;
+use env::EnvFile;
+use libc;
+use procfile::Procfile;
+use service::Service;
+use shellwords;
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+use std::convert::TryFrom;
+use std::iter;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::thread;
+use Result;
+
+/// The signals that are passed on to the command. `SIGINT` isn't here because the terminal
+/// already sends it to the whole foreground process group, command included. It's still caught,
+/// so that Ctrl-C leaves it to the command whether to exit, and its exit code is reported.
+const FORWARDED_SIGNALS: &[i32] = &[SIGHUP, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2];
+
+/// Run a single command in the foreground with the same environment as the services.
+///
+/// If the first word of the command names a service in the `Procfile`, that service's command is
+/// run, in its directory and with its environment, and the rest of the words are appended to it.
+/// Otherwise the command is run as given with the variables from the env files.
+///
+/// # Returns
+///
+/// The command's exit code. If the command was killed by a signal, this is 128 plus the signal
+/// number, like a shell would report.
+pub fn run_oneoff(procfile: &Procfile, env_files: &[EnvFile], command: &[String]) -> Result<i32> {
+    let expression = build_expression(procfile, env_files, command)?;
+    let handle = expression
+        .unchecked()
+        .start()
+        .map_err(|err| format_err!("Unable to run {:?}: {:?}", &command, &err))?;
+
+    let mut signals = Signals::new(FORWARDED_SIGNALS.iter().chain(iter::once(&SIGINT)))
+        .map_err(|err| format_err!("Unable to install signal handlers: {:?}", &err))?;
+    let signals_handle = signals.handle();
+    let pids = handle.pids();
+    let forwarder = thread::spawn(move || {
+        for signal in signals.forever().filter(|signal| *signal != SIGINT) {
+            for pid in &pids {
+                unsafe {
+                    libc::kill(*pid as libc::pid_t, signal);
+                }
+            }
+        }
+    });
+
+    let output = handle.wait();
+    signals_handle.close();
+    let _ = forwarder.join();
+
+    let output = output.map_err(|err| format_err!("Error running {:?}: {:?}", &command, &err))?;
+    Ok(exit_code(output.status))
+}
+
+fn build_expression(
+    procfile: &Procfile,
+    env_files: &[EnvFile],
+    command: &[String],
+) -> Result<Expression> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| format_err!("No command given to run."))?;
+
+    let services = if procfile.exists() {
+        env::read_services(procfile, env_files)?
+    } else {
+        Vec::new()
+    };
+
+    if let Some(service) = services.into_iter().find(|s| &s.name == program) {
+        let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+        let service = Service {
+            command: format!("{} {}", &service.command, shellwords::join(&args)),
+            ..service
+        };
+        Expression::try_from(service)
+    } else {
+        let vars = env::read_vars(procfile, env_files)?;
+        Ok(vars
+            .iter()
+            .fold(cmd(program, args), |c, (key, value)| c.env(key, value)))
+    }
+}
+
+fn exit_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::run_oneoff;
+    use env::EnvFile;
+    use procfile::Procfile;
+    use spectral::prelude::*;
+
+    fn command(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| String::from(*w)).collect()
+    }
+
+    #[test]
+    fn test_returns_exit_code() {
+        let procfile = Procfile::new("fixtures/env/Procfile".into());
+        let code = run_oneoff(&procfile, &[], &command(&["sh", "-c", "exit 3"]));
+        assert_that(&code).is_ok().is_equal_to(3);
+    }
+
+    #[test]
+    fn test_reports_signals_like_a_shell() {
+        let procfile = Procfile::new("fixtures/env/Procfile".into());
+        let code = run_oneoff(&procfile, &[], &command(&["sh", "-c", "kill -TERM $$"]));
+        assert_that(&code).is_ok().is_equal_to(128 + 15);
+    }
+
+    #[test]
+    fn test_runs_service_with_extra_args() {
+        let procfile = Procfile::new("fixtures/env/Procfile".into());
+        let code = run_oneoff(&procfile, &[], &command(&["exit-with", "4"]));
+        assert_that(&code).is_ok().is_equal_to(4);
+    }
+
+    #[test]
+    fn test_runs_service_with_its_env() {
+        let procfile = Procfile::new("fixtures/env/Procfile".into());
+        let env_files = vec![EnvFile::new("fixtures/env/other.env".into())];
+        let code = run_oneoff(&procfile, &env_files, &command(&["check-greeting"]));
+        assert_that(&code).is_ok().is_equal_to(0);
+    }
+
+    #[test]
+    fn test_runs_command_with_env_files() {
+        let procfile = Procfile::new("fixtures/env/Procfile".into());
+        let code = run_oneoff(
+            &procfile,
+            &[],
+            &command(&["sh", "-c", "test \"$GREETING\" = 'hello from .env'"]),
+        );
+        assert_that(&code).is_ok().is_equal_to(0);
+    }
+
+    #[test]
+    fn test_errors_on_empty_command() {
+        let procfile = Procfile::new("fixtures/env/Procfile".into());
+        assert_that(&run_oneoff(&procfile, &[], &[])).is_err();
+    }
+}
@@ -1,6 +1,6 @@
 use service::Service;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use Result;
 
 /// A `Procfile`. This is a newtype for a `PathBuf`.
@@ -13,18 +13,61 @@ impl Procfile {
         Procfile(procfile)
     }
 
-    /// Read a vector of `Service` instances from a `Procfile`.
+    /// The path to the `Procfile`.
+    pub fn path(&self) -> &Path {
+        let &Procfile(ref procfile) = self;
+        procfile
+    }
+
+    /// Does the `Procfile` exist?
+    pub fn exists(&self) -> bool {
+        let &Procfile(ref procfile) = self;
+        procfile.exists()
+    }
+
+    /// Find the `Procfile` for a directory. This is the first one in the directory or one of its
+    /// parents. If there isn't one, this is the `Procfile` that would be in the directory.
+    pub fn find(dir: &Path) -> Procfile {
+        dir.ancestors()
+            .map(|dir| dir.join("Procfile"))
+            .find(|procfile| procfile.is_file())
+            .map(Procfile)
+            .unwrap_or_else(|| Procfile(dir.join("Procfile")))
+    }
+
+    /// The directory containing the `Procfile`. Services run relative to this.
+    pub fn directory(&self) -> PathBuf {
+        let &Procfile(ref procfile) = self;
+        match procfile.parent() {
+            Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Read a vector of `Service` instances from a `Procfile`. Each service's working directory
+    /// is resolved against the directory the `Procfile` is in.
     pub fn read_services(&self) -> Result<Vec<Service>> {
         let &Procfile(ref procfile) = self;
         let f = File::open(&procfile)
             .map_err(|err| format_err!("Unable to open Procfile: {:?}\n{}", &procfile, &err))?;
-        Service::read_procfile(f).map_err(|err| {
+        let mut services = Service::read_procfile(f).map_err(|err| {
             format_err!(
                 "Unable to read data from Procfile: {:?}\n{}",
                 &procfile,
                 &err
             )
-        })
+        })?;
+
+        let directory = self.directory();
+        for service in &mut services {
+            let cwd = match service.cwd.take() {
+                Some(cwd) => directory.join(cwd),
+                None => directory.clone(),
+            };
+            service.cwd = Some(cwd);
+        }
+
+        Ok(services)
     }
 }
 
@@ -35,22 +78,69 @@ mod test {
         use service::Service;
         use spectral::assert_that;
         use spectral::prelude::*;
+        use std::path::PathBuf;
 
         #[test]
         fn test_reads_list_of_services() {
             let procfile = Procfile::new("fixtures/Procfile".into());
             let services = procfile.read_services().unwrap();
-            let expected = vec![
+            let mut expected = vec![
                 Service::new("ticker", "ruby ./ticker $PORT"),
                 Service::new("error", "ruby ./error"),
                 Service::new("utf8", "ruby ./utf8"),
                 Service::new("spawner", "./spawner"),
             ];
+            for service in &mut expected {
+                service.cwd = Some(PathBuf::from("fixtures"));
+            }
             let mut assert = assert_that(&services);
             assert.contains(&expected[0]);
             assert.contains(&expected[1]);
             assert.contains(&expected[2]);
             assert.contains(&expected[3]);
         }
+
+        #[test]
+        fn test_resolves_cwd_against_procfile_directory() {
+            let procfile = Procfile::new("fixtures/Procfile.cwd".into());
+            let services = procfile.read_services().unwrap();
+            let cwds = services
+                .into_iter()
+                .map(|s| s.cwd)
+                .collect::<Vec<Option<PathBuf>>>();
+            assert_that(&cwds).equals_iterator(
+                &vec![
+                    Some(PathBuf::from("fixtures/hello there")),
+                    Some(PathBuf::from("/tmp")),
+                    Some(PathBuf::from("fixtures")),
+                ].iter(),
+            );
+        }
+
+        #[test]
+        fn test_bare_procfile_runs_in_current_directory() {
+            let procfile = Procfile::new("Procfile".into());
+            assert_that(&procfile.directory()).is_equal_to(PathBuf::from("."));
+        }
+    }
+
+    mod find {
+        use procfile::Procfile;
+        use spectral::prelude::*;
+        use std::env;
+        use std::path::Path;
+
+        #[test]
+        fn test_finds_procfile_in_parent() {
+            let procfile = Procfile::find(Path::new("fixtures/hello there"));
+            assert_that(&procfile.path()).is_equal_to(Path::new("fixtures/Procfile"));
+        }
+
+        #[test]
+        fn test_defaults_to_directory() {
+            let dir = env::temp_dir().join("straw-boss.no-procfile");
+            let procfile = Procfile::find(&dir);
+            assert_that(&procfile.path()).is_equal_to(dir.join("Procfile").as_path());
+        }
     }
 }
@@ -0,0 +1,246 @@
+use chrono::prelude::*;
+use messaging::Sender;
+use serde_json;
+use server::ResponseMessage;
+use service::worker::ServiceWorker;
+use std::collections::BTreeMap;
+use std::os::unix::net::UnixStream;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tasks::TaskSpec;
+use Result;
+
+/// How long the server waits on a subscriber that isn't reading its events before dropping it.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The names of the kinds of events, for the command line.
+pub const EVENT_KINDS: &[&str] = &["started", "exited", "restarting", "stopped"];
+
+/// What happened to a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum EventKind {
+    /// Its command started, after being started for the first time, restarted, scaled up, or
+    /// run on its schedule.
+    Started,
+    /// Its command exited, on its own or because it was stopped.
+    Exited,
+    /// Straw boss is restarting it, because its files changed or a client asked.
+    Restarting,
+    /// Straw boss asked it to stop, because a client asked, it was scaled away, or the server is
+    /// shutting down.
+    Stopped,
+}
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Started => "started",
+            EventKind::Exited => "exited",
+            EventKind::Restarting => "restarting",
+            EventKind::Stopped => "stopped",
+        }
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = ::failure::Error;
+
+    fn from_str(s: &str) -> Result<EventKind> {
+        match s {
+            "started" => Ok(EventKind::Started),
+            "exited" => Ok(EventKind::Exited),
+            "restarting" => Ok(EventKind::Restarting),
+            "stopped" => Ok(EventKind::Stopped),
+            _ => Err(format_err!("Unknown event: {:?}", s)),
+        }
+    }
+}
+
+/// Something that happened to a service, as it's sent to subscribers.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Event {
+    pub kind: EventKind,
+    pub service: String,
+    pub time: SystemTime,
+    /// The ID of the first process in the service's pipeline, when it started.
+    pub pid: Option<u32>,
+    /// The exit code, when it exited on its own.
+    pub code: Option<i32>,
+    /// The signal that terminated it, when one did.
+    pub signal: Option<i32>,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, service: &str) -> Event {
+        Event {
+            kind,
+            service: String::from(service),
+            time: SystemTime::now(),
+            pid: None,
+            code: None,
+            signal: None,
+        }
+    }
+
+    /// The event as a line of JSON, like
+    /// `{"event":"exited","service":"web","time":"2018-10-01T12:00:00.000Z","code":1}`. Fields
+    /// that don't apply to the event are left out.
+    pub fn to_json(&self) -> Result<String> {
+        let time: DateTime<Utc> = self.time.into();
+        let json = JsonEvent {
+            event: self.kind.name(),
+            service: &self.service,
+            time: time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            pid: self.pid,
+            code: self.code,
+            signal: self.signal,
+        };
+        serde_json::to_string(&json)
+            .map_err(|err| format_err!("Unable to write {:?} as JSON: {:?}", &self, &err))
+    }
+}
+
+/// How an event's written for scripts.
+#[derive(Serialize)]
+struct JsonEvent<'a> {
+    event: &'static str,
+    service: &'a str,
+    time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signal: Option<i32>,
+}
+
+/// Which events a subscriber wants.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct EventFilter {
+    pub tasks: TaskSpec,
+    /// The kinds of events. Empty means all of them.
+    pub kinds: Vec<EventKind>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        self.tasks.matches(&event.service)
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> EventFilter {
+        EventFilter {
+            tasks: TaskSpec::All,
+            kinds: Vec::new(),
+        }
+    }
+}
+
+/// The clients that are subscribed to the server's events. This also remembers what it's seen of
+/// each worker, so that it can tell when they start and exit.
+#[derive(Debug, Default)]
+pub struct Events {
+    subscribers: Vec<(UnixStream, EventFilter)>,
+    /// When each worker's latest run started, and whether its exit has been published.
+    seen: BTreeMap<String, (Option<SystemTime>, bool)>,
+}
+
+impl Events {
+    pub fn new() -> Events {
+        Events::default()
+    }
+
+    /// Send the events that pass the filter to the client on the other end of the stream, until
+    /// it goes away.
+    pub fn subscribe(&mut self, stream: UnixStream, filter: EventFilter) -> Result<()> {
+        stream
+            .set_write_timeout(Some(WRITE_TIMEOUT))
+            .map_err(|err| format_err!("Unable to subscribe: {:?}", &err))?;
+        self.subscribers.push((stream, filter));
+        Ok(())
+    }
+
+    /// Send an event to the subscribers that want it. Ones that can't be sent to are dropped.
+    pub fn publish(&mut self, event: &Event) {
+        let subscribers = self
+            .subscribers
+            .drain(..)
+            .filter_map(|(mut stream, filter)| {
+                if filter.matches(event)
+                    && stream.send(ResponseMessage::Event(event.clone())).is_err()
+                {
+                    return None;
+                }
+                Some((stream, filter))
+            }).collect();
+        self.subscribers = subscribers;
+    }
+
+    /// Publish the starts and exits of the workers since the last check.
+    pub fn check<'a, I>(&mut self, workers: I)
+    where
+        I: IntoIterator<Item = &'a ServiceWorker>,
+    {
+        for worker in workers {
+            let name = &worker.service().name;
+            let started_at = worker.started_at();
+            let (last_started_at, mut exit_published) =
+                self.seen.get(name).cloned().unwrap_or((None, false));
+            if started_at != last_started_at {
+                exit_published = false;
+                if let Some(started_at) = started_at {
+                    let mut event = Event::new(EventKind::Started, name);
+                    event.time = started_at;
+                    event.pid = worker.pid();
+                    self.publish(&event);
+                }
+            }
+            if let Some(exit) = worker.exit_info().filter(|_| !exit_published) {
+                exit_published = true;
+                if started_at.is_some() {
+                    let mut event = Event::new(EventKind::Exited, name);
+                    event.time = exit.exited_at;
+                    event.code = exit.code;
+                    event.signal = exit.signal;
+                    self.publish(&event);
+                }
+            }
+            self.seen.insert(name.clone(), (started_at, exit_published));
+        }
+    }
+
+    /// Tell the subscribers that no more events are coming, because the server's stopping.
+    pub fn close(&mut self) {
+        for (mut stream, _) in self.subscribers.drain(..) {
+            let _ = stream.send(ResponseMessage::Stopping);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Event, EventFilter, EventKind};
+    use spectral::prelude::*;
+    use tasks::TaskSpec;
+
+    #[test]
+    fn test_parses_kinds() {
+        assert_that(&"exited".parse::<EventKind>()).is_ok().is_equal_to(EventKind::Exited);
+        assert_that(&"exploded".parse::<EventKind>()).is_err();
+    }
+
+    #[test]
+    fn test_filters_by_service_and_kind() {
+        let filter = EventFilter {
+            tasks: TaskSpec::List(vec![String::from("web")]),
+            kinds: vec![EventKind::Exited, EventKind::Stopped],
+        };
+        assert_that(&filter.matches(&Event::new(EventKind::Exited, "web.2"))).is_true();
+        assert_that(&filter.matches(&Event::new(EventKind::Started, "web"))).is_false();
+        assert_that(&filter.matches(&Event::new(EventKind::Exited, "worker"))).is_false();
+        assert_that(&EventFilter::default().matches(&Event::new(EventKind::Started, "worker")))
+            .is_true();
+    }
+}
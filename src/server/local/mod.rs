@@ -1,20 +1,90 @@
-use messaging::{Receiver, Sender};
-use server::{daemonize, ManagerServer, RequestMessage, ResponseMessage};
-use service::worker::{ServiceWorker, Worker};
-use service::Service;
-use std::collections::HashSet;
+use chrono::prelude::*;
+use failure::Error;
+use formation::{Formation, Instance};
+use libc;
+use messaging::{connect, Receiver, Sender};
+use notify::RecommendedWatcher;
+use server::events::{Event, EventKind, Events};
+use server::pid_file::{PidFile, PidLock};
+use server::ready::{ready_pipe, ReadyNotifier, STARTUP_TIMEOUT};
+use server::watch::watch;
+use server::{
+    daemonize, DaemonInfo, ManagerServer, ProcessScale, RequestMessage, ResponseMessage,
+    ServerEvent, ServerOptions,
+};
+use service::cgroup::Slice;
+use service::hooks::{HookEvent, RunningHook};
+use service::schedule::{Overlap, Timer};
+use service::worker::{ServiceWorker, Worker, WorkerState};
+use service::{Service, ServiceKind};
+use signal_hook::consts::signal::{SIGINT, SIGKILL, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::{Handle, Signals};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::os::unix::net::UnixListener;
+use std::iter;
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tasks::TaskSpec;
 use Result;
 
 pub const DOMAIN_SOCKET: &str = "/tmp/straw-boss-server.sock";
 
+/// The signals the server handles. `SIGINT` and `SIGTERM` shut it down, and the others are passed
+/// on to every service.
+const HANDLED_SIGNALS: &[i32] = &[SIGINT, SIGTERM, SIGUSR1, SIGUSR2];
+
+/// How long services get to exit after being asked to before they're killed. This starts once
+/// their `pre-stop` hooks are done, since those have timeouts of their own.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the event loop wakes up to check on things when nothing else is happening.
+const TICK: Duration = Duration::from_millis(100);
+
+/// How far a shutdown's got.
+enum Shutdown {
+    /// The formation's `pre-stop` hook is running. The services are asked to stop once it's done.
+    PreStop(Box<RunningHook>),
+    /// The services have been asked to stop.
+    Stopping,
+}
+
+/// A long-lived process type from the Procfile, and how many instances of it should run.
+#[derive(Debug)]
+struct ProcessType {
+    service: Service,
+    desired: usize,
+}
+
 #[derive(Default)]
 pub struct RestManagerServer {
     socket_path: PathBuf,
-    pid_file: Option<PathBuf>,
+    pid_file: Option<PidFile>,
+    ready: Option<ReadyNotifier>,
+    owns_socket: bool,
+    options: ServerOptions,
     workers: Vec<ServiceWorker>,
+    /// When each scheduled service runs next, by name.
+    timers: BTreeMap<String, Timer>,
+    /// The process types that can be scaled, by name.
+    process_types: BTreeMap<String, ProcessType>,
+    /// Instances that were scaled away and haven't exited yet.
+    retiring: Vec<ServiceWorker>,
+    /// The services that were asked to stop so they can be restarted, by name. Each starts again
+    /// once it's exited.
+    restarting: HashSet<String>,
+    /// The services that a client asked to stop and that haven't exited yet, by name.
+    stopping: HashSet<String>,
+    /// The cgroup slice that the services run in, if there is one.
+    slice: Option<Slice>,
+    /// The clients that are subscribed to events.
+    events: Events,
+    startup_failures: Option<String>,
+    shutdown: Option<Shutdown>,
+    exit_code: i32,
 }
 
 impl RestManagerServer {
@@ -23,79 +93,921 @@ impl RestManagerServer {
     }
 
     pub fn at_path(socket_path: PathBuf) -> RestManagerServer {
+        RestManagerServer::with_options(socket_path, ServerOptions::default())
+    }
+
+    pub fn with_options(socket_path: PathBuf, options: ServerOptions) -> RestManagerServer {
         RestManagerServer {
             socket_path,
             pid_file: None,
+            ready: None,
+            owns_socket: false,
+            options,
             workers: vec![],
+            timers: BTreeMap::new(),
+            process_types: BTreeMap::new(),
+            retiring: vec![],
+            restarting: HashSet::new(),
+            stopping: HashSet::new(),
+            slice: None,
+            events: Events::new(),
+            startup_failures: None,
+            shutdown: None,
+            exit_code: 0,
         }
     }
 
+    /// Open the socket. If one's left behind by a server that isn't answering anymore, it's
+    /// removed first.
     fn create_listener(&mut self) -> Result<UnixListener> {
-        UnixListener::bind(&self.socket_path).map_err(|err| {
+        if self.socket_path.exists() && self.options.replace {
+            self.replace_server(None, |server| !server.socket_path.exists())?;
+        }
+        if self.socket_path.exists() {
+            if UnixStream::connect(&self.socket_path).is_ok() {
+                return Err(format_err!(
+                    "Straw-boss is already running on {:?}.",
+                    &self.socket_path
+                ));
+            }
+            eprintln!("Removing stale socket {:?}.", &self.socket_path);
+            fs::remove_file(&self.socket_path).map_err(|err| {
+                format_err!(
+                    "Unable to remove stale socket {:?}: {:?}",
+                    &self.socket_path,
+                    &err
+                )
+            })?;
+        }
+
+        let listener = UnixListener::bind(&self.socket_path).map_err(|err| {
             format_err!("Unable to open socket: {:?}: {:?}", &self.socket_path, &err)
-        })
+        })?;
+        self.owns_socket = true;
+        Ok(listener)
+    }
+
+    /// Lock the PID file. If another server has it, either fail with that server's PID, or, with
+    /// the `replace` option, stop it and wait for the lock.
+    fn lock_pid_file(&self, path: &Path) -> Result<PidFile> {
+        match PidFile::try_lock(path)? {
+            PidLock::Acquired(pid_file) => Ok(pid_file),
+            PidLock::Held(pid) if self.options.replace => {
+                let mut pid_file = None;
+                self.replace_server(pid, |_| match PidFile::try_lock(path) {
+                    Ok(PidLock::Acquired(locked)) => {
+                        pid_file = Some(locked);
+                        true
+                    }
+                    _ => false,
+                })?;
+                pid_file.ok_or_else(|| format_err!("Unable to lock PID file {:?}.", path))
+            }
+            PidLock::Held(pid) => Err(format_err!(
+                "Straw-boss is already running as PID {} on socket {:?}.",
+                pid.map(|pid| pid.to_string())
+                    .unwrap_or_else(|| String::from("?")),
+                &self.socket_path
+            )),
+        }
+    }
+
+    /// Ask the server that's already running to stop, and wait until `is_gone` says it has. If it
+    /// doesn't answer on the socket, it's sent `SIGTERM` instead.
+    fn replace_server<F>(&self, pid: Option<u32>, mut is_gone: F) -> Result<()>
+    where
+        F: FnMut(&RestManagerServer) -> bool,
+    {
+        eprintln!("Stopping the straw boss that's already running.");
+        let stopped = connect(&self.socket_path)
+            .and_then(|mut stream| stream.send(RequestMessage::StopServer))
+            .is_ok();
+        if !stopped {
+            if let Some(pid) = pid {
+                unsafe {
+                    libc::kill(pid as libc::pid_t, SIGTERM);
+                }
+            }
+        }
+
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT * 2;
+        while !is_gone(self) {
+            if Instant::now() >= deadline {
+                return Err(format_err!(
+                    "The straw boss running on {:?} didn't stop.",
+                    &self.socket_path
+                ));
+            }
+            thread::sleep(TICK);
+        }
+        Ok(())
+    }
+
+    /// Set up the cgroup slice for the services, if the server has one. If it can't be set up, the
+    /// services run without cgroups.
+    fn create_slice(&self, services: &[Service]) -> Option<Slice> {
+        let path = match self.options.cgroup {
+            Some(ref path) => path,
+            None => {
+                if services.iter().any(|service| !service.cgroup.is_empty()) {
+                    eprintln!("The services' cgroup settings only apply with --cgroup.");
+                }
+                return None;
+            }
+        };
+        match Slice::create(path) {
+            Ok(slice) => Some(slice),
+            Err(err) => {
+                eprintln!("Running the services without cgroups. {}", &err);
+                None
+            }
+        }
+    }
+
+    /// A worker for a service, in the server's cgroup slice.
+    fn worker(&self, service: Service) -> ServiceWorker {
+        ServiceWorker::with_slice(service, self.slice.clone())
+    }
+
+    /// Tell the process that started the daemon, if it's waiting, that it couldn't start.
+    fn startup_failed(&mut self, err: Error) -> Error {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.failed(&err);
+        }
+        err
+    }
+
+    /// Start everything that feeds the event loop: the socket, the file watcher, and the signal
+    /// handlers.
+    fn listen(
+        &mut self,
+        tx: mpsc::Sender<ServerEvent>,
+    ) -> Result<(Option<RecommendedWatcher>, Handle)> {
+        self.accept(tx.clone())?;
+
+        // The watcher has to be created here, after any daemonizing, because its threads don't
+        // survive the fork.
+        let watcher = if self.options.watch {
+            let services = self
+                .workers
+                .iter()
+                .map(|w| w.service().clone())
+                .collect::<Vec<_>>();
+            watch(&services, tx.clone())?
+        } else {
+            None
+        };
+
+        let signals = self.handle_signals(tx)?;
+        Ok((watcher, signals))
+    }
+
+    /// Accept connections on a separate thread and pass them along to the event loop.
+    fn accept(&mut self, events: mpsc::Sender<ServerEvent>) -> Result<()> {
+        let listener = self.create_listener()?;
+        thread::Builder::new()
+            .name(String::from("accept"))
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            if events.send(ServerEvent::Request(stream)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => eprintln!("Unable to read from listener: {:?}", &err),
+                    }
+                }
+            }).map_err(|err| format_err!("Unable to start listening: {:?}", &err))?;
+        Ok(())
+    }
+
+    /// Pass the signals the server gets along to the event loop.
+    fn handle_signals(&mut self, events: mpsc::Sender<ServerEvent>) -> Result<Handle> {
+        let mut signals = Signals::new(HANDLED_SIGNALS)
+            .map_err(|err| format_err!("Unable to install signal handlers: {:?}", &err))?;
+        let handle = signals.handle();
+        thread::Builder::new()
+            .name(String::from("signals"))
+            .spawn(move || {
+                for signal in signals.forever() {
+                    if events.send(ServerEvent::Signal(signal)).is_err() {
+                        break;
+                    }
+                }
+            }).map_err(|err| format_err!("Unable to start handling signals: {:?}", &err))?;
+        Ok(handle)
+    }
+
+    /// Respond to one request from a client.
+    fn handle_request(&mut self, mut stream: UnixStream) -> Result<()> {
+        let request: RequestMessage = stream.recv()?;
+        match request {
+            RequestMessage::Ping => stream.send(ResponseMessage::Pong)?,
+            RequestMessage::GetInfo => {
+                let info = DaemonInfo {
+                    procfile: self.options.procfile.clone().unwrap_or_default(),
+                    pid: process::id(),
+                };
+                stream.send(ResponseMessage::Info(info))?;
+            }
+            RequestMessage::GetWorkers => {
+                let response = ResponseMessage::Workers(
+                    self.workers
+                        .iter()
+                        .map(|sw| {
+                            let mut status = sw.status();
+                            status.next_run = self
+                                .timers
+                                .get(&sw.service().name)
+                                .and_then(|timer| timer.next_run());
+                            status
+                        }).collect(),
+                );
+                stream.send(response)?;
+            }
+            RequestMessage::GetExitInfo => {
+                let response = ResponseMessage::ExitInfo(
+                    self.workers
+                        .iter()
+                        .filter_map(|sw| {
+                            sw.exit_info()
+                                .map(|exit| (sw.service().name.clone(), exit))
+                        }).collect(),
+                );
+                stream.send(response)?;
+            }
+            RequestMessage::Scale(formation) => {
+                let response = match self.scale(&formation) {
+                    Ok(()) => ResponseMessage::Scaled,
+                    Err(err) => ResponseMessage::Error(err.to_string()),
+                };
+                stream.send(response)?;
+            }
+            RequestMessage::GetScale => {
+                let response = ResponseMessage::ProcessScales(
+                    self.process_types
+                        .iter()
+                        .map(|(name, process_type)| ProcessScale {
+                            name: name.clone(),
+                            desired: process_type.desired,
+                            running: self
+                                .workers
+                                .iter()
+                                .filter(|w| instance_number(name, &w.service().name).is_some())
+                                .filter(|w| w.is_running())
+                                .count(),
+                        }).collect(),
+                );
+                stream.send(response)?;
+            }
+            RequestMessage::GetLogs(name, count) => {
+                let response = match self.workers.iter().find(|w| w.service().name == name) {
+                    Some(w) => ResponseMessage::Logs(w.log(count)),
+                    None => ResponseMessage::Error(format!("There's no service named {}.", name)),
+                };
+                stream.send(response)?;
+            }
+            RequestMessage::Subscribe(filter) => self.events.subscribe(stream, filter)?,
+            // The server stops once the shutdown's done, like it does for `SIGTERM`.
+            RequestMessage::StopServer => {
+                if self.shutdown.is_none() {
+                    self.begin_shutdown()?;
+                }
+            }
+            RequestMessage::StopTasks(tasks) => {
+                let tasks = TaskSpec::List(tasks);
+                for w in &mut self.workers.iter_mut() {
+                    if tasks.matches(&w.service().name) {
+                        let name = w.service().name.clone();
+                        self.restarting.remove(&name);
+                        if w.is_running() {
+                            terminate(w).map_err(|err| {
+                                format_err!("Unable to stop {:?}: {:?}", &name, &err)
+                            })?;
+                            self.stopping.insert(name.clone());
+                        }
+                        self.events.publish(&Event::new(EventKind::Stopped, &name));
+                    }
+                }
+            }
+            RequestMessage::Signal(tasks, signal) => {
+                for w in self.workers.iter_mut().filter(|w| w.is_running()) {
+                    if tasks.matches(&w.service().name) {
+                        w.signal(signal).map_err(|err| {
+                            format_err!("Unable to signal {:?}: {:?}", &w.service().name, &err)
+                        })?;
+                    }
+                }
+            }
+            RequestMessage::Pause(tasks) => {
+                for w in self.workers.iter_mut().filter(|w| w.is_running()) {
+                    if tasks.matches(&w.service().name) {
+                        w.pause().map_err(|err| {
+                            format_err!("Unable to pause {:?}: {:?}", &w.service().name, &err)
+                        })?;
+                    }
+                }
+            }
+            RequestMessage::Resume(tasks) => {
+                for w in self.workers.iter_mut().filter(|w| w.is_running()) {
+                    if tasks.matches(&w.service().name) {
+                        w.resume().map_err(|err| {
+                            format_err!("Unable to resume {:?}: {:?}", &w.service().name, &err)
+                        })?;
+                    }
+                }
+            }
+            RequestMessage::Restart(tasks) => {
+                if self.shutdown.is_some() {
+                    return Ok(());
+                }
+                for w in self.workers.iter_mut() {
+                    if tasks.matches(&w.service().name) {
+                        self.events
+                            .publish(&Event::new(EventKind::Restarting, &w.service().name));
+                        restart(w, &mut self.restarting, &mut self.events).map_err(|err| {
+                            format_err!("Unable to restart {:?}: {:?}", &w.service().name, &err)
+                        })?;
+                        self.stopping.remove(&w.service().name);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Respond to a signal sent to the server. The first `SIGINT` or `SIGTERM` asks every service
+    /// to stop, and a second one kills them. Other signals are passed along to the services.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the server should stop now.
+    fn handle_signal(&mut self, signal: i32) -> Result<bool> {
+        match signal {
+            SIGINT | SIGTERM if self.shutdown.is_some() => {
+                eprintln!("Killing services.");
+                // This drops the formation's `pre-stop` hook, which kills it if it's still going.
+                self.shutdown = Some(Shutdown::Stopping);
+                self.signal_all(SIGKILL)?;
+                Ok(false)
+            }
+            SIGINT | SIGTERM => {
+                eprintln!("Stopping services. Press Ctrl-C again to kill them.");
+                self.begin_shutdown().and(Ok(true))
+            }
+            signal => self.signal_all(signal).and(Ok(true)),
+        }
+    }
+
+    /// Start shutting down. The formation's `pre-stop` hook runs first, without holding up the
+    /// server, and then every service is asked to stop.
+    fn begin_shutdown(&mut self) -> Result<()> {
+        self.publish_stopped();
+        match self.options.hooks.start(HookEvent::PreStop, None, None, None) {
+            Ok(Some(hook)) => {
+                self.shutdown = Some(Shutdown::PreStop(Box::new(hook)));
+                Ok(())
+            }
+            Ok(None) => self.stop_services(),
+            Err(err) => {
+                eprintln!("{}", &err);
+                self.stop_services()
+            }
+        }
+    }
+
+    /// Ask every service to stop with `SIGTERM`, once its own `pre-stop` hook has run. Each gets
+    /// `SHUTDOWN_TIMEOUT` after that to exit before it's killed.
+    fn stop_services(&mut self) -> Result<()> {
+        self.shutdown = Some(Shutdown::Stopping);
+        for w in self.workers.iter_mut().filter(|w| w.is_running()) {
+            terminate(w).map_err(|err| {
+                format_err!("Unable to stop {:?}: {:?}", &w.service().name, &err)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// With the `exit_on` option, check whether a service has exited in a way that should stop
+    /// the server. If one has, the others are shut down, and the server exits with that service's
+    /// exit code. When more than one has, the first to exit counts. Services that were asked to
+    /// stop or restart don't count.
+    fn check_exits(&mut self) -> Result<()> {
+        let exit_on = match self.options.exit_on {
+            Some(exit_on) if self.shutdown.is_none() => exit_on,
+            _ => return Ok(()),
+        };
+
+        let first = self
+            .workers
+            .iter()
+            .filter(|w| w.thread_id().is_some() && w.service().schedule.is_none())
+            .filter(|w| {
+                let name = &w.service().name;
+                !self.restarting.contains(name) && !self.stopping.contains(name)
+            })
+            .filter_map(|w| w.exit_info().map(|exit| (w.service().name.clone(), exit)))
+            .filter(|(_, exit)| exit_on.matches(exit))
+            .min_by_key(|(_, exit)| exit.exited_at);
+        if let Some((name, exit)) = first {
+            eprintln!(
+                "{} exited with {}. Stopping the other services.",
+                &name,
+                exit.describe()
+            );
+            self.exit_code = exit.exit_code();
+            self.begin_shutdown()?;
+        }
+        Ok(())
+    }
+
+    /// Start the scheduled services that are due. One that's due while its last run is still
+    /// going is skipped or queued, depending on its `overlap`. Nothing new starts while the server
+    /// is shutting down.
+    fn run_schedules(&mut self) {
+        if self.shutdown.is_some() {
+            return;
+        }
+        let now = Local::now();
+        for w in self.workers.iter_mut() {
+            let timer = match self.timers.get_mut(&w.service().name) {
+                Some(timer) => timer,
+                None => continue,
+            };
+            let busy = w.is_running();
+            if timer.due(now) {
+                if !busy || w.service().overlap == Overlap::Queue {
+                    timer.queued += 1;
+                } else {
+                    eprintln!(
+                        "{}: skipping this run, the last one is still going",
+                        &w.service().name
+                    );
+                }
+            }
+            if !busy && timer.queued > 0 {
+                timer.queued -= 1;
+                if let Err(err) = start_again(w, &mut self.events) {
+                    eprintln!("Unable to run {}", &err);
+                }
+            }
+        }
+    }
+
+    /// Change how many instances of the process types in the formation run. New instances start
+    /// right away. Instances that aren't needed anymore are asked to stop, highest-numbered
+    /// first, and they're killed if they haven't by `SHUTDOWN_TIMEOUT`. Nothing's scaled while the
+    /// server is shutting down, since new instances wouldn't be stopped.
+    fn scale(&mut self, formation: &Formation) -> Result<()> {
+        if self.shutdown.is_some() {
+            return Err(format_err!("Unable to scale while the server is shutting down."));
+        }
+        if let Some(name) = formation
+            .names()
+            .into_iter()
+            .find(|name| !self.process_types.contains_key(*name))
+        {
+            return Err(format_err!(
+                "Unable to scale {}. There's no long-lived service by that name.",
+                name
+            ));
+        }
+
+        let names = self.process_types.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            if let Some(desired) = formation.get(&name) {
+                self.scale_process_type(&name, desired)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn scale_process_type(&mut self, name: &str, desired: usize) -> Result<()> {
+        let service = match self.process_types.get_mut(name) {
+            Some(process_type) => {
+                process_type.desired = desired;
+                process_type.service.clone()
+            }
+            None => return Ok(()),
+        };
+
+        let mut numbers = self
+            .workers
+            .iter()
+            .filter_map(|w| instance_number(name, &w.service().name))
+            .collect::<Vec<_>>();
+        numbers.sort();
+
+        while numbers.len() > desired {
+            let number = numbers.pop().unwrap_or_default();
+            let instance_name = instance(&service, number).name;
+            if let Some(index) = self
+                .workers
+                .iter()
+                .position(|w| w.service().name == instance_name)
+            {
+                let worker = self.workers.remove(index);
+                self.retire(worker)?;
+            }
+        }
+
+        while numbers.len() < desired {
+            let number = numbers.last().cloned().unwrap_or_default() + 1;
+            let mut worker = self.worker(instance(&service, number));
+            if let Err(err) = worker.start() {
+                eprintln!("Unable to scale up {}", &err);
+            }
+            // Keep each process type's instances together, for `status`.
+            let index = self
+                .workers
+                .iter()
+                .rposition(|w| instance_number(name, &w.service().name).is_some())
+                .map(|index| index + 1)
+                .unwrap_or_else(|| self.workers.len());
+            self.workers.insert(index, worker);
+            numbers.push(number);
+        }
+        Ok(())
+    }
+
+    /// Ask an instance that's been scaled away to stop. It's killed if it hasn't exited
+    /// `SHUTDOWN_TIMEOUT` after its `pre-stop` hook.
+    fn retire(&mut self, mut worker: ServiceWorker) -> Result<()> {
+        self.events
+            .publish(&Event::new(EventKind::Stopped, &worker.service().name));
+        terminate(&mut worker)?;
+        self.retiring.push(worker);
+        Ok(())
+    }
+
+    /// Let go of retiring instances that have exited. Their threads aren't waited for, since they
+    /// can still be running `post-exit` hooks.
+    fn check_retiring(&mut self) {
+        for worker in self.retiring.iter_mut().filter(|w| !w.is_running()) {
+            worker.release();
+        }
+        self.retiring.retain(|worker| worker.is_running());
+    }
+
+    /// Follow up on the services that were asked to stop or restart, once they've exited. The
+    /// ones being restarted start again, unless the server's shutting down, and the threads of
+    /// the stopped ones are let go of.
+    fn check_stopping(&mut self) {
+        for w in self.workers.iter_mut().filter(|w| !w.is_running()) {
+            let name = w.service().name.clone();
+            if self.stopping.remove(&name) {
+                w.release();
+            }
+            if self.shutdown.is_none() && self.restarting.remove(&name) {
+                if let Err(err) = start_again(w, &mut self.events) {
+                    eprintln!("Unable to restart {}", &err);
+                }
+            }
+        }
+    }
+
+    /// Tell subscribers that the running services are being stopped.
+    fn publish_stopped(&mut self) {
+        for w in self.workers.iter().filter(|w| w.is_running()) {
+            self.events
+                .publish(&Event::new(EventKind::Stopped, &w.service().name));
+        }
+    }
+
+    /// Run one of the formation's hooks and wait for it. It's only reported if it fails, because
+    /// the server carries on either way.
+    fn run_hook(&self, event: HookEvent, exit_code: Option<i32>) {
+        if let Err(err) = self.options.hooks.run(event, None, exit_code, None) {
+            eprintln!("{}", &err);
+        }
+    }
+
+    fn signal_all(&mut self, signal: i32) -> Result<()> {
+        for w in self.workers.iter_mut().filter(|w| w.is_running()) {
+            w.signal(signal).map_err(|err| {
+                format_err!("Unable to signal {:?}: {:?}", &w.service().name, &err)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Check on a shutdown that's in progress. The services are asked to stop once the formation's
+    /// `pre-stop` hook is done. Their tasks kill the ones that don't stop in time, so this doesn't
+    /// wait on any of them.
+    ///
+    /// # Returns
+    ///
+    /// `false` once the server should stop.
+    fn check_shutdown(&mut self) -> Result<bool> {
+        let pre_stopped = match self.shutdown {
+            None => return Ok(true),
+            Some(Shutdown::PreStop(ref mut hook)) => match hook.try_finish() {
+                Some(result) => {
+                    if let Err(err) = result {
+                        eprintln!("{}", &err);
+                    }
+                    true
+                }
+                None => return Ok(true),
+            },
+            Some(Shutdown::Stopping) => false,
+        };
+        if pre_stopped {
+            self.stop_services()?;
+        }
+        Ok(self.workers.iter().any(|w| w.is_running()))
+    }
+
+    /// Restart the services whose files changed, including ones that have exited since they were
+    /// started. Nothing's restarted while the server is shutting down, and paused services are left
+    /// alone so they don't lose their state.
+    fn restart_changed(&mut self, changed: &BTreeMap<String, PathBuf>) -> Result<()> {
+        if self.shutdown.is_some() {
+            return Ok(());
+        }
+        for w in self.workers.iter_mut().filter(|w| w.thread_id().is_some()) {
+            if let Some(path) = changed.get(&w.service().name) {
+                if w.state() == WorkerState::Paused {
+                    eprintln!("{}: {:?} changed, but it's paused", &w.service().name, &path);
+                    continue;
+                }
+                eprintln!("{}: {:?} changed, restarting", &w.service().name, &path);
+                self.events
+                    .publish(&Event::new(EventKind::Restarting, &w.service().name));
+                restart(w, &mut self.restarting, &mut self.events).map_err(|err| {
+                    format_err!("Unable to restart {:?}: {:?}", &w.service().name, &err)
+                })?;
+                self.stopping.remove(&w.service().name);
+            }
+        }
+        Ok(())
     }
 }
 
 impl ManagerServer for RestManagerServer {
+    /// Lock the PID file and fork into the background. The lock's taken first, so that a second
+    /// daemon fails right away in the foreground. The forked process inherits it.
+    ///
+    /// The original process doesn't exit until the daemon is serving, and it exits with the
+    /// daemon's error if it couldn't start.
     fn daemonize<P: AsRef<Path>>(&mut self, pid_file: P) -> Result<()> {
-        daemonize(&pid_file)?;
-        self.pid_file = Some(PathBuf::from(pid_file.as_ref()));
+        let mut pid_file = self.lock_pid_file(pid_file.as_ref())?;
+        let (waiter, notifier) = ready_pipe()?;
+        let timeout = self.options.startup_timeout.unwrap_or(STARTUP_TIMEOUT);
+        daemonize(move || {
+            if let Err(err) = waiter.wait(timeout) {
+                eprintln!("{}", &err);
+                process::exit(1);
+            }
+        })?;
+        self.ready = Some(notifier);
+        pid_file.write_pid().map_err(|err| self.startup_failed(err))?;
+        self.pid_file = Some(pid_file);
         Ok(())
     }
 
+    /// Start every service, after the formation's `pre-start` hook. The ones that can't be started
+    /// are reported as failed, and the rest keep running. This only fails if none of them could
+    /// start, or if the hook failed.
     fn start_workers(&mut self, workers: Vec<Service>) -> Result<()> {
-        self.workers = workers
+        // A user that doesn't exist is a mistake in the Procfile, so it's caught before anything
+        // runs.
+        for service in &workers {
+            if let Err(err) = service.credentials() {
+                return Err(self.startup_failed(err));
+            }
+        }
+        if let Err(err) = self.options.hooks.run(HookEvent::PreStart, None, None, None) {
+            return Err(self.startup_failed(err));
+        }
+        self.slice = self.create_slice(&workers);
+        let (oneshots, services): (Vec<_>, Vec<_>) = workers
             .into_iter()
-            .map(ServiceWorker::new)
-            .map(|mut w| w.start().and(Ok(w)))
-            .collect::<Result<Vec<_>>>()?;
+            .partition(|service| service.kind == ServiceKind::Oneshot);
+        for service in oneshots {
+            let worker = self.worker(service);
+            let worker = run_oneshot(worker).map_err(|err| self.startup_failed(err))?;
+            self.workers.push(worker);
+        }
+
+        let oneshot_count = self.workers.len();
+        let now = Local::now();
+        for service in services {
+            // Scheduled services wait for their first run.
+            if let Some(ref schedule) = service.schedule {
+                self.timers
+                    .insert(service.name.clone(), Timer::new(schedule.clone(), now));
+                let worker = self.worker(service);
+                self.workers.push(worker);
+                continue;
+            }
+            self.process_types.insert(
+                service.name.clone(),
+                ProcessType {
+                    service: service.clone(),
+                    desired: 1,
+                },
+            );
+            let mut worker = self.worker(service);
+            let _ = worker.start();
+            self.workers.push(worker);
+        }
+
+        let (summary, none_running) = {
+            let services = &self.workers[oneshot_count..];
+            let failed = services
+                .iter()
+                .filter_map(|w| w.error().map(|error| (&w.service().name, error)))
+                .collect::<Vec<_>>();
+            if failed.is_empty() {
+                return Ok(());
+            }
+            let summary = failed.iter().fold(
+                format!(
+                    "Unable to start {} of {} services.",
+                    failed.len(),
+                    services.len()
+                ),
+                |summary, (name, error)| summary + &format!("\n    {}: {}", name, error),
+            );
+            (summary, services.iter().all(|w| w.thread_id().is_none()))
+        };
+
+        if none_running {
+            return Err(self.startup_failed(format_err!("{}", summary)));
+        }
+        eprintln!("{}", &summary);
+        self.startup_failures = Some(summary);
         Ok(())
     }
 
-    fn start_server(&mut self) -> Result<()> {
-        let listener = self.create_listener()?;
+    fn start_server(&mut self) -> Result<i32> {
+        let (tx, rx) = mpsc::channel();
+        let (_watcher, signals) = self
+            .listen(tx)
+            .map_err(|err| self.startup_failed(err))?;
+        if self.options.hooks.get(HookEvent::PostStart).is_some() {
+            let hooks = self.options.hooks.clone();
+            thread::spawn(move || {
+                if let Err(err) = hooks.run(HookEvent::PostStart, None, None, None) {
+                    eprintln!("{}", &err);
+                }
+            });
+        }
+        if let Some(ready) = self.ready.take() {
+            match self.startup_failures {
+                Some(ref summary) => ready.degraded(summary)?,
+                None => ready.ready()?,
+            }
+        }
 
-        for stream in listener.incoming() {
-            let mut stream =
-                stream.map_err(|err| format_err!("Unable to read from listener: {:?}", &err))?;
-            let request: RequestMessage = stream.recv()?;
-            match request {
-                RequestMessage::GetWorkers => {
-                    let response = ResponseMessage::Workers(
-                        self.workers.iter().map(|sw| sw.service().clone()).collect(),
-                    );
-                    stream.send(response)?;
-                }
-                RequestMessage::StopServer => break,
-                RequestMessage::StopTasks(tasks) => {
-                    let tasks = tasks.iter().collect::<HashSet<_>>();
-                    for w in &mut self.workers.iter_mut() {
-                        if tasks.contains(&w.service().name) {
-                            w.kill().map_err(|err| {
-                                format_err!("Unable to kill {:?}: {:?}", &w.service().name, &err)
-                            })?;
-                        }
+        loop {
+            let keep_going = match rx.recv_timeout(TICK) {
+                Ok(ServerEvent::Request(stream)) => {
+                    if let Err(err) = self.handle_request(stream) {
+                        eprintln!("Unable to handle request: {}", &err);
                     }
+                    true
                 }
+                Ok(ServerEvent::FilesChanged(changed)) => {
+                    self.restart_changed(&changed)?;
+                    true
+                }
+                Ok(ServerEvent::Signal(signal)) => self.handle_signal(signal)?,
+                Err(mpsc::RecvTimeoutError::Timeout) => true,
+                Err(mpsc::RecvTimeoutError::Disconnected) => false,
+            };
+            if keep_going {
+                self.check_stopping();
+                self.check_exits()?;
+                self.run_schedules();
+                self.events
+                    .check(self.workers.iter().chain(self.retiring.iter()));
+                self.check_retiring();
+            }
+            if !keep_going || !self.check_shutdown()? {
+                break;
             }
         }
 
-        Ok(())
+        signals.close();
+        // The services that exited while shutting down are the last events.
+        self.events.check(self.workers.iter());
+        self.events.close();
+        // Dropping the workers stops anything that's still running, so the `post-exit` hook runs
+        // once everything's gone.
+        self.workers.clear();
+        self.retiring.clear();
+        // Services that failed to start make the server fail, even though it carried on without
+        // them.
+        let result = match self.startup_failures.take() {
+            Some(summary) => Err(format_err!("{}", summary)),
+            None => Ok(self.exit_code),
+        };
+        let exit_code = result.as_ref().map_or(1, |code| *code);
+        self.run_hook(HookEvent::PostExit, Some(exit_code));
+        result
     }
 }
 
 impl Drop for RestManagerServer {
     fn drop(&mut self) {
-        // Eating the errors b/c we're trying to shutdown.
-        if self.socket_path.exists() {
+        // Eating the errors b/c we're trying to shutdown. The socket's only removed if this server
+        // opened it, so that one that failed to start doesn't remove a running server's.
+        if self.owns_socket && self.socket_path.exists() {
             let _ = fs::remove_file(&self.socket_path);
         }
-        self.pid_file.take().into_iter().for_each(|pid_file| {
-            if pid_file.exists() {
-                let _ = fs::remove_file(pid_file);
-            }
-        });
+        // Dropping this removes the PID file and releases the lock.
+        self.pid_file.take();
+    }
+}
+
+/// An instance of a process type. The first keeps the process type's name, and the others are
+/// numbered after it, like `web.2`. Each one's `PORT` is one above the last's.
+fn instance(service: &Service, number: usize) -> Service {
+    let mut service = Instance::new(service, number).service;
+    if number > 1 {
+        service.name = format!("{}.{}", &service.name, number);
+    }
+    service
+}
+
+/// The instance number of a worker, if it's an instance of the process type.
+fn instance_number(process_type: &str, name: &str) -> Option<usize> {
+    if name == process_type {
+        return Some(1);
+    }
+    if name.starts_with(process_type) && name[process_type.len()..].starts_with('.') {
+        name[process_type.len() + 1..]
+            .parse()
+            .ok()
+            .filter(|number| *number > 1)
+    } else {
+        None
+    }
+}
+
+/// Ask a worker's task to stop with `SIGTERM`, once its `pre-stop` hook has run. It's killed if it
+/// hasn't exited `SHUTDOWN_TIMEOUT` after that. A paused task is resumed, since it can't act on
+/// the signal until it is.
+fn terminate(worker: &mut ServiceWorker) -> Result<()> {
+    worker.terminate(SIGTERM, SHUTDOWN_TIMEOUT)?;
+    if worker.state() == WorkerState::Paused {
+        worker.resume()?;
+    }
+    Ok(())
+}
+
+/// Restart a worker without waiting for it. A running task is asked to stop, and it's added to
+/// `restarting` so that it's started again once it's exited. One that isn't running starts right
+/// away.
+fn restart(
+    worker: &mut ServiceWorker,
+    restarting: &mut HashSet<String>,
+    events: &mut Events,
+) -> Result<()> {
+    if !worker.is_running() {
+        return start_again(worker, events);
+    }
+    terminate(worker)?;
+    restarting.insert(worker.service().name.clone());
+    Ok(())
+}
+
+/// Start a worker's task again after it's exited. How the last run exited is published first,
+/// because the new run replaces it. The last run's thread is let go of rather than waited for, so
+/// that its `post-exit` hooks don't hold up the server.
+fn start_again(worker: &mut ServiceWorker, events: &mut Events) -> Result<()> {
+    events.check(iter::once(&*worker));
+    worker.release();
+    worker.start()
+}
+
+/// Run a one-shot task and wait for it to finish.
+///
+/// # Returns
+///
+/// The finished worker, so that it can be reported as completed. If the task couldn't be started
+/// or didn't succeed, this is an error with how it exited and the end of its stderr.
+fn run_oneshot(mut worker: ServiceWorker) -> Result<ServiceWorker> {
+    worker
+        .start()
+        .and_then(|_| worker.join())
+        .map_err(|err| format_err!("Unable to run one-shot task {}", &err))?;
+
+    let name = worker.service().name.clone();
+    match worker.exit_info() {
+        Some(ref exit) if exit.code == Some(0) => Ok(worker),
+        Some(exit) => Err(format_err!(
+            "One-shot task {} exited with {}.{}",
+            &name,
+            exit.describe(),
+            exit.stderr
+                .iter()
+                .fold(String::new(), |output, line| output + "\n    " + line)
+        )),
+        None => Err(format_err!("One-shot task {} didn't report how it exited.", &name)),
     }
 }
 
@@ -3,15 +3,24 @@ use client::local::RestManagerClient;
 use client::ManagerClient;
 use reqwest;
 use server::local::RestManagerServer;
-use server::ManagerServer;
-use service::Service;
+use server::events::{EventFilter, EventKind};
+use server::pid_file::{PidFile, PidLock};
+use server::watch::DEBOUNCE;
+use server::{ExitOn, ManagerServer, ProcessScale, ServerOptions};
+use service::hooks::Hooks;
+use service::worker::{Worker, WorkerState, WorkerStatus};
+use service::schedule::Overlap;
+use service::{Service, ServiceKind};
+use signal_hook::consts::signal::{SIGINT, SIGTERM, SIGUSR1};
 use spectral::prelude::*;
 use std::env;
 use std::fs;
+use std::os::unix::net::UnixListener;
 use std::path::PathBuf;
 use std::process;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tasks::TaskSpec;
 
 fn setup(name: &str) -> PathBuf {
@@ -87,10 +96,18 @@ fn test_get_workers_response_with_workers() {
     assert_that(&socket_path).exists();
 
     let client = RestManagerClient::at_path(socket_path.clone());
-    let workers = client.get_workers();
-    assert_that(&workers)
-        .is_ok()
-        .is_equal_to(&vec![Service::new("python", "python3 -m http.server 3040")]);
+    let workers = client.get_workers().unwrap();
+    assert_that(&workers).has_length(1);
+    let mut worker = workers[0].clone();
+    assert_that(&worker.pid).is_some();
+    assert_that(&worker.started_at).is_some();
+    worker.pid = None;
+    worker.usage = None;
+    worker.started_at = None;
+    assert_that(&worker).is_equal_to(&WorkerStatus::new(
+        Service::new("python", "python3 -m http.server 3040"),
+        WorkerState::Running,
+    ));
 
     assert_that(&client.stop(TaskSpec::All)).is_ok();
     assert_that(&handle.join()).is_ok();
@@ -185,6 +202,8 @@ fn test_stops_tasks_when_stop_task() {
     client
         .stop(TaskSpec::List(vec![String::from("web1")]))
         .unwrap();
+    // The server doesn't wait for web1 to exit before it answers.
+    thread::sleep(Duration::from_millis(500));
 
     assert_that(&reqwest::get("http://localhost:9875/")).is_err();
     assert_that(&reqwest::get("http://localhost:9876/")).is_ok();
@@ -192,3 +211,768 @@ fn test_stops_tasks_when_stop_task() {
     client.stop(TaskSpec::All).unwrap();
     handle.join().unwrap();
 }
+
+#[test]
+fn test_stops_every_instance_of_a_task() {
+    let socket_path = setup("test_stops_every_instance_of_a_task");
+    let server_socket = socket_path.clone();
+
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![
+                Service::new("web", "sleep 30"),
+                Service::new("worker", "sleep 30"),
+            ]).unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    client.scale("web=2".parse().unwrap()).unwrap();
+    client.stop(TaskSpec::List(vec![String::from("web")])).unwrap();
+    thread::sleep(Duration::from_millis(500));
+
+    let states = client
+        .get_workers()
+        .unwrap()
+        .into_iter()
+        .map(|w| (w.service.name, w.state))
+        .collect::<Vec<_>>();
+    assert_that(&states).is_equal_to(vec![
+        (String::from("web"), WorkerState::Stopped),
+        (String::from("web.2"), WorkerState::Stopped),
+        (String::from("worker"), WorkerState::Running),
+    ]);
+
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_restarts_workers_when_watched_files_change() {
+    let socket_path = setup("test_restarts_workers_when_watched_files_change");
+    let server_socket = socket_path.clone();
+    let dir = env::temp_dir().join(format!("straw-boss.restarts-workers.{}", process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut service = Service::new("web", "sh -c 'echo started >> starts; sleep 30'");
+    service.cwd = Some(dir.clone());
+    service.watch = vec![String::from("*.rb")];
+    let handle = thread::spawn(move || {
+        let options = ServerOptions {
+            watch: true,
+            ..ServerOptions::default()
+        };
+        let mut server = RestManagerServer::with_options(server_socket, options);
+        server.start_workers(vec![service]).unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    fs::write(dir.join("app.rb"), b"puts 1\n").unwrap();
+    thread::sleep(DEBOUNCE + Duration::from_secs(1));
+
+    let starts = fs::read_to_string(dir.join("starts")).unwrap();
+    assert_that(&starts.lines().count()).is_equal_to(2);
+
+    let client = RestManagerClient::at_path(socket_path.clone());
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_passes_signals_to_workers() {
+    let socket_path = setup("test_passes_signals_to_workers");
+    let tmpfile = env::temp_dir().join(format!("straw-boss.passes-signals.{}", process::id()));
+    if tmpfile.exists() {
+        fs::remove_file(&tmpfile).unwrap();
+    }
+
+    let mut server = RestManagerServer::at_path(socket_path);
+    let command = format!(
+        "sh -c 'trap \"touch {}\" USR1; while true; do sleep 0.1; done'",
+        tmpfile.to_string_lossy()
+    );
+    server
+        .start_workers(vec![Service::new("trap", &command)])
+        .unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    assert_that(&server.handle_signal(SIGUSR1)).is_ok().is_true();
+    thread::sleep(Duration::from_millis(500));
+    assert_that(&tmpfile).exists();
+
+    fs::remove_file(&tmpfile).unwrap();
+}
+
+#[test]
+fn test_interrupt_stops_workers() {
+    let socket_path = setup("test_interrupt_stops_workers");
+    let mut server = RestManagerServer::at_path(socket_path);
+    server
+        .start_workers(vec![Service::new("sleep", "sleep 30")])
+        .unwrap();
+
+    assert_that(&server.handle_signal(SIGINT)).is_ok().is_true();
+    thread::sleep(Duration::from_millis(200));
+    assert_that(&server.check_shutdown()).is_ok().is_false();
+}
+
+#[test]
+fn test_second_interrupt_kills_workers() {
+    let socket_path = setup("test_second_interrupt_kills_workers");
+    let mut server = RestManagerServer::at_path(socket_path);
+    server
+        .start_workers(vec![Service::new(
+            "stubborn",
+            "sh -c 'trap \"\" TERM INT; while true; do sleep 0.1; done'",
+        )]).unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    assert_that(&server.handle_signal(SIGINT)).is_ok().is_true();
+    thread::sleep(Duration::from_millis(200));
+    assert_that(&server.check_shutdown()).is_ok().is_true();
+
+    assert_that(&server.handle_signal(SIGINT)).is_ok().is_false();
+    thread::sleep(Duration::from_millis(200));
+    assert_that(&server.workers[0].try_wait().unwrap().is_some()).is_true();
+}
+
+#[test]
+fn test_interrupt_does_not_wait_for_pre_stop_hook() {
+    let socket_path = setup("test_interrupt_does_not_wait_for_pre_stop_hook");
+    let mut hooks = Hooks::default();
+    hooks.set("pre-stop", "sleep 1").unwrap();
+    let options = ServerOptions {
+        hooks,
+        ..ServerOptions::default()
+    };
+    let mut server = RestManagerServer::with_options(socket_path, options);
+    server
+        .start_workers(vec![Service::new("sleep", "sleep 30")])
+        .unwrap();
+
+    let start = Instant::now();
+    assert_that(&server.handle_signal(SIGINT)).is_ok().is_true();
+    assert_that(&server.check_shutdown()).is_ok().is_true();
+    assert_that(&(Instant::now() - start)).is_less_than(Duration::from_millis(500));
+    // The services are only asked to stop once the hook's done.
+    thread::sleep(Duration::from_millis(300));
+    assert_that(&server.workers[0].is_running()).is_true();
+
+    thread::sleep(Duration::from_millis(1000));
+    assert_that(&server.check_shutdown()).is_ok().is_true();
+    thread::sleep(Duration::from_millis(300));
+    assert_that(&server.check_shutdown()).is_ok().is_false();
+}
+
+#[test]
+fn test_stop_server_does_not_wait_for_pre_stop_hook() {
+    let socket_path = setup("test_stop_server_does_not_wait_for_pre_stop_hook");
+    let server_socket = socket_path.clone();
+    let handle = thread::spawn(move || {
+        let mut hooks = Hooks::default();
+        hooks.set("pre-stop", "sleep 1").unwrap();
+        let options = ServerOptions {
+            hooks,
+            ..ServerOptions::default()
+        };
+        let mut server = RestManagerServer::with_options(server_socket, options);
+        server
+            .start_workers(vec![Service::new("sleep", "sleep 30")])
+            .unwrap();
+        server.start_server().unwrap()
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    client.stop(TaskSpec::All).unwrap();
+    let start = Instant::now();
+    assert_that(&client.get_workers()).is_ok();
+    assert_that(&(Instant::now() - start)).is_less_than(Duration::from_millis(500));
+    assert_that(&handle.join().unwrap()).is_equal_to(0);
+}
+
+#[test]
+fn test_restart_does_not_wait_for_pre_stop_hook() {
+    let socket_path = setup("test_restart_does_not_wait_for_pre_stop_hook");
+    let mut service = Service::new("sleep", "sleep 30");
+    service.hooks.set("pre-stop", "sleep 1").unwrap();
+    let mut server = RestManagerServer::at_path(socket_path);
+    server.start_workers(vec![service]).unwrap();
+    let changed = vec![(String::from("sleep"), PathBuf::from("app.rb"))]
+        .into_iter()
+        .collect();
+
+    let start = Instant::now();
+    assert_that(&server.restart_changed(&changed)).is_ok();
+    server.check_stopping();
+    assert_that(&(Instant::now() - start)).is_less_than(Duration::from_millis(500));
+    assert_that(&server.workers[0].status().restarts).is_equal_to(0);
+
+    // It starts again once the hook's done and it's exited.
+    thread::sleep(Duration::from_millis(1500));
+    server.check_stopping();
+    assert_that(&server.workers[0].status().restarts).is_equal_to(1);
+    assert_that(&server.workers[0].is_running()).is_true();
+}
+
+#[test]
+fn test_signals_tasks_when_signal() {
+    let socket_path = setup("test_signals_tasks_when_signal");
+    let server_socket = socket_path.clone();
+    let tmpfile = env::temp_dir().join(format!("straw-boss.signals-tasks.{}", process::id()));
+    if tmpfile.exists() {
+        fs::remove_file(&tmpfile).unwrap();
+    }
+
+    let command = format!("sh fixtures/signal/grandchild.sh {}", tmpfile.to_string_lossy());
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![
+                Service::new("trap.1", &command),
+                Service::new("sleep", "sleep 30"),
+            ]).unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    client
+        .signal(TaskSpec::List(vec![String::from("trap")]), SIGUSR1)
+        .unwrap();
+    thread::sleep(Duration::from_millis(500));
+
+    assert_that(&tmpfile).exists();
+
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+    fs::remove_file(&tmpfile).unwrap();
+}
+
+#[test]
+fn test_pauses_and_resumes_tasks() {
+    let socket_path = setup("test_pauses_and_resumes_tasks");
+    let server_socket = socket_path.clone();
+    let tmpfile = env::temp_dir().join(format!("straw-boss.pauses-tasks.{}", process::id()));
+    if tmpfile.exists() {
+        fs::remove_file(&tmpfile).unwrap();
+    }
+
+    let command = format!(
+        "sh -c 'while true; do echo tick >> {}; sleep 0.1; done'",
+        tmpfile.to_string_lossy()
+    );
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![Service::new("ticker", &command)])
+            .unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    let ticker = || TaskSpec::List(vec![String::from("ticker")]);
+    let ticks = || fs::read_to_string(&tmpfile).unwrap().lines().count();
+
+    client.pause(ticker()).unwrap();
+    let workers = client.get_workers().unwrap();
+    assert_that(&workers[0].state).is_equal_to(WorkerState::Paused);
+    let paused_at = ticks();
+    thread::sleep(Duration::from_millis(500));
+    assert_that(&ticks()).is_equal_to(paused_at);
+
+    client.resume(ticker()).unwrap();
+    let workers = client.get_workers().unwrap();
+    assert_that(&workers[0].state).is_equal_to(WorkerState::Running);
+    thread::sleep(Duration::from_millis(500));
+    assert_that(&ticks()).is_greater_than(paused_at);
+
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+    fs::remove_file(&tmpfile).unwrap();
+}
+
+#[test]
+fn test_removes_stale_socket() {
+    let socket_path = setup("test_removes_stale_socket");
+    drop(UnixListener::bind(&socket_path).unwrap());
+    assert_that(&socket_path).exists();
+
+    let mut server = RestManagerServer::at_path(socket_path.clone());
+    assert_that(&server.create_listener()).is_ok();
+}
+
+#[test]
+fn test_leaves_live_socket_alone() {
+    let socket_path = setup("test_leaves_live_socket_alone");
+    let _listener = UnixListener::bind(&socket_path).unwrap();
+
+    {
+        let mut server = RestManagerServer::at_path(socket_path.clone());
+        assert_that(&server.create_listener()).is_err();
+    }
+
+    assert_that(&socket_path).exists();
+    fs::remove_file(&socket_path).unwrap();
+}
+
+#[test]
+fn test_refuses_to_start_when_pid_file_locked() {
+    let socket_path = setup("test_refuses_to_start_when_pid_file_locked");
+    let pid_path = env::temp_dir().join(format!("straw-boss.locked.{}.pid", process::id()));
+    let _lock = match PidFile::try_lock(&pid_path).unwrap() {
+        PidLock::Acquired(mut pid_file) => {
+            pid_file.write_pid().unwrap();
+            pid_file
+        }
+        PidLock::Held(_) => panic!("Expected to lock {:?}.", &pid_path),
+    };
+
+    let mut server = RestManagerServer::at_path(socket_path);
+    let result = server.daemonize(&pid_path);
+    assert_that(&result).is_err();
+    let message = result.unwrap_err().to_string();
+    assert_that(&message.as_str()).contains(
+        format!("already running as PID {} on socket", process::id()).as_str(),
+    );
+}
+
+#[test]
+fn test_replaces_running_server() {
+    let socket_path = setup("test_replaces_running_server");
+    let server_socket = socket_path.clone();
+
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server.start_server().unwrap();
+    });
+    thread::sleep(Duration::from_secs(1));
+
+    let options = ServerOptions {
+        replace: true,
+        ..ServerOptions::default()
+    };
+    let mut server = RestManagerServer::with_options(socket_path.clone(), options);
+    assert_that(&server.create_listener()).is_ok();
+    assert_that(&handle.join()).is_ok();
+    assert_that(&socket_path).exists();
+}
+
+#[test]
+fn test_keeps_running_services_that_started() {
+    let socket_path = setup("test_keeps_running_services_that_started");
+    let server_socket = socket_path.clone();
+
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![
+                Service::new("sleeper", "sleep 30"),
+                Service::new("missing", "straw-boss-no-such-command"),
+            ]).unwrap();
+        server.start_server()
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    let workers = client.get_workers().unwrap();
+    assert_that(&workers[0].state).is_equal_to(WorkerState::Running);
+    assert_that(&workers[1].state).is_equal_to(WorkerState::Failed);
+    assert_that(&workers[1].error)
+        .is_some()
+        .contains("straw-boss-no-such-command");
+
+    client.stop(TaskSpec::All).unwrap();
+    let result = handle.join().unwrap();
+    assert_that(&result).is_err();
+    assert_that(&result.unwrap_err().to_string().as_str())
+        .starts_with("Unable to start 1 of 2 services.");
+}
+
+#[test]
+fn test_fails_when_no_services_start() {
+    let socket_path = setup("test_fails_when_no_services_start");
+    let mut server = RestManagerServer::at_path(socket_path);
+    let result = server.start_workers(vec![Service::new("missing", "straw-boss-no-such-command")]);
+    assert_that(&result).is_err();
+}
+
+#[test]
+fn test_runs_without_cgroups_when_slice_is_missing() {
+    let socket_path = setup("test_runs_without_cgroups_when_slice_is_missing");
+    let options = ServerOptions {
+        cgroup: Some(PathBuf::from("/tmp/straw-boss-no-such-cgroup")),
+        ..ServerOptions::default()
+    };
+    let mut server = RestManagerServer::with_options(socket_path, options);
+    let mut service = Service::new("sleeper", "sleep 30");
+    service.cgroup.set("memory.max", "64M").unwrap();
+    assert_that(&server.start_workers(vec![service])).is_ok();
+    assert_that(&server.workers[0].state()).is_equal_to(WorkerState::Running);
+}
+
+#[test]
+fn test_fails_when_user_is_missing() {
+    let socket_path = setup("test_fails_when_user_is_missing");
+    let mut server = RestManagerServer::at_path(socket_path);
+    let mut service = Service::new("sleeper", "sleep 30");
+    service.user = Some(String::from("straw-boss-no-such-user"));
+    let result = server.start_workers(vec![service]);
+    assert_that(&result).is_err();
+    assert_that(&server.workers).is_empty();
+}
+
+#[test]
+fn test_runs_formation_hooks() {
+    let socket_path = setup("test_runs_formation_hooks");
+    let server_socket = socket_path.clone();
+    let output = env::temp_dir().join(format!("straw-boss-hooks-{}.log", process::id()));
+    let mut hooks = Hooks::default();
+    for event in &["pre-start", "pre-stop", "post-exit"] {
+        let command = format!("echo $STRAWBOSS_EVENT $STRAWBOSS_EXIT_CODE >> {:?}", &output);
+        hooks.set(event, &command).unwrap();
+    }
+
+    let handle = thread::spawn(move || {
+        let options = ServerOptions {
+            hooks,
+            ..ServerOptions::default()
+        };
+        let mut server = RestManagerServer::with_options(server_socket, options);
+        server
+            .start_workers(vec![Service::new("sleeper", "sleep 30")])
+            .unwrap();
+        server.start_server()
+    });
+    thread::sleep(Duration::from_secs(1));
+    RestManagerClient::at_path(socket_path)
+        .stop(TaskSpec::All)
+        .unwrap();
+    assert_that(&handle.join().unwrap()).is_ok().is_equal_to(0);
+
+    let lines = fs::read_to_string(&output).unwrap();
+    let _ = fs::remove_file(&output);
+    assert_that(&lines.as_str()).is_equal_to("pre-start\npre-stop\npost-exit 0\n");
+}
+
+#[test]
+fn test_fails_when_pre_start_hook_fails() {
+    let socket_path = setup("test_fails_when_pre_start_hook_fails");
+    let mut hooks = Hooks::default();
+    hooks.set("pre-start", "exit 2").unwrap();
+    let options = ServerOptions {
+        hooks,
+        ..ServerOptions::default()
+    };
+    let mut server = RestManagerServer::with_options(socket_path, options);
+    let result = server.start_workers(vec![Service::new("sleeper", "sleep 30")]);
+    assert_that(&result.unwrap_err().to_string().as_str())
+        .is_equal_to("The pre-start hook for the formation exited with code 2.");
+    assert_that(&server.workers).is_empty();
+}
+
+fn oneshot(name: &str, command: &str) -> Service {
+    let mut service = Service::new(name, command);
+    service.kind = ServiceKind::Oneshot;
+    service
+}
+
+#[test]
+fn test_runs_oneshots_before_services() {
+    let socket_path = setup("test_runs_oneshots_before_services");
+    let marker = env::temp_dir().join(format!("straw-boss.oneshot.{}", process::id()));
+    if marker.exists() {
+        fs::remove_file(&marker).unwrap();
+    }
+
+    let mut server = RestManagerServer::at_path(socket_path);
+    server
+        .start_workers(vec![
+            Service::new("web", &format!("sh -c 'test -f {} && exec sleep 30'", marker.display())),
+            oneshot("migrate", &format!("sh -c 'sleep 0.5; touch {}'", marker.display())),
+        ]).unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    let states = server.workers.iter().map(|w| w.state()).collect::<Vec<_>>();
+    assert_that(&states).is_equal_to(vec![WorkerState::Completed, WorkerState::Running]);
+
+    fs::remove_file(&marker).unwrap();
+}
+
+#[test]
+fn test_stops_when_oneshot_fails() {
+    let socket_path = setup("test_stops_when_oneshot_fails");
+    let mut server = RestManagerServer::at_path(socket_path);
+    let result = server.start_workers(vec![
+        oneshot("migrate", "sh -c 'echo no database >&2; exit 2'"),
+        oneshot("seed", "true"),
+        Service::new("web", "sleep 30"),
+    ]);
+
+    let message = result.unwrap_err().to_string();
+    assert_that(&message.as_str()).starts_with("One-shot task migrate exited with code 2 at ");
+    assert_that(&message.as_str()).ends_with("\n    no database");
+    assert_that(&server.workers).is_empty();
+}
+
+fn scheduled(name: &str, command: &str, overlap: Overlap) -> Service {
+    let mut service = Service::new(name, command);
+    service.schedule = Some("@every 1s".parse().unwrap());
+    service.overlap = overlap;
+    service
+}
+
+#[test]
+fn test_runs_scheduled_services() {
+    let socket_path = setup("test_runs_scheduled_services");
+    let mut server = RestManagerServer::at_path(socket_path);
+    server
+        .start_workers(vec![scheduled("tick", "sh -c 'exit 3'", Overlap::Skip)])
+        .unwrap();
+    assert_that(&server.workers[0].state()).is_equal_to(WorkerState::Scheduled);
+
+    for _ in 0..25 {
+        thread::sleep(Duration::from_millis(100));
+        server.run_schedules();
+    }
+    thread::sleep(Duration::from_millis(500));
+
+    let status = server.workers[0].status();
+    assert_that(&status.state).is_equal_to(WorkerState::Scheduled);
+    assert_that(&status.runs).has_length(2);
+    assert_that(&status.runs[0].code).is_equal_to(Some(3));
+}
+
+#[test]
+fn test_skips_or_queues_overlapping_runs() {
+    let socket_path = setup("test_skips_or_queues_overlapping_runs");
+    let mut server = RestManagerServer::at_path(socket_path);
+    server
+        .start_workers(vec![
+            scheduled("skip", "sleep 30", Overlap::Skip),
+            scheduled("queue", "sleep 30", Overlap::Queue),
+        ]).unwrap();
+
+    for _ in 0..25 {
+        thread::sleep(Duration::from_millis(100));
+        server.run_schedules();
+    }
+
+    let states = server.workers.iter().map(|w| w.state()).collect::<Vec<_>>();
+    assert_that(&states).is_equal_to(vec![WorkerState::Running, WorkerState::Running]);
+    assert_that(&server.timers["skip"].queued).is_equal_to(0);
+    assert_that(&server.timers["queue"].queued).is_equal_to(1);
+}
+
+#[test]
+fn test_scales_services() {
+    let socket_path = setup("test_scales_services");
+    let server_socket = socket_path.clone();
+
+    let handle = thread::spawn(move || {
+        let mut web = Service::new("web", "sleep 30");
+        web.env.insert(String::from("PORT"), String::from("5000"));
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![web, Service::new("worker", "sleep 30")])
+            .unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    client.scale("web=3".parse().unwrap()).unwrap();
+    let workers = client.get_workers().unwrap();
+    let names = workers
+        .iter()
+        .map(|w| w.service.name.as_str())
+        .collect::<Vec<_>>();
+    assert_that(&names).is_equal_to(vec!["web", "web.2", "web.3", "worker"]);
+    assert_that(&workers[2].service.env["PORT"]).is_equal_to(String::from("5002"));
+
+    client.scale("web=1,worker=0".parse().unwrap()).unwrap();
+    thread::sleep(Duration::from_millis(500));
+    let names = client
+        .get_workers()
+        .unwrap()
+        .into_iter()
+        .map(|w| w.service.name)
+        .collect::<Vec<_>>();
+    assert_that(&names).is_equal_to(vec![String::from("web")]);
+    assert_that(&client.get_scale().unwrap()).is_equal_to(vec![
+        ProcessScale {
+            name: String::from("web"),
+            desired: 1,
+            running: 1,
+        },
+        ProcessScale {
+            name: String::from("worker"),
+            desired: 0,
+            running: 0,
+        },
+    ]);
+
+    assert_that(&client.scale("clock=2".parse().unwrap())).is_err();
+
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_does_not_scale_while_shutting_down() {
+    let socket_path = setup("test_does_not_scale_while_shutting_down");
+    let mut server = RestManagerServer::at_path(socket_path);
+    server
+        .start_workers(vec![Service::new("web", "sleep 30")])
+        .unwrap();
+
+    assert_that(&server.handle_signal(SIGINT)).is_ok().is_true();
+    assert_that(&server.scale(&"web=2".parse().unwrap())).is_err();
+    assert_that(&server.workers.len()).is_equal_to(1);
+}
+
+#[test]
+fn test_reports_exit_info() {
+    let socket_path = setup("test_reports_exit_info");
+    let server_socket = socket_path.clone();
+
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![
+                Service::new("sleeper", "sleep 30"),
+                Service::new("quick", "sh -c 'echo bye >&2; exit 4'"),
+            ]).unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    let exits = client.get_exit_info().unwrap();
+    assert_that(&exits.keys().collect::<Vec<_>>()).is_equal_to(vec![&String::from("quick")]);
+    assert_that(&exits["quick"].code).is_equal_to(Some(4));
+    assert_that(&exits["quick"].stderr).is_equal_to(vec![String::from("bye")]);
+
+    let workers = client.get_workers().unwrap();
+    assert_that(&workers[1].state).is_equal_to(WorkerState::Stopped);
+    assert_that(&workers[1].exit).is_equal_to(Some(exits["quick"].clone()));
+
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_exits_with_first_service_to_exit() {
+    let socket_path = setup("test_exits_with_first_service_to_exit");
+    let options = ServerOptions {
+        exit_on: Some(ExitOn::AnyExit),
+        ..ServerOptions::default()
+    };
+    let mut server = RestManagerServer::with_options(socket_path, options);
+    server
+        .start_workers(vec![
+            Service::new("sleeper", "sleep 30"),
+            Service::new("quick", "sh -c 'sleep 0.5; exit 3'"),
+        ]).unwrap();
+
+    let started = Utc::now();
+    assert_that(&server.start_server()).is_ok_containing(3);
+    assert_that(&(Utc::now() - started).num_seconds()).is_less_than(5);
+}
+
+#[test]
+fn test_exit_on_failure_ignores_success() {
+    let socket_path = setup("test_exit_on_failure_ignores_success");
+    let options = ServerOptions {
+        exit_on: Some(ExitOn::Failure),
+        ..ServerOptions::default()
+    };
+    let mut server = RestManagerServer::with_options(socket_path, options);
+    server
+        .start_workers(vec![
+            Service::new("sleeper", "sleep 30"),
+            Service::new("setup", "true"),
+            Service::new("broken", "sh -c 'sleep 1; kill -TERM $$'"),
+        ]).unwrap();
+
+    assert_that(&server.start_server()).is_ok_containing(143);
+}
+
+#[test]
+fn test_streams_events_to_subscribers() {
+    let socket_path = setup("test_streams_events_to_subscribers");
+    let server_socket = socket_path.clone();
+
+    let handle = thread::spawn(move || {
+        let mut server = RestManagerServer::at_path(server_socket);
+        server
+            .start_workers(vec![
+                Service::new("web", "sleep 30"),
+                Service::new("worker", "sleep 30"),
+            ]).unwrap();
+        server.start_server().unwrap();
+    });
+
+    thread::sleep(Duration::from_secs(1));
+    let subscribe = |filter: EventFilter| {
+        let events = Arc::new(Mutex::new(vec![]));
+        let subscriber_events = events.clone();
+        let client = RestManagerClient::at_path(socket_path.clone());
+        let subscriber = thread::spawn(move || {
+            client.subscribe(filter, |event| {
+                subscriber_events.lock().unwrap().push(event);
+                true
+            })
+        });
+        (events, subscriber)
+    };
+    let (web_events, web_subscriber) = subscribe(EventFilter {
+        tasks: TaskSpec::List(vec![String::from("web")]),
+        kinds: vec![],
+    });
+    let (stopped_events, stopped_subscriber) = subscribe(EventFilter {
+        tasks: TaskSpec::All,
+        kinds: vec![EventKind::Stopped],
+    });
+
+    thread::sleep(Duration::from_millis(500));
+    let client = RestManagerClient::at_path(socket_path.clone());
+    client.restart(TaskSpec::All).unwrap();
+    thread::sleep(Duration::from_millis(500));
+    client
+        .signal(TaskSpec::List(vec![String::from("web")]), SIGTERM)
+        .unwrap();
+    thread::sleep(Duration::from_millis(500));
+    client.stop(TaskSpec::All).unwrap();
+    handle.join().unwrap();
+    assert_that(&web_subscriber.join().unwrap()).is_ok();
+    assert_that(&stopped_subscriber.join().unwrap()).is_ok();
+
+    let web_events = web_events.lock().unwrap();
+    let kinds = web_events.iter().map(|event| event.kind).collect::<Vec<_>>();
+    assert_that(&kinds).is_equal_to(vec![
+        EventKind::Restarting,
+        EventKind::Exited,
+        EventKind::Started,
+        EventKind::Exited,
+    ]);
+    assert_that(&web_events.iter().all(|event| event.service == "web")).is_true();
+    // The run that was restarted was asked to stop.
+    assert_that(&web_events[1].signal).is_equal_to(Some(SIGTERM));
+    assert_that(&web_events[2].pid).is_some();
+    assert_that(&web_events[3].signal).is_equal_to(Some(SIGTERM));
+
+    // The web service had already exited, so only the worker was stopped.
+    let stopped_events = stopped_events.lock().unwrap();
+    assert_that(&stopped_events.len()).is_equal_to(1);
+    assert_that(&stopped_events[0].kind).is_equal_to(EventKind::Stopped);
+    assert_that(&stopped_events[0].service.as_str()).is_equal_to("worker");
+}
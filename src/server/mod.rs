@@ -1,11 +1,24 @@
 use daemonize::Daemonize;
+use formation::Formation;
+use server::events::{Event, EventFilter};
+use service::exit::ExitInfo;
+use service::hooks::Hooks;
+use service::worker::WorkerStatus;
 use service::Service;
+use std::collections::BTreeMap;
 use std::env;
+use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tasks::TaskSpec;
 use Result;
 
+pub mod events;
 pub mod local;
+pub mod pid_file;
+pub mod ready;
 pub mod start;
+pub mod watch;
 
 #[derive(Debug)]
 pub enum ServerRunMode {
@@ -13,35 +26,139 @@ pub enum ServerRunMode {
     Daemon(PathBuf),
 }
 
-pub fn daemonize<P: AsRef<Path>>(pid_file: P) -> Result<()> {
+/// Settings for how the server manages its workers.
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Restart services when the files matching their `watch` globs change.
+    pub watch: bool,
+    /// The `Procfile` the services came from. This is only reported to clients.
+    pub procfile: Option<PathBuf>,
+    /// If another server is already running, stop it and take over instead of failing.
+    pub replace: bool,
+    /// How long `start --daemon` waits for the daemon to be serving. This defaults to
+    /// `ready::STARTUP_TIMEOUT`.
+    pub startup_timeout: Option<Duration>,
+    /// Stop everything when a service exits like this.
+    pub exit_on: Option<ExitOn>,
+    /// The delegated cgroup v2 slice to run each service in a cgroup of its own in. Without one,
+    /// services don't get cgroups.
+    pub cgroup: Option<PathBuf>,
+    /// Commands to run before the services start, once they have, before they're stopped, and
+    /// after they've all exited.
+    pub hooks: Hooks,
+}
+
+/// Which service exits stop the server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitOn {
+    /// Any service exiting, for any reason.
+    AnyExit,
+    /// A service exiting with a non-zero code or because of a signal.
+    Failure,
+}
+
+impl ExitOn {
+    /// Should this exit stop the server?
+    pub fn matches(self, exit: &ExitInfo) -> bool {
+        match self {
+            ExitOn::AnyExit => true,
+            ExitOn::Failure => exit.code != Some(0),
+        }
+    }
+}
+
+/// What a server reports about itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct DaemonInfo {
+    pub procfile: PathBuf,
+    pub pid: u32,
+}
+
+/// How many instances of a long-lived process type the server is meant to run, and how many are
+/// running.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Eq)]
+pub struct ProcessScale {
+    pub name: String,
+    pub desired: usize,
+    pub running: usize,
+}
+
+/// Things that the server's event loop responds to.
+#[derive(Debug)]
+pub enum ServerEvent {
+    /// A client connected and is about to send a `RequestMessage`.
+    Request(UnixStream),
+    /// Files changed. This maps each service that needs to restart to the first file that
+    /// changed for it.
+    FilesChanged(BTreeMap<String, PathBuf>),
+    /// The server received a signal.
+    Signal(i32),
+}
+
+/// Fork into the background. The PID file is handled separately, so that it can be locked before
+/// forking. `exit_action` runs in the original process just before it exits.
+pub fn daemonize<F: FnOnce() + 'static>(exit_action: F) -> Result<()> {
     let cwd = env::current_dir()
         .map_err(|err| format_err!("Unable to get current working directory: {:?}", &err))?;
 
     Daemonize::new()
-        .pid_file(pid_file)
         .working_directory(cwd)
+        .exit_action(exit_action)
         .start()
         .map_err(|err| format_err!("Unable to start daemon: {:?}", &err))
 }
 
 // TODO: Compose in a manager to run the workers. Don't have the server do it.
 pub trait ManagerServer {
-    fn daemonize<P: AsRef<Path>>(&mut self, pid_file: P) -> Result<()> {
-        daemonize(&pid_file)
-    }
+    fn daemonize<P: AsRef<Path>>(&mut self, pid_file: P) -> Result<()>;
 
     fn start_workers(&mut self, workers: Vec<Service>) -> Result<()>;
-    fn start_server(&mut self) -> Result<()>;
+
+    /// Serve until the server's told to stop.
+    ///
+    /// # Returns
+    ///
+    /// The exit code for the process.
+    fn start_server(&mut self) -> Result<i32>;
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub enum RequestMessage {
+    Ping,
+    GetInfo,
     GetWorkers,
+    GetExitInfo,
     StopServer,
     StopTasks(Vec<String>),
+    Signal(TaskSpec, i32),
+    Pause(TaskSpec),
+    Resume(TaskSpec),
+    /// Kill some services and start them again.
+    Restart(TaskSpec),
+    /// The last lines that a service wrote, up to a count.
+    GetLogs(String, usize),
+    /// Keep the connection open and send the events that pass the filter over it.
+    Subscribe(EventFilter),
+    /// Change how many instances of some process types run.
+    Scale(Formation),
+    GetScale,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub enum ResponseMessage {
-    Workers(Vec<Service>),
+    Pong,
+    Info(DaemonInfo),
+    Workers(Vec<WorkerStatus>),
+    /// How each service that's finished did so.
+    ExitInfo(BTreeMap<String, ExitInfo>),
+    Scaled,
+    ProcessScales(Vec<ProcessScale>),
+    /// A service's last lines of output, oldest first.
+    Logs(Vec<String>),
+    /// Something happened to a service. These are sent to subscribers.
+    Event(Event),
+    /// The server's stopping, so no more events are coming.
+    Stopping,
+    /// The request couldn't be carried out, and this is why.
+    Error(String),
 }
@@ -0,0 +1,160 @@
+use libc;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process;
+use Result;
+
+/// A PID file that's locked with `flock` for as long as this is around. Only one process can hold
+/// the lock, so only one daemon can run for a PID file. The lock goes away when the process does,
+/// however it exits, so a file left behind by a crash doesn't keep a new daemon from starting.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    file: File,
+}
+
+/// The result of trying to lock a PID file.
+#[derive(Debug)]
+pub enum PidLock {
+    /// The lock's ours.
+    Acquired(PidFile),
+    /// Another process has it. This is its PID, if it's written one.
+    Held(Option<u32>),
+}
+
+impl PidFile {
+    /// Try to lock the PID file without waiting. The file is created if it's not there.
+    pub fn try_lock(path: &Path) -> Result<PidLock> {
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(path)
+                .map_err(|err| format_err!("Unable to open PID file {:?}: {:?}", path, &err))?;
+
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+                    let mut contents = String::new();
+                    let _ = file.read_to_string(&mut contents);
+                    return Ok(PidLock::Held(contents.trim().parse().ok()));
+                }
+                return Err(format_err!("Unable to lock PID file {:?}: {:?}", path, &err));
+            }
+
+            // The last holder removes the file before letting go of the lock. If that happened
+            // after it was opened here, this lock is on a file that's gone, so start again.
+            if is_same_file(&file, path) {
+                return Ok(PidLock::Acquired(PidFile {
+                    path: path.to_path_buf(),
+                    file,
+                }));
+            }
+        }
+    }
+
+    /// Write the current process's ID into the file, replacing whatever was there.
+    pub fn write_pid(&mut self) -> Result<()> {
+        let pid = process::id();
+        self.file
+            .set_len(0)
+            .and_then(|_| self.file.seek(SeekFrom::Start(0)))
+            .and_then(|_| writeln!(self.file, "{}", pid))
+            .and_then(|_| self.file.flush())
+            .map_err(|err| format_err!("Unable to write PID file {:?}: {:?}", &self.path, &err))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    /// Remove the file. The lock's released when the file is closed, right after.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn is_same_file(file: &File, path: &Path) -> bool {
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PidFile, PidLock};
+    use spectral::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::{self, Command};
+
+    fn setup(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("straw-boss.{}.{}.pid", name, process::id()));
+        if path.exists() {
+            fs::remove_file(&path).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_writes_pid() {
+        let path = setup("writes-pid");
+        if let PidLock::Acquired(mut pid_file) = PidFile::try_lock(&path).unwrap() {
+            pid_file.write_pid().unwrap();
+            let contents = fs::read_to_string(&path).unwrap();
+            assert_that(&contents).is_equal_to(format!("{}\n", process::id()));
+        } else {
+            panic!("Expected to lock {:?}.", &path);
+        }
+    }
+
+    #[test]
+    fn test_second_lock_is_held() {
+        let path = setup("second-lock");
+        let first = PidFile::try_lock(&path).unwrap();
+        if let PidLock::Acquired(mut pid_file) = first {
+            pid_file.write_pid().unwrap();
+            match PidFile::try_lock(&path).unwrap() {
+                PidLock::Held(pid) => assert_that(&pid).is_equal_to(Some(process::id())),
+                PidLock::Acquired(_) => panic!("Locked {:?} twice.", &path),
+            }
+        } else {
+            panic!("Expected to lock {:?}.", &path);
+        }
+    }
+
+    #[test]
+    fn test_takes_over_stale_file() {
+        let path = setup("stale");
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        fs::write(&path, format!("{}\n", pid)).unwrap();
+
+        if let PidLock::Acquired(mut pid_file) = PidFile::try_lock(&path).unwrap() {
+            pid_file.write_pid().unwrap();
+            let contents = fs::read_to_string(&path).unwrap();
+            assert_that(&contents).is_equal_to(format!("{}\n", process::id()));
+        } else {
+            panic!("Expected to lock {:?}.", &path);
+        }
+    }
+
+    #[test]
+    fn test_removes_file_when_dropped() {
+        let path = setup("removes-file");
+        {
+            let _lock = PidFile::try_lock(&path).unwrap();
+            assert_that(&path).exists();
+        }
+        assert_that(&path).does_not_exist();
+    }
+}
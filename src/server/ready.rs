@@ -0,0 +1,178 @@
+use failure::Error;
+use messaging::{Receiver, Sender};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use Result;
+
+/// How long `start --daemon` waits for the daemon to be ready, unless it's told otherwise.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What the daemon tells the process that started it once it's done starting up.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum StartupMessage {
+    Ready,
+    /// The daemon is serving, but some services couldn't start.
+    Degraded(String),
+    Failed(String),
+}
+
+/// The end of the readiness pipe that the process running `start` waits on.
+#[derive(Debug)]
+pub struct ReadyWaiter {
+    stream: UnixStream,
+    notifier: Arc<Mutex<Option<UnixStream>>>,
+}
+
+/// The end of the readiness pipe that the daemon reports on.
+#[derive(Debug)]
+pub struct ReadyNotifier {
+    stream: Arc<Mutex<Option<UnixStream>>>,
+}
+
+/// Create the pipe before forking. The parent keeps the `ReadyWaiter`, and the daemon keeps the
+/// `ReadyNotifier`. Neither end is inherited by the services, so only the daemon can hold the pipe
+/// open.
+pub fn ready_pipe() -> Result<(ReadyWaiter, ReadyNotifier)> {
+    let (waiter, notifier) = UnixStream::pair()
+        .map_err(|err| format_err!("Unable to create readiness pipe: {:?}", &err))?;
+    let notifier = Arc::new(Mutex::new(Some(notifier)));
+    Ok((
+        ReadyWaiter {
+            stream: waiter,
+            notifier: notifier.clone(),
+        },
+        ReadyNotifier { stream: notifier },
+    ))
+}
+
+impl ReadyWaiter {
+    /// Wait for the daemon to say that it's serving.
+    ///
+    /// # Returns
+    ///
+    /// An error with the daemon's own error if it failed to start up, or if it exited or ran out
+    /// of time without saying anything.
+    pub fn wait(mut self, timeout: Duration) -> Result<()> {
+        // Both processes have the notifier's end after the fork. Closing this one's copy means
+        // that the daemon exiting closes the pipe.
+        if let Ok(mut notifier) = self.notifier.lock() {
+            notifier.take();
+        }
+
+        let started = Instant::now();
+        self.stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|err| format_err!("Unable to wait for daemon: {:?}", &err))?;
+        match self.stream.recv() {
+            Ok(StartupMessage::Ready) => Ok(()),
+            Ok(StartupMessage::Degraded(message)) | Ok(StartupMessage::Failed(message)) => {
+                Err(format_err!("{}", message))
+            }
+            Err(_) if started.elapsed() >= timeout => Err(format_err!(
+                "Straw-boss didn't start within {} seconds.",
+                timeout.as_secs()
+            )),
+            Err(_) => Err(format_err!("Straw-boss exited before it was ready.")),
+        }
+    }
+}
+
+impl ReadyNotifier {
+    /// Tell the waiting process that the daemon is serving.
+    pub fn ready(self) -> Result<()> {
+        self.send(StartupMessage::Ready)
+    }
+
+    /// Tell the waiting process that the daemon is serving, but with services that couldn't
+    /// start. It exits with the summary.
+    pub fn degraded(self, summary: &str) -> Result<()> {
+        self.send(StartupMessage::Degraded(String::from(summary)))
+    }
+
+    /// Tell the waiting process why the daemon couldn't start.
+    pub fn failed(self, err: &Error) -> Result<()> {
+        self.send(StartupMessage::Failed(err.to_string()))
+    }
+
+    fn send(self, message: StartupMessage) -> Result<()> {
+        let mut stream = self
+            .stream
+            .lock()
+            .map_err(|err| format_err!("Unable to get readiness pipe: {:?}", &err))?;
+        match stream.take() {
+            Some(mut stream) => stream.send(message),
+            None => Err(format_err!("The readiness pipe is closed.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ready_pipe, ReadyNotifier};
+    use spectral::prelude::*;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    // The daemon is a separate process, so its end of the pipe is still open after the waiter
+    // closes this process's copy. This stands in for that.
+    fn daemon_end(notifier: &ReadyNotifier) -> UnixStream {
+        let stream = notifier.stream.lock().unwrap();
+        stream.as_ref().unwrap().try_clone().unwrap()
+    }
+
+    #[test]
+    fn test_ready() {
+        let (waiter, notifier) = ready_pipe().unwrap();
+        notifier.ready().unwrap();
+        assert_that(&waiter.wait(Duration::from_secs(5))).is_ok();
+    }
+
+    #[test]
+    fn test_failed() {
+        let (waiter, notifier) = ready_pipe().unwrap();
+        notifier
+            .failed(&format_err!("Unable to open socket."))
+            .unwrap();
+        let result = waiter.wait(Duration::from_secs(5));
+        assert_that(&result.unwrap_err().to_string()).is_equal_to(String::from(
+            "Unable to open socket.",
+        ));
+    }
+
+    #[test]
+    fn test_degraded() {
+        let (waiter, notifier) = ready_pipe().unwrap();
+        notifier.degraded("Unable to start 1 of 2 services.").unwrap();
+        let result = waiter.wait(Duration::from_secs(5));
+        assert_that(&result.unwrap_err().to_string()).is_equal_to(String::from(
+            "Unable to start 1 of 2 services.",
+        ));
+    }
+
+    #[test]
+    fn test_exits_without_saying() {
+        let (waiter, notifier) = ready_pipe().unwrap();
+        drop(notifier);
+        let result = waiter.wait(Duration::from_secs(5));
+        assert_that(&result.unwrap_err().to_string())
+            .is_equal_to(String::from("Straw-boss exited before it was ready."));
+    }
+
+    #[test]
+    fn test_closes_own_copy_of_notifier() {
+        let (waiter, _notifier) = ready_pipe().unwrap();
+        let result = waiter.wait(Duration::from_secs(5));
+        assert_that(&result.unwrap_err().to_string())
+            .is_equal_to(String::from("Straw-boss exited before it was ready."));
+    }
+
+    #[test]
+    fn test_times_out() {
+        let (waiter, notifier) = ready_pipe().unwrap();
+        let _daemon = daemon_end(&notifier);
+        let result = waiter.wait(Duration::from_secs(1));
+        assert_that(&result.unwrap_err().to_string())
+            .is_equal_to(String::from("Straw-boss didn't start within 1 seconds."));
+    }
+}
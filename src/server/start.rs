@@ -6,11 +6,13 @@ use Result;
 ///
 /// If `is_daemon` is given, the server is started in the background, and this
 /// function returns immediately.
+///
+/// This returns the exit code for the process.
 pub fn start(
     server: &mut impl ManagerServer,
     run_mode: ServerRunMode,
     workers: Vec<Service>,
-) -> Result<()> {
+) -> Result<i32> {
     if let ServerRunMode::Daemon(pid_file) = run_mode {
         server.daemonize(pid_file)?;
     }
@@ -63,8 +65,8 @@ mod test {
             self.push(ServerCalls::Daemonize)
         }
 
-        fn start_server(&mut self) -> Result<()> {
-            self.push(ServerCalls::StartServer)
+        fn start_server(&mut self) -> Result<i32> {
+            self.push(ServerCalls::StartServer).and(Ok(0))
         }
 
         fn start_workers(&mut self, _workers: Vec<Service>) -> Result<()> {
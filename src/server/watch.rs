@@ -0,0 +1,370 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::Gitignore;
+use ignore::Match;
+use notify::{raw_watcher, RawEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use server::ServerEvent;
+use service::Service;
+use std::collections::BTreeMap;
+use std::env;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use Result;
+
+/// How long the watched files have to be quiet before a burst of changes restarts anything.
+pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The longest a burst of changes can hold up the restarts, counting from its first change, even if
+/// the files never go quiet.
+pub const DEBOUNCE_LIMIT: Duration = Duration::from_secs(2);
+
+/// The files one service is restarted for.
+#[derive(Debug)]
+pub struct ServiceWatch {
+    name: String,
+    root: PathBuf,
+    globs: GlobSet,
+    /// The `.gitignore` files that apply, innermost first.
+    gitignores: Vec<Gitignore>,
+}
+
+impl ServiceWatch {
+    /// Create a watch for a service's `watch` globs. This returns `None` if the service doesn't
+    /// watch anything.
+    pub fn new(service: &Service) -> Result<Option<ServiceWatch>> {
+        if service.watch.is_empty() {
+            return Ok(None);
+        }
+
+        let root = match service.cwd {
+            Some(ref cwd) => cwd.clone(),
+            None => env::current_dir()
+                .map_err(|err| format_err!("Unable to get current directory: {:?}", &err))?,
+        };
+        let root = root
+            .canonicalize()
+            .map_err(|err| format_err!("Unable to watch {:?}: {:?}", &root, &err))?;
+
+        let mut globs = GlobSetBuilder::new();
+        for glob in &service.watch {
+            globs.add(Glob::new(glob).map_err(|err| {
+                format_err!("Invalid watch glob for {}: {}", &service.name, &err)
+            })?);
+        }
+        let globs = globs
+            .build()
+            .map_err(|err| format_err!("Invalid watch globs for {}: {}", &service.name, &err))?;
+
+        Ok(Some(ServiceWatch {
+            name: service.name.clone(),
+            root: root.clone(),
+            globs,
+            gitignores: read_gitignores(&root)?,
+        }))
+    }
+
+    /// Does a change to the file mean the service needs to restart?
+    pub fn matches(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        if relative.components().any(|c| c.as_os_str() == ".git") {
+            return false;
+        }
+        // Like git, the innermost `.gitignore` that says anything about the file decides.
+        for gitignore in &self.gitignores {
+            match gitignore.matched_path_or_any_parents(path, path.is_dir()) {
+                Match::Ignore(_) => return false,
+                Match::Whitelist(_) => break,
+                Match::None => {}
+            }
+        }
+        self.globs.is_match(relative)
+    }
+}
+
+/// Read the `.gitignore` files that apply to a directory: its own and those of its parents, up to
+/// the root of the git repository it's in. Each one's patterns are relative to its own directory,
+/// and they're returned innermost first.
+fn read_gitignores(root: &Path) -> Result<Vec<Gitignore>> {
+    let mut gitignores = Vec::new();
+    for dir in root.ancestors() {
+        let path = dir.join(".gitignore");
+        if path.exists() {
+            let (gitignore, err) = Gitignore::new(&path);
+            if let Some(err) = err {
+                return Err(format_err!("Unable to read {:?}: {}", &path, &err));
+            }
+            gitignores.push(gitignore);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    Ok(gitignores)
+}
+
+/// Watch the files for all of the services. When files change, the services that need to restart
+/// are sent to the server as a `ServerEvent::FilesChanged`, with the first file that changed for
+/// each.
+///
+/// # Returns
+///
+/// The filesystem watcher, if any services have files to watch. The files are watched until this
+/// is dropped.
+pub fn watch(
+    services: &[Service],
+    server: Sender<ServerEvent>,
+) -> Result<Option<RecommendedWatcher>> {
+    let watches = services
+        .iter()
+        .map(ServiceWatch::new)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    if watches.is_empty() {
+        return Ok(None);
+    }
+
+    let (tx, rx) = channel();
+    let mut watcher =
+        raw_watcher(tx).map_err(|err| format_err!("Unable to watch files: {:?}", &err))?;
+    for watch in &watches {
+        watcher
+            .watch(&watch.root, RecursiveMode::Recursive)
+            .map_err(|err| format_err!("Unable to watch {:?}: {:?}", &watch.root, &err))?;
+    }
+
+    thread::Builder::new()
+        .name(String::from("watch"))
+        .spawn(move || debounce(&watches, &rx, &server))
+        .map_err(|err| format_err!("Unable to start watching files: {:?}", &err))?;
+
+    Ok(Some(watcher))
+}
+
+/// Collect changes until the watched files are quiet for `DEBOUNCE`, or for `DEBOUNCE_LIMIT` after
+/// the first change, then tell the server about them. Changes that no service watches don't hold
+/// anything up. This stops when either the watcher or the server goes away.
+fn debounce(watches: &[ServiceWatch], rx: &Receiver<RawEvent>, server: &Sender<ServerEvent>) {
+    let mut changed = BTreeMap::new();
+    // When the first and the last change in the burst were seen.
+    let mut burst: Option<(Instant, Instant)> = None;
+    loop {
+        let received = match burst {
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some((first, last)) => {
+                let due = (last + DEBOUNCE).min(first + DEBOUNCE_LIMIT);
+                rx.recv_timeout(due.saturating_duration_since(Instant::now()))
+            }
+        };
+        match received {
+            Ok(event) => {
+                if collect(watches, event, &mut changed) {
+                    let now = Instant::now();
+                    burst = Some((burst.map_or(now, |(first, _)| first), now));
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                burst = None;
+                let changed = mem::take(&mut changed);
+                if server.send(ServerEvent::FilesChanged(changed)).is_err() {
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Add the services that a change is for to `changed`.
+///
+/// # Returns
+///
+/// Whether any service watches the file that changed.
+fn collect(
+    watches: &[ServiceWatch],
+    event: RawEvent,
+    changed: &mut BTreeMap<String, PathBuf>,
+) -> bool {
+    let path = match event.path {
+        Some(path) => path,
+        None => return false,
+    };
+    let mut matched = false;
+    for watch in watches.iter().filter(|watch| watch.matches(&path)) {
+        matched = true;
+        changed
+            .entry(watch.name.clone())
+            .or_insert_with(|| path.clone());
+    }
+    matched
+}
+
+#[cfg(test)]
+mod test {
+    use super::{debounce, watch, ServiceWatch, DEBOUNCE, DEBOUNCE_LIMIT};
+    use notify::{op, RawEvent};
+    use server::ServerEvent;
+    use service::Service;
+    use spectral::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn setup(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("straw-boss.watch.{}.{}", name, process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join(".gitignore"), b"*.log\n").unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    fn service(dir: &PathBuf, globs: &[&str]) -> Service {
+        let mut service = Service::new("web", "start web");
+        service.cwd = Some(dir.clone());
+        service.watch = globs.iter().map(|g| String::from(*g)).collect();
+        service
+    }
+
+    /// Debounce the changes sent to the returned channel for a service that watches `globs`.
+    fn debouncer(dir: &PathBuf, globs: &[&str]) -> (Sender<RawEvent>, Receiver<ServerEvent>) {
+        let watches = vec![ServiceWatch::new(&service(dir, globs)).unwrap().unwrap()];
+        let (changes_tx, changes_rx) = channel();
+        let (server_tx, server_rx) = channel();
+        thread::spawn(move || debounce(&watches, &changes_rx, &server_tx));
+        (changes_tx, server_rx)
+    }
+
+    fn change(path: &Path) -> RawEvent {
+        RawEvent {
+            path: Some(path.to_path_buf()),
+            op: Ok(op::WRITE),
+            cookie: None,
+        }
+    }
+
+    #[test]
+    fn test_no_globs_no_watch() {
+        let service = Service::new("web", "start web");
+        assert_that(&ServiceWatch::new(&service).unwrap().is_none()).is_true();
+    }
+
+    #[test]
+    fn test_matches_globs_relative_to_cwd() {
+        let dir = setup("matches-globs");
+        let watch = ServiceWatch::new(&service(&dir, &["src/**/*.rb"]))
+            .unwrap()
+            .unwrap();
+
+        assert_that(&watch.matches(&dir.join("src/app/main.rb"))).is_true();
+        assert_that(&watch.matches(&dir.join("src/app/main.py"))).is_false();
+        assert_that(&watch.matches(&PathBuf::from("/elsewhere/src/main.rb"))).is_false();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_skips_gitignored_files() {
+        let dir = setup("skips-gitignored");
+        let watch = ServiceWatch::new(&service(&dir, &["**/*"])).unwrap().unwrap();
+
+        assert_that(&watch.matches(&dir.join("src/main.rb"))).is_true();
+        assert_that(&watch.matches(&dir.join("src/debug.log"))).is_false();
+        assert_that(&watch.matches(&dir.join(".git/index"))).is_false();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reads_parent_gitignore_relative_to_its_directory() {
+        let dir = setup("parent-gitignore");
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join(".gitignore"), b"/build\n*.tmp\n").unwrap();
+        let app = dir.join("app");
+        fs::create_dir_all(app.join("build")).unwrap();
+        fs::write(app.join(".gitignore"), b"!keep.tmp\n").unwrap();
+        let watch = ServiceWatch::new(&service(&app, &["**/*"])).unwrap().unwrap();
+
+        assert_that(&watch.matches(&app.join("build/main.rb"))).is_true();
+        assert_that(&watch.matches(&app.join("keep.tmp"))).is_true();
+        assert_that(&watch.matches(&app.join("other.tmp"))).is_false();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sends_one_event_for_a_burst() {
+        let dir = setup("sends-one-event");
+        let (tx, rx) = channel();
+        let _watcher = watch(&[service(&dir, &["src/*"])], tx).unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        for i in 0..5 {
+            fs::write(dir.join("src").join(format!("{}.rb", i)), b"puts 1\n").unwrap();
+        }
+
+        let event = rx.recv_timeout(DEBOUNCE * 4);
+        assert_that(&event).is_ok();
+        if let Ok(ServerEvent::FilesChanged(changed)) = event {
+            assert_that(&changed.keys().collect::<Vec<_>>())
+                .is_equal_to(vec![&String::from("web")]);
+        } else {
+            panic!("Expected FilesChanged.");
+        }
+        assert_that(&rx.recv_timeout(DEBOUNCE * 2)).is_err();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ignored_changes_do_not_hold_up_a_burst() {
+        let dir = setup("ignored-changes");
+        let (changes, server) = debouncer(&dir, &["src/*"]);
+
+        let start = Instant::now();
+        changes.send(change(&dir.join("src/main.rb"))).unwrap();
+        let ignored = thread::spawn(move || {
+            for _ in 0..20 {
+                changes.send(change(&dir.join("src/debug.log"))).unwrap();
+                changes.send(change(&dir.join("README.md"))).unwrap();
+                thread::sleep(Duration::from_millis(100));
+            }
+            fs::remove_dir_all(&dir).unwrap();
+        });
+
+        assert_that(&server.recv_timeout(DEBOUNCE * 2)).is_ok();
+        assert_that(&(Instant::now() - start)).is_less_than(DEBOUNCE * 2);
+        ignored.join().unwrap();
+        assert_that(&server.recv_timeout(DEBOUNCE * 2)).is_err();
+    }
+
+    #[test]
+    fn test_limits_how_long_a_burst_is_held_up() {
+        let dir = setup("limits-burst");
+        let (changes, server) = debouncer(&dir, &["src/*"]);
+
+        let start = Instant::now();
+        let burst = thread::spawn(move || {
+            for _ in 0..40 {
+                changes.send(change(&dir.join("src/main.rb"))).unwrap();
+                thread::sleep(Duration::from_millis(100));
+            }
+            fs::remove_dir_all(&dir).unwrap();
+        });
+
+        assert_that(&server.recv_timeout(DEBOUNCE_LIMIT * 2)).is_ok();
+        assert_that(&(Instant::now() - start)).is_less_than(DEBOUNCE_LIMIT + DEBOUNCE);
+        burst.join().unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+use libc;
+use service::usage::Usage;
+use shellwords;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+use Result;
+
+/// Where cgroup v2 is mounted. Relative slice paths are under this.
+const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+
+/// The controllers that are handed down to the services' cgroups, if the slice has them.
+const CONTROLLERS: &[&str] = &["cpu", "memory", "pids"];
+
+/// The cgroup settings that services can have, in the order they're written.
+const SETTINGS: &[&str] = &["memory.max", "cpu.max", "pids.max"];
+
+/// How many times to try removing a cgroup whose processes are still exiting.
+const REMOVE_TRIES: u32 = 10;
+
+/// A service's cgroup v2 settings. These are only applied when the server has a slice to put the
+/// services' cgroups in. The values are written as they are to the files they're named after.
+#[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CgroupLimits {
+    /// The most memory the service can use, in bytes, like `512M`, or `max`.
+    pub memory_max: Option<String>,
+    /// The CPU time the service can use in each period, in microseconds, and optionally the
+    /// period, like `50000 100000` for half of a CPU, or `max`.
+    pub cpu_max: Option<String>,
+    /// The most processes the service can have, or `max`.
+    pub pids_max: Option<String>,
+}
+
+impl CgroupLimits {
+    /// Are there no settings to write?
+    pub fn is_empty(&self) -> bool {
+        *self == CgroupLimits::default()
+    }
+
+    /// Is this the name of a cgroup setting?
+    pub fn is_setting(name: &str) -> bool {
+        SETTINGS.contains(&name)
+    }
+
+    /// Set a cgroup setting from an annotation named after its file.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        let valid = match name {
+            "memory.max" => is_max_or(&value, is_size),
+            "cpu.max" => {
+                let mut parts = value.splitn(2, ' ');
+                let quota = parts.next().map_or(false, |quota| is_max_or(quota, is_count));
+                quota && parts.next().map_or(true, is_count)
+            }
+            "pids.max" => is_max_or(&value, is_count),
+            _ => return Err(format_err!("Unknown cgroup setting: {:?}", name)),
+        };
+        if !valid {
+            return Err(format_err!("Invalid {}: {:?}", name, &value));
+        }
+        match name {
+            "memory.max" => self.memory_max = Some(value),
+            "cpu.max" => self.cpu_max = Some(value),
+            _ => self.pids_max = Some(value),
+        }
+        Ok(())
+    }
+
+    /// The settings that are set, as file names and values.
+    pub fn settings(&self) -> Vec<(&'static str, &str)> {
+        let values = [&self.memory_max, &self.cpu_max, &self.pids_max];
+        SETTINGS
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(name, value)| value.as_ref().map(|value| (*name, value.as_str())))
+            .collect()
+    }
+
+    /// The settings as `key=value` annotations, like `memory.max=512M`.
+    pub fn to_annotations(&self) -> Vec<String> {
+        self.settings()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, shellwords::escape(value)))
+            .collect()
+    }
+}
+
+fn is_max_or(value: &str, is_valid: fn(&str) -> bool) -> bool {
+    value == "max" || is_valid(value)
+}
+
+fn is_count(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_size(value: &str) -> bool {
+    let number = value.trim_right_matches(|c| "KMGkmg".contains(c));
+    value.len() - number.len() <= 1 && is_count(number)
+}
+
+/// The delegated cgroup that the services' cgroups go in. Straw boss needs to be able to write
+/// to it, which usually means it's been delegated by systemd or made by root for straw boss's
+/// user.
+///
+/// The services each get a cgroup under `services`. If straw boss itself is in the slice, it moves
+/// into `server`, because a cgroup with processes of its own can't hand its controllers down.
+#[derive(Debug, Clone)]
+pub struct Slice {
+    services: PathBuf,
+}
+
+impl Slice {
+    /// Set up a slice. Relative paths are under `/sys/fs/cgroup`. This fails if the slice isn't
+    /// a cgroup v2 directory, or if straw boss can't write to it.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Slice> {
+        let path = Path::new(CGROUP_MOUNT).join(path);
+        let available = read(&path.join("cgroup.controllers"))?;
+        let pid = process::id().to_string();
+        if read(&path.join("cgroup.procs"))?
+            .lines()
+            .any(|line| line == pid)
+        {
+            let server = path.join("server");
+            create_dir(&server)?;
+            write(&server.join("cgroup.procs"), &pid)?;
+        }
+
+        let services = path.join("services");
+        create_dir(&services)?;
+        let controllers = CONTROLLERS
+            .iter()
+            .filter(|controller| available.split_whitespace().any(|c| c == **controller))
+            .map(|controller| format!("+{}", controller))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !controllers.is_empty() {
+            write(&path.join("cgroup.subtree_control"), &controllers)?;
+            write(&services.join("cgroup.subtree_control"), &controllers)?;
+        }
+        Ok(Slice { services })
+    }
+
+    /// The path to a service's cgroup.
+    pub fn cgroup_path(&self, service_name: &str) -> PathBuf {
+        self.services.join(service_name)
+    }
+
+    /// What a service's processes are using. This is `None` if there are no stats to read.
+    pub fn usage(&self, service_name: &str) -> Option<Usage> {
+        let path = self.cgroup_path(service_name);
+        let memory = read(&path.join("memory.current"))
+            .ok()
+            .and_then(|memory| memory.trim().parse().ok());
+        let cpu = read(&path.join("cpu.stat")).ok().and_then(|stat| {
+            stat.lines()
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    match (fields.next(), fields.next()) {
+                        (Some("usage_usec"), Some(usec)) => usec.parse().ok(),
+                        _ => None,
+                    }
+                }).next()
+                .map(Duration::from_micros)
+        });
+        if memory.is_none() && cpu.is_none() {
+            None
+        } else {
+            Some(Usage { memory, cpu })
+        }
+    }
+}
+
+/// A service's cgroup, while the service is running. It's removed when this is dropped, if it's
+/// empty by then.
+#[derive(Debug)]
+pub struct Cgroup {
+    path: PathBuf,
+    procs: File,
+}
+
+impl Cgroup {
+    /// Create the cgroup, or reuse the one that's there, and write the service's settings to it.
+    pub fn create(path: PathBuf, limits: &CgroupLimits) -> Result<Cgroup> {
+        create_dir(&path)?;
+        for (name, value) in limits.settings() {
+            write(&path.join(name), value)?;
+        }
+        let procs_path = path.join("cgroup.procs");
+        let procs = OpenOptions::new()
+            .write(true)
+            .open(&procs_path)
+            .map_err(|err| format_err!("Unable to open {:?}: {}", &procs_path, &err))?;
+        Ok(Cgroup { path, procs })
+    }
+
+    /// The open `cgroup.procs` file, for `join` to write to.
+    pub fn procs_fd(&self) -> RawFd {
+        self.procs.as_raw_fd()
+    }
+
+    /// Signal every process in the cgroup.
+    pub fn signal(&self, signal: i32) -> Result<()> {
+        for pid in self.pids()? {
+            unsafe {
+                libc::kill(pid, signal);
+            }
+        }
+        Ok(())
+    }
+
+    /// Kill every process in the cgroup. This uses `cgroup.kill` where the kernel has it, and
+    /// `SIGKILL` where it doesn't.
+    pub fn kill(&self) -> Result<()> {
+        let killed = OpenOptions::new()
+            .write(true)
+            .open(self.path.join("cgroup.kill"))
+            .and_then(|mut kill| kill.write_all(b"1"));
+        match killed {
+            Ok(()) => Ok(()),
+            Err(_) => self.signal(libc::SIGKILL),
+        }
+    }
+
+    fn pids(&self) -> Result<Vec<libc::pid_t>> {
+        let procs = read(&self.path.join("cgroup.procs"))?;
+        Ok(procs.lines().filter_map(|pid| pid.trim().parse().ok()).collect())
+    }
+}
+
+impl Drop for Cgroup {
+    /// Remove the cgroup. Killed processes can take a moment to leave it, so this waits a little
+    /// while it's busy.
+    fn drop(&mut self) {
+        for _ in 0..REMOVE_TRIES {
+            match fs::remove_dir(&self.path) {
+                Err(ref err) if err.raw_os_error() == Some(libc::EBUSY) => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+/// Move the current process into the cgroup whose `cgroup.procs` is open as `procs`. This is meant
+/// to run in a forked child, right before it runs the command, so it only makes a system call.
+pub fn join(procs: RawFd) -> io::Result<()> {
+    let pid = b"0";
+    let written = unsafe { libc::write(procs, pid.as_ptr() as *const libc::c_void, pid.len()) };
+    if written < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|err| format_err!("Unable to read {:?}: {}", path, &err))
+}
+
+fn write(path: &Path, value: &str) -> Result<()> {
+    fs::write(path, value).map_err(|err| format_err!("Unable to write {:?}: {}", path, &err))
+}
+
+fn create_dir(path: &Path) -> Result<()> {
+    match fs::create_dir(path) {
+        Err(ref err) if err.kind() != io::ErrorKind::AlreadyExists => {
+            Err(format_err!("Unable to create cgroup {:?}: {}", path, err))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CgroupLimits, Slice};
+    use service::usage::Usage;
+    use spectral::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use std::time::Duration;
+
+    /// A directory that looks enough like a delegated cgroup, with straw boss in it.
+    fn fake_slice(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("straw-boss.cgroup.{}.{}", name, process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cgroup.controllers"), "cpuset cpu io memory pids\n").unwrap();
+        fs::write(dir.join("cgroup.procs"), format!("1\n{}\n", process::id())).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_sets_limits() {
+        let mut limits = CgroupLimits::default();
+        limits.set("memory.max", "512M").unwrap();
+        limits.set("cpu.max", "50000  100000").unwrap();
+        limits.set("pids.max", "max").unwrap();
+
+        assert_that(&limits.settings()).is_equal_to(vec![
+            ("memory.max", "512M"),
+            ("cpu.max", "50000 100000"),
+            ("pids.max", "max"),
+        ]);
+        assert_that(&limits.to_annotations()).is_equal_to(vec![
+            String::from("memory.max=512M"),
+            String::from("cpu.max=50000\\ 100000"),
+            String::from("pids.max=max"),
+        ]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_limits() {
+        let mut limits = CgroupLimits::default();
+        for (name, value) in &[
+            ("memory.high", "1G"),
+            ("memory.max", "lots"),
+            ("memory.max", "1MB"),
+            ("cpu.max", "half"),
+            ("cpu.max", "50000 100000 1"),
+            ("pids.max", "-1"),
+        ] {
+            assert_that(&limits.set(name, value)).is_err();
+        }
+        assert_that(&limits.is_empty()).is_true();
+    }
+
+    #[test]
+    fn test_creates_slice() {
+        let dir = fake_slice("creates");
+        let slice = Slice::create(&dir).unwrap();
+
+        let pid = fs::read_to_string(dir.join("server/cgroup.procs")).unwrap();
+        assert_that(&pid).is_equal_to(process::id().to_string());
+        let controllers = fs::read_to_string(dir.join("services/cgroup.subtree_control")).unwrap();
+        assert_that(&controllers.as_str()).is_equal_to("+cpu +memory +pids");
+        assert_that(&slice.cgroup_path("web")).is_equal_to(dir.join("services/web"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_needs_cgroup_v2() {
+        let dir = fake_slice("needs-v2");
+        fs::remove_file(dir.join("cgroup.controllers")).unwrap();
+        assert_that(&Slice::create(&dir)).is_err();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reads_usage() {
+        let dir = fake_slice("usage");
+        let slice = Slice::create(&dir).unwrap();
+        let cgroup = slice.cgroup_path("web");
+        assert_that(&slice.usage("web")).is_none();
+
+        fs::create_dir(&cgroup).unwrap();
+        fs::write(cgroup.join("memory.current"), "13107200\n").unwrap();
+        fs::write(cgroup.join("cpu.stat"), "usage_usec 1250000\nuser_usec 1000000\n").unwrap();
+        let usage = slice.usage("web");
+        assert_that(&usage).is_equal_to(Some(Usage {
+            memory: Some(13_107_200),
+            cpu: Some(Duration::from_millis(1250)),
+        }));
+        assert_that(&usage.unwrap().describe().as_str()).is_equal_to("12.5M memory, 1.25s CPU");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
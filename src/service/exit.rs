@@ -0,0 +1,169 @@
+use chrono::prelude::*;
+use os_pipe::PipeReader;
+use service::log::Log;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use Result;
+
+/// How many lines at the end of a service's stderr are kept.
+pub const STDERR_LINES: usize = 20;
+
+/// How a service's task finished.
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct ExitInfo {
+    /// The exit code, if it exited on its own.
+    pub code: Option<i32>,
+    /// The signal that terminated it, if one did.
+    pub signal: Option<i32>,
+    /// When straw boss noticed that it had finished.
+    pub exited_at: SystemTime,
+    /// The last lines it wrote to stderr.
+    pub stderr: Vec<String>,
+}
+
+impl ExitInfo {
+    pub fn new(status: &ExitStatus, stderr: Vec<String>) -> ExitInfo {
+        ExitInfo {
+            code: status.code(),
+            signal: status.signal(),
+            exited_at: SystemTime::now(),
+            stderr,
+        }
+    }
+
+    /// The exit code for a process that's passing this exit on. A signal is reported as 128 plus
+    /// its number, like the shell does.
+    pub fn exit_code(&self) -> i32 {
+        match (self.code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+
+    /// Describe the exit for people, like `code 1 at 2018-10-01 12:00:00`.
+    pub fn describe(&self) -> String {
+        let how = match (self.code, self.signal) {
+            (Some(code), _) => format!("code {}", code),
+            (None, Some(signal)) => format!("signal {}", signal),
+            (None, None) => String::from("unknown status"),
+        };
+        let at: DateTime<Local> = self.exited_at.into();
+        format!("{} at {}", how, at.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+/// A service's stderr. Everything read is copied through to straw boss's own stderr and added to
+/// the service's log, and the last `STDERR_LINES` lines are kept for the `ExitInfo`.
+#[derive(Debug)]
+pub struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    closed: Receiver<()>,
+}
+
+impl StderrTail {
+    /// Start reading the service's stderr on a separate thread.
+    pub fn start(reader: PipeReader, service_name: &str, log: Log) -> Result<StderrTail> {
+        let lines = Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_LINES)));
+        let (tx, closed) = channel();
+        let thread_lines = lines.clone();
+
+        thread::Builder::new()
+            .name(format!("{}-stderr", service_name))
+            .spawn(move || {
+                let mut reader = BufReader::new(reader);
+                let mut buffer = Vec::new();
+                while let Ok(count) = reader.read_until(b'\n', &mut buffer) {
+                    if count == 0 {
+                        break;
+                    }
+                    let _ = io::stderr().write_all(&buffer);
+                    log.push(&buffer);
+                    if let Ok(mut lines) = thread_lines.lock() {
+                        if lines.len() == STDERR_LINES {
+                            lines.pop_front();
+                        }
+                        let line = String::from_utf8_lossy(&buffer);
+                        lines.push_back(String::from(line.trim_right_matches('\n')));
+                    }
+                    buffer.clear();
+                }
+                let _ = tx.send(());
+            }).map_err(|err| {
+                format_err!("Unable to read stderr for {}: {:?}", service_name, &err)
+            })?;
+
+        Ok(StderrTail { lines, closed })
+    }
+
+    /// The lines kept so far. Anything the task wrote just before it exited may not have been read
+    /// yet, so this waits up to `wait` for the pipe to close first.
+    pub fn lines(&self, wait: Duration) -> Vec<String> {
+        let _ = self.closed.recv_timeout(wait);
+        self.lines
+            .lock()
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Has the task's exit been recorded? Once it has, its processes have been reaped.
+pub fn has_exited(exit: &Arc<Mutex<Option<ExitInfo>>>) -> bool {
+    exit.lock().map(|exit| exit.is_some()).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExitInfo, StderrTail, STDERR_LINES};
+    use os_pipe::pipe;
+    use service::log::Log;
+    use spectral::prelude::*;
+    use std::io::Write;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[test]
+    fn test_keeps_last_lines() {
+        let (reader, mut writer) = pipe().unwrap();
+        let tail = StderrTail::start(reader, "test", Log::new()).unwrap();
+        for i in 0..(STDERR_LINES + 5) {
+            writeln!(writer, "line {}", i).unwrap();
+        }
+        drop(writer);
+
+        let lines = tail.lines(Duration::from_secs(5));
+        assert_that(&lines).has_length(STDERR_LINES);
+        assert_that(&lines[0]).is_equal_to(String::from("line 5"));
+        assert_that(&lines[STDERR_LINES - 1])
+            .is_equal_to(format!("line {}", STDERR_LINES + 4));
+    }
+
+    #[test]
+    fn test_describes_exit_code() {
+        let status = Command::new("sh").args(&["-c", "exit 3"]).status().unwrap();
+        let info = ExitInfo::new(&status, vec![]);
+        assert_that(&info.code).is_equal_to(Some(3));
+        assert_that(&info.signal).is_none();
+        assert_that(&info.exit_code()).is_equal_to(3);
+        assert_that(&info.describe().as_str()).starts_with("code 3 at ");
+    }
+
+    #[test]
+    fn test_describes_signal() {
+        let status = Command::new("sh")
+            .args(&["-c", "kill -TERM $$"])
+            .status()
+            .unwrap();
+        let info = ExitInfo::new(&status, vec![]);
+        assert_that(&info.code).is_none();
+        assert_that(&info.signal).is_equal_to(Some(15));
+        assert_that(&info.exit_code()).is_equal_to(143);
+        assert_that(&info.describe().as_str()).starts_with("signal 15 at ");
+    }
+}
@@ -0,0 +1,575 @@
+use duct::{cmd, Handle};
+use libc;
+use os_pipe::pipe;
+use service::exit::{has_exited, ExitInfo};
+use service::log::Log;
+use service::Service;
+use shellwords;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use Result;
+
+/// How long a hook gets to finish before it's killed, unless it's given a timeout.
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The names of the hooks, for the command line.
+pub const HOOK_EVENTS: &[&str] = &["pre-start", "post-start", "pre-stop", "post-exit"];
+
+/// How often a running hook is checked on.
+const HOOK_POLL: Duration = Duration::from_millis(50);
+
+/// How long to wait for the rest of a hook's output after it exits.
+const OUTPUT_GRACE: Duration = Duration::from_millis(200);
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HookEvent {
+    /// Before the command starts. If the hook fails, the command isn't started.
+    PreStart,
+    /// After the command's started. This runs alongside it.
+    PostStart,
+    /// Before straw boss stops the command, because a client asked, it was scaled away or
+    /// restarted, or the server is shutting down.
+    PreStop,
+    /// After the command exits, for whatever reason.
+    PostExit,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreStart => "pre-start",
+            HookEvent::PostStart => "post-start",
+            HookEvent::PreStop => "pre-stop",
+            HookEvent::PostExit => "post-exit",
+        }
+    }
+}
+
+impl FromStr for HookEvent {
+    type Err = ::failure::Error;
+
+    fn from_str(s: &str) -> Result<HookEvent> {
+        match s {
+            "pre-start" => Ok(HookEvent::PreStart),
+            "post-start" => Ok(HookEvent::PostStart),
+            "pre-stop" => Ok(HookEvent::PreStop),
+            "post-exit" => Ok(HookEvent::PostExit),
+            _ => Err(format_err!("Unknown hook: {:?}", s)),
+        }
+    }
+}
+
+/// Shell commands that straw boss runs around a service's lifecycle, or the whole formation's.
+/// They run as straw boss's user, with `sh -c`, in the service's directory and with its
+/// environment. They're told why they're running by `STRAWBOSS_EVENT`, `STRAWBOSS_SERVICE`, and,
+/// after an exit, `STRAWBOSS_EXIT_CODE`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Hooks {
+    pub pre_start: Option<String>,
+    pub post_start: Option<String>,
+    pub pre_stop: Option<String>,
+    pub post_exit: Option<String>,
+    /// How long each hook gets, in seconds. This defaults to `HOOK_TIMEOUT`.
+    pub timeout: Option<u64>,
+}
+
+impl Hooks {
+    /// Are there no hooks?
+    pub fn is_empty(&self) -> bool {
+        *self == Hooks::default()
+    }
+
+    /// The command for an event, if there is one.
+    pub fn get(&self, event: HookEvent) -> Option<&str> {
+        let command = match event {
+            HookEvent::PreStart => &self.pre_start,
+            HookEvent::PostStart => &self.post_start,
+            HookEvent::PreStop => &self.pre_stop,
+            HookEvent::PostExit => &self.post_exit,
+        };
+        command.as_ref().map(|command| command.as_str())
+    }
+
+    /// Set a hook's command from an event's name, or the timeout from `timeout`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        if name == "timeout" {
+            let timeout = value
+                .parse()
+                .ok()
+                .filter(|timeout| *timeout > 0)
+                .ok_or_else(|| format_err!("Invalid hook timeout: {:?}", value))?;
+            self.timeout = Some(timeout);
+            return Ok(());
+        }
+        let command = Some(String::from(value));
+        match name.parse()? {
+            HookEvent::PreStart => self.pre_start = command,
+            HookEvent::PostStart => self.post_start = command,
+            HookEvent::PreStop => self.pre_stop = command,
+            HookEvent::PostExit => self.post_exit = command,
+        }
+        Ok(())
+    }
+
+    /// How long each hook gets before it's killed.
+    pub fn timeout(&self) -> Duration {
+        self.timeout.map_or(HOOK_TIMEOUT, Duration::from_secs)
+    }
+
+    /// The hooks as `key=value` annotations, like `hook.pre-start=./bin/setup`.
+    pub fn to_annotations(&self) -> Vec<String> {
+        let events = [
+            HookEvent::PreStart,
+            HookEvent::PostStart,
+            HookEvent::PreStop,
+            HookEvent::PostExit,
+        ];
+        let mut annotations = events
+            .iter()
+            .filter_map(|event| {
+                self.get(*event).map(|command| {
+                    format!("hook.{}={}", event.name(), shellwords::escape(command))
+                })
+            }).collect::<Vec<_>>();
+        if let Some(timeout) = self.timeout {
+            annotations.push(format!("hook.timeout={}", timeout));
+        }
+        annotations
+    }
+
+    /// Run the hook for an event, if there is one, and wait for it to finish.
+    ///
+    /// # Returns
+    ///
+    /// An error if the hook couldn't be run, failed, or timed out.
+    pub fn run(
+        &self,
+        event: HookEvent,
+        service: Option<&Service>,
+        exit_code: Option<i32>,
+        log: Option<&Log>,
+    ) -> Result<()> {
+        match self.start(event, service, exit_code, log)? {
+            Some(hook) => hook.wait(),
+            None => Ok(()),
+        }
+    }
+
+    /// Start the hook for an event, if there is one, without waiting for it. Each line it writes
+    /// to stdout or stderr goes to straw boss's stdout, and into `log` if there is one, labeled
+    /// with the service and the event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: Which hook to run.
+    /// * `service`: The service it's for, or `None` for the formation's hooks.
+    /// * `exit_code`: How the service, or the server, exited, for `post-exit`.
+    /// * `log`: Where else the hook's output goes.
+    ///
+    /// # Returns
+    ///
+    /// The running hook, or `None` if there isn't one for the event. This is an error if the hook
+    /// couldn't be run.
+    pub fn start(
+        &self,
+        event: HookEvent,
+        service: Option<&Service>,
+        exit_code: Option<i32>,
+        log: Option<&Log>,
+    ) -> Result<Option<RunningHook>> {
+        let command = match self.get(event) {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        let owner = service.map_or_else(
+            || String::from("the formation"),
+            |service| service.name.clone(),
+        );
+        let label = match service {
+            Some(service) => format!("{} {}", &service.name, event.name()),
+            None => String::from(event.name()),
+        };
+
+        let (reader, writer) =
+            pipe().map_err(|err| format_err!("Unable to create pipe for hook: {:?}", &err))?;
+        let expression = cmd("sh", vec!["-c", command])
+            .env("STRAWBOSS_EVENT", event.name())
+            .stdin_null()
+            .stderr_to_stdout()
+            .stdout_file(writer)
+            .unchecked()
+            .before_spawn(|command| {
+                // The hook gets a process group of its own, so that everything it starts can be
+                // killed if it runs out of time.
+                unsafe {
+                    command.pre_exec(|| {
+                        if libc::setpgid(0, 0) != 0 {
+                            return Err(io::Error::last_os_error());
+                        }
+                        Ok(())
+                    });
+                }
+                Ok(())
+            });
+        let expression = match service {
+            Some(service) => {
+                let expression = service
+                    .env
+                    .iter()
+                    .fold(expression, |e, (key, value)| e.env(key, value))
+                    .env("STRAWBOSS_SERVICE", &service.name);
+                match service.cwd {
+                    Some(ref cwd) => expression.dir(cwd),
+                    None => expression,
+                }
+            }
+            None => expression.env_remove("STRAWBOSS_SERVICE"),
+        };
+        let expression = match exit_code {
+            Some(code) => expression.env("STRAWBOSS_EXIT_CODE", code.to_string()),
+            None => expression.env_remove("STRAWBOSS_EXIT_CODE"),
+        };
+        let handle = expression.start().map_err(|err| {
+            format_err!("Unable to run {} hook for {}: {}", event.name(), &owner, &err)
+        })?;
+        // The expression, with this process's copy of the pipe's write end, is gone by now, so
+        // the pipe closes when the hook's processes do.
+        drop(expression);
+        let (done_tx, done_rx) = channel();
+        let log = log.cloned();
+        thread::Builder::new()
+            .name(format!("{}-hook", &label))
+            .spawn(move || {
+                let mut reader = BufReader::new(reader);
+                let mut buffer = Vec::new();
+                while let Ok(count) = reader.read_until(b'\n', &mut buffer) {
+                    if count == 0 {
+                        break;
+                    }
+                    let mut line = format!("[{}] ", &label).into_bytes();
+                    line.extend(&buffer);
+                    let _ = io::stdout().write_all(&line);
+                    if let Some(ref log) = log {
+                        log.push(&line);
+                    }
+                    buffer.clear();
+                }
+                let _ = done_tx.send(());
+            }).map_err(|err| format_err!("Unable to read hook output: {:?}", &err))?;
+
+        Ok(Some(RunningHook {
+            handle,
+            event,
+            owner,
+            timeout: self.timeout(),
+            deadline: Instant::now() + self.timeout(),
+            output: done_rx,
+            finished: false,
+        }))
+    }
+}
+
+/// A hook that's been started. Dropping it before it's finished kills it, along with anything it
+/// started.
+pub struct RunningHook {
+    handle: Handle,
+    event: HookEvent,
+    owner: String,
+    timeout: Duration,
+    deadline: Instant,
+    /// Closed once all of the hook's output has been read.
+    output: Receiver<()>,
+    finished: bool,
+}
+
+impl RunningHook {
+    /// Check on the hook without waiting for it. One that's run out of time is killed.
+    ///
+    /// # Returns
+    ///
+    /// `None` while the hook's still running. Once it's finished, an error if it failed or timed
+    /// out.
+    pub fn try_finish(&mut self) -> Option<Result<()>> {
+        let output = match self.handle.try_wait() {
+            Ok(output) => output,
+            Err(err) => {
+                return Some(Err(format_err!(
+                    "Error checking on {} hook for {}: {:?}",
+                    self.event.name(),
+                    &self.owner,
+                    &err
+                )))
+            }
+        };
+        let status = match output {
+            Some(output) => Some(output.status),
+            None if Instant::now() >= self.deadline => {
+                self.kill();
+                None
+            }
+            None => return None,
+        };
+        self.finished = true;
+        let _ = self.output.recv_timeout(OUTPUT_GRACE);
+
+        Some(match status {
+            Some(status) if status.success() => Ok(()),
+            Some(status) => Err(format_err!(
+                "The {} hook for {} exited with {}.",
+                self.event.name(),
+                &self.owner,
+                match (status.code(), status.signal()) {
+                    (Some(code), _) => format!("code {}", code),
+                    (None, Some(signal)) => format!("signal {}", signal),
+                    (None, None) => String::from("an unknown status"),
+                }
+            )),
+            None => Err(format_err!(
+                "The {} hook for {} timed out after {}s.",
+                self.event.name(),
+                &self.owner,
+                self.timeout.as_secs()
+            )),
+        })
+    }
+
+    /// Wait for the hook to finish.
+    pub fn wait(mut self) -> Result<()> {
+        loop {
+            if let Some(result) = self.try_finish() {
+                return result;
+            }
+            thread::sleep(HOOK_POLL);
+        }
+    }
+
+    fn kill(&self) {
+        for pid in self.handle.pids() {
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+        let _ = self.handle.kill();
+    }
+}
+
+impl Drop for RunningHook {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.kill();
+        }
+    }
+}
+
+/// The hooks for one run of a service. The ones that run alongside it are started on threads of
+/// their own, and they're waited for when the run's over.
+pub struct ServiceHooks {
+    service: Service,
+    log: Log,
+    running: Vec<thread::JoinHandle<()>>,
+    /// The `pre-stop` hook, while it's running alongside the service.
+    pre_stop: Option<RunningHook>,
+    stopping: bool,
+    exited: bool,
+}
+
+impl ServiceHooks {
+    pub fn new(service: &Service, log: Log) -> ServiceHooks {
+        ServiceHooks {
+            service: service.clone(),
+            log,
+            running: Vec::new(),
+            pre_stop: None,
+            stopping: false,
+            exited: false,
+        }
+    }
+
+    /// Run the `pre-start` hook and wait for it.
+    pub fn before_start(&self) -> Result<()> {
+        self.service
+            .hooks
+            .run(HookEvent::PreStart, Some(&self.service), None, Some(&self.log))
+    }
+
+    /// Start the `post-start` hook.
+    pub fn after_start(&mut self) {
+        self.spawn(HookEvent::PostStart, None);
+    }
+
+    /// Start the `pre-stop` hook without waiting for it, unless it's already run or the service
+    /// has already exited. `stopping` says when it's done.
+    pub fn start_stop(&mut self, exit: &Arc<Mutex<Option<ExitInfo>>>) {
+        if self.stopping || has_exited(exit) {
+            return;
+        }
+        self.stopping = true;
+        let hooks = &self.service.hooks;
+        let service = Some(&self.service);
+        match hooks.start(HookEvent::PreStop, service, None, Some(&self.log)) {
+            Ok(hook) => self.pre_stop = hook,
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+
+    /// Is the `pre-stop` hook that `start_stop` started still running? It's reported if it
+    /// failed, once it's done.
+    pub fn stopping(&mut self) -> bool {
+        let result = match self.pre_stop {
+            Some(ref mut hook) => hook.try_finish(),
+            None => return false,
+        };
+        match result {
+            Some(result) => {
+                if let Err(err) = result {
+                    eprintln!("{}", &err);
+                }
+                self.pre_stop = None;
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Run the `pre-stop` hook and wait for it, unless it's already run or the service has
+    /// already exited. One that `start_stop` started and that's still running is killed instead,
+    /// since the service is about to be too.
+    pub fn before_stop(&mut self, exit: &Arc<Mutex<Option<ExitInfo>>>) {
+        // Dropping a running hook kills it.
+        self.pre_stop = None;
+        if self.stopping || has_exited(exit) {
+            return;
+        }
+        self.stopping = true;
+        let hooks = &self.service.hooks;
+        let service = Some(&self.service);
+        if let Err(err) = hooks.run(HookEvent::PreStop, service, None, Some(&self.log)) {
+            eprintln!("{}", &err);
+        }
+    }
+
+    /// Start the `post-exit` hook once the service's exit has been recorded. Only the first time
+    /// counts.
+    pub fn after_exit(&mut self, exit: &Arc<Mutex<Option<ExitInfo>>>) {
+        if self.exited {
+            return;
+        }
+        let exit_code = match exit.lock() {
+            Ok(exit) => exit.as_ref().map(|exit| exit.exit_code()),
+            Err(_) => None,
+        };
+        if exit_code.is_some() {
+            self.exited = true;
+            self.spawn(HookEvent::PostExit, exit_code);
+        }
+    }
+
+    /// Wait for the hooks that are still running.
+    pub fn join(&mut self) {
+        if let Some(hook) = self.pre_stop.take() {
+            if let Err(err) = hook.wait() {
+                eprintln!("{}", &err);
+            }
+        }
+        for hook in self.running.drain(..) {
+            let _ = hook.join();
+        }
+    }
+
+    fn spawn(&mut self, event: HookEvent, exit_code: Option<i32>) {
+        if self.service.hooks.get(event).is_none() {
+            return;
+        }
+        let service = self.service.clone();
+        let log = self.log.clone();
+        let spawned = thread::Builder::new().spawn(move || {
+            if let Err(err) = service.hooks.run(event, Some(&service), exit_code, Some(&log)) {
+                eprintln!("{}", &err);
+            }
+        });
+        match spawned {
+            Ok(hook) => self.running.push(hook),
+            Err(err) => eprintln!(
+                "Unable to run {} hook for {}: {:?}",
+                event.name(),
+                &self.service.name,
+                &err
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HookEvent, Hooks};
+    use service::log::Log;
+    use service::Service;
+    use spectral::prelude::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_sets_hooks_from_annotations() {
+        let mut hooks = Hooks::default();
+        hooks.set("pre-start", "./bin/setup --quick").unwrap();
+        hooks.set("timeout", "5").unwrap();
+
+        assert_that(&hooks.get(HookEvent::PreStart)).is_equal_to(Some("./bin/setup --quick"));
+        assert_that(&hooks.get(HookEvent::PostExit)).is_none();
+        assert_that(&hooks.to_annotations()).is_equal_to(vec![
+            String::from("hook.pre-start=./bin/setup\\ --quick"),
+            String::from("hook.timeout=5"),
+        ]);
+        assert_that(&hooks.set("pre-boot", "true")).is_err();
+        assert_that(&hooks.set("timeout", "0")).is_err();
+    }
+
+    #[test]
+    fn test_runs_hook_with_context() {
+        let mut service = Service::new("web", "start web-server");
+        service.env.insert(String::from("MODE"), String::from("dev"));
+        let command = "echo $STRAWBOSS_EVENT $STRAWBOSS_SERVICE $STRAWBOSS_EXIT_CODE $MODE; \
+                       echo oops >&2";
+        service.hooks.set("post-exit", command).unwrap();
+        let log = Log::new();
+
+        let result = service
+            .hooks
+            .run(HookEvent::PostExit, Some(&service), Some(3), Some(&log));
+        assert_that(&result).is_ok();
+        assert_that(&log.tail(10)).is_equal_to(vec![
+            String::from("[web post-exit] post-exit web 3 dev"),
+            String::from("[web post-exit] oops"),
+        ]);
+    }
+
+    #[test]
+    fn test_reports_failed_hook() {
+        let mut hooks = Hooks::default();
+        hooks.set("pre-start", "exit 4").unwrap();
+
+        let result = hooks.run(HookEvent::PreStart, None, None, None);
+        assert_that(&result.unwrap_err().to_string().as_str())
+            .is_equal_to("The pre-start hook for the formation exited with code 4.");
+        assert_that(&hooks.run(HookEvent::PreStop, None, None, None)).is_ok();
+    }
+
+    #[test]
+    fn test_kills_hook_that_times_out() {
+        let mut hooks = Hooks::default();
+        hooks.set("pre-stop", "sleep 30 & sleep 30").unwrap();
+        hooks.set("timeout", "1").unwrap();
+        let start = Instant::now();
+
+        let result = hooks.run(HookEvent::PreStop, None, None, None);
+        assert_that(&result.unwrap_err().to_string().as_str())
+            .is_equal_to("The pre-stop hook for the formation timed out after 1s.");
+        assert_that(&start.elapsed().as_secs()).is_less_than(3);
+    }
+}
@@ -0,0 +1,177 @@
+use libc;
+use std::io;
+use Result;
+
+/// Resource limits for a service's processes. They're set in each process after it's forked and
+/// before it runs the command, so they only apply to the service and whatever it starts.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Limits {
+    /// The most files that each process can have open (`RLIMIT_NOFILE`).
+    pub open_files: Option<u64>,
+    /// The most virtual memory that each process can have, in bytes (`RLIMIT_AS`).
+    pub address_space: Option<u64>,
+    /// The most CPU time that each process can use, in seconds (`RLIMIT_CPU`).
+    pub cpu_seconds: Option<u64>,
+    /// The largest core dump that each process can write, in bytes (`RLIMIT_CORE`).
+    pub core_size: Option<u64>,
+    /// The processes' niceness, from -20 (most favorable) to 19 (least).
+    pub nice: Option<i32>,
+}
+
+/// The `limit.NAME` annotations, in the order they're written.
+const LIMIT_NAMES: &[&str] = &["nofile", "as", "cpu", "core"];
+
+impl Limits {
+    /// Are there no limits to set?
+    pub fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    /// Set a limit from a `limit.NAME` annotation. The names are `nofile`, `as`, `cpu`, and
+    /// `core`. Sizes can have a `K`, `M`, or `G` suffix, and any limit can be `unlimited`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "nofile" => self.open_files = Some(parse_limit(value, false)?),
+            "as" => self.address_space = Some(parse_limit(value, true)?),
+            "cpu" => self.cpu_seconds = Some(parse_limit(value, false)?),
+            "core" => self.core_size = Some(parse_limit(value, true)?),
+            _ => return Err(format_err!("Unknown limit: {:?}", name)),
+        }
+        Ok(())
+    }
+
+    /// Set the niceness from a `nice` annotation.
+    pub fn set_nice(&mut self, value: &str) -> Result<()> {
+        let nice = value
+            .parse()
+            .ok()
+            .filter(|nice| *nice >= -20 && *nice <= 19)
+            .ok_or_else(|| format_err!("Invalid nice value {:?}. It has to be -20 to 19.", value))?;
+        self.nice = Some(nice);
+        Ok(())
+    }
+
+    /// The limits as `key=value` annotations, like `limit.nofile=1024` and `nice=10`.
+    pub fn to_annotations(&self) -> Vec<String> {
+        let limits = [
+            self.open_files,
+            self.address_space,
+            self.cpu_seconds,
+            self.core_size,
+        ];
+        LIMIT_NAMES
+            .iter()
+            .zip(limits.iter())
+            .filter_map(|(name, limit)| {
+                limit.map(|limit| format!("limit.{}={}", name, show(limit)))
+            })
+            .chain(self.nice.map(|nice| format!("nice={}", nice)))
+            .collect()
+    }
+
+    /// Apply the limits to the current process. This is meant to run in a forked child, right
+    /// before it runs the command, so it only makes system calls.
+    pub fn apply(&self) -> io::Result<()> {
+        if let Some(limit) = self.open_files {
+            set_rlimit(libc::RLIMIT_NOFILE, limit)?;
+        }
+        if let Some(limit) = self.address_space {
+            set_rlimit(libc::RLIMIT_AS, limit)?;
+        }
+        if let Some(limit) = self.cpu_seconds {
+            set_rlimit(libc::RLIMIT_CPU, limit)?;
+        }
+        if let Some(limit) = self.core_size {
+            set_rlimit(libc::RLIMIT_CORE, limit)?;
+        }
+        if let Some(nice) = self.nice {
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(target_os = "linux"))]
+type Resource = libc::c_int;
+
+fn set_rlimit(resource: Resource, limit: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        rlim_max: limit as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Parse a limit. `unlimited` is `RLIM_INFINITY`, and sizes can have a `K`, `M`, or `G` suffix.
+fn parse_limit(value: &str, is_size: bool) -> Result<u64> {
+    if value == "unlimited" {
+        return Ok(libc::RLIM_INFINITY as u64);
+    }
+    let invalid = || format_err!("Invalid limit: {:?}", value);
+    let (number, multiplier) = match value.chars().last() {
+        Some('K') if is_size => (&value[..value.len() - 1], 1 << 10),
+        Some('M') if is_size => (&value[..value.len() - 1], 1 << 20),
+        Some('G') if is_size => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    number
+        .parse::<u64>()
+        .map_err(|_| invalid())?
+        .checked_mul(multiplier)
+        .ok_or_else(invalid)
+}
+
+/// Show a limit the way it's written in an annotation.
+fn show(limit: u64) -> String {
+    if limit == libc::RLIM_INFINITY as u64 {
+        String::from("unlimited")
+    } else {
+        limit.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Limits;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_sets_limits() {
+        let mut limits = Limits::default();
+        limits.set("nofile", "1024").unwrap();
+        limits.set("as", "512M").unwrap();
+        limits.set("cpu", "60").unwrap();
+        limits.set("core", "unlimited").unwrap();
+        limits.set_nice("10").unwrap();
+
+        assert_that(&limits.open_files).is_equal_to(Some(1024));
+        assert_that(&limits.address_space).is_equal_to(Some(512 * 1024 * 1024));
+        assert_that(&limits.cpu_seconds).is_equal_to(Some(60));
+        assert_that(&limits.nice).is_equal_to(Some(10));
+        assert_that(&limits.to_annotations()).is_equal_to(vec![
+            String::from("limit.nofile=1024"),
+            String::from("limit.as=536870912"),
+            String::from("limit.cpu=60"),
+            String::from("limit.core=unlimited"),
+            String::from("nice=10"),
+        ]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_limits() {
+        let mut limits = Limits::default();
+        assert_that(&limits.set("rss", "1024")).is_err();
+        assert_that(&limits.set("nofile", "1K")).is_err();
+        assert_that(&limits.set("as", "lots")).is_err();
+        assert_that(&limits.set_nice("20")).is_err();
+        assert_that(&limits.is_empty()).is_true();
+    }
+}
@@ -0,0 +1,101 @@
+use os_pipe::PipeReader;
+use std::collections::VecDeque;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use Result;
+
+/// How many lines of each service's output are kept for `top`.
+pub const LOG_LINES: usize = 500;
+
+/// The last lines that a service wrote to stdout and stderr. A worker keeps the same log for all
+/// of its runs, so it carries on across restarts.
+#[derive(Debug, Clone, Default)]
+pub struct Log {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Log {
+    pub fn new() -> Log {
+        Log::default()
+    }
+
+    /// Add a line, dropping the oldest one if the log's full.
+    pub fn push(&self, line: &[u8]) {
+        if let Ok(mut lines) = self.lines.lock() {
+            if lines.len() == LOG_LINES {
+                lines.pop_front();
+            }
+            let line = String::from_utf8_lossy(line);
+            lines.push_back(String::from(line.trim_right_matches('\n')));
+        }
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        self.lines
+            .lock()
+            .map(|lines| {
+                let skip = lines.len().saturating_sub(count);
+                lines.iter().skip(skip).cloned().collect()
+            }).unwrap_or_default()
+    }
+}
+
+/// Copy a service's stdout through to straw boss's own stdout on a separate thread, adding each
+/// line to the service's log on the way.
+pub fn tee_stdout(reader: PipeReader, service_name: &str, log: Log) -> Result<()> {
+    thread::Builder::new()
+        .name(format!("{}-stdout", service_name))
+        .spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut buffer = Vec::new();
+            while let Ok(count) = reader.read_until(b'\n', &mut buffer) {
+                if count == 0 {
+                    break;
+                }
+                let _ = io::stdout().write_all(&buffer);
+                log.push(&buffer);
+                buffer.clear();
+            }
+        }).map_err(|err| format_err!("Unable to read stdout for {}: {:?}", service_name, &err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{tee_stdout, Log, LOG_LINES};
+    use os_pipe::pipe;
+    use spectral::prelude::*;
+    use std::io::Write;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_keeps_last_lines() {
+        let log = Log::new();
+        for i in 0..(LOG_LINES + 5) {
+            log.push(format!("line {}\n", i).as_bytes());
+        }
+
+        assert_that(&log.tail(LOG_LINES * 2)).has_length(LOG_LINES);
+        assert_that(&log.tail(2)).is_equal_to(vec![
+            format!("line {}", LOG_LINES + 3),
+            format!("line {}", LOG_LINES + 4),
+        ]);
+    }
+
+    #[test]
+    fn test_tees_stdout_into_log() {
+        let (reader, mut writer) = pipe().unwrap();
+        let log = Log::new();
+        tee_stdout(reader, "test", log.clone()).unwrap();
+        writeln!(writer, "hello").unwrap();
+        writeln!(writer, "world").unwrap();
+        drop(writer);
+        thread::sleep(Duration::from_millis(200));
+
+        assert_that(&log.tail(10)).is_equal_to(vec![String::from("hello"), String::from("world")]);
+    }
+}
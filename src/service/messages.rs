@@ -1,17 +1,32 @@
+use std::time::Duration;
+
 /// Messages to the service workers.
 #[derive(Eq, Ord, PartialEq, PartialOrd, Debug)]
 pub enum TaskMessage {
     /// Wait for the thing to finish.
     Join,
-    /// Kill the running service.
+    /// Run the service's `pre-stop` hook, then kill it.
     Kill,
+    /// Start the service's `pre-stop` hook, and once it's done, send the service a signal to ask it
+    /// to stop. If it hasn't exited after the grace period, it's killed.
+    Stop(i32, Duration),
+    /// Send a signal to the running service.
+    Signal(i32),
+    /// Check whether the service has finished, without waiting for it.
+    TryWait,
 }
 
 /// Response to messages.
-use std::process::Output;
+use std::process::{ExitStatus, Output};
 
 #[derive(Debug)]
 pub enum TaskResponse {
+    /// The process was spawned. These are the IDs of the processes in its pipeline.
+    Started(Vec<u32>),
+    /// The process couldn't be spawned. This is the error.
+    Failed(String),
     /// The result of the running process after it's finished.
     Joined(Output),
+    /// The process's exit status, if it has finished.
+    Waited(Option<ExitStatus>),
 }
@@ -1,19 +1,48 @@
-use duct::{cmd, Expression};
+use duct::{cmd, Expression, Handle};
+use env::expand;
 use failure::Error;
+use libc;
+use os_pipe::pipe;
+use service::cgroup::{Cgroup, CgroupLimits, Slice};
+use service::exit::{has_exited, ExitInfo, StderrTail};
+use service::hooks::{Hooks, ServiceHooks};
+use service::limits::Limits;
+use service::log::{tee_stdout, Log};
 use service::messages::{TaskMessage, TaskResponse};
+use service::schedule::{Overlap, Schedule};
+use service::user::Credentials;
 use shellwords;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::io;
 use std::io::BufRead;
 use std::iter::FromIterator;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::ExitStatus;
 use std::str::FromStr;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use Result;
 
+pub mod cgroup;
+pub mod exit;
+pub mod hooks;
+pub mod limits;
+pub mod log;
 pub mod messages;
+pub mod schedule;
+pub mod usage;
+pub mod user;
 pub mod worker;
 
+/// How often a running task checks whether its process has exited.
+const EXIT_POLL: Duration = Duration::from_millis(100);
+
+/// How long to wait for the rest of a task's stderr after it exits.
+const STDERR_GRACE: Duration = Duration::from_millis(200);
+
 type CommandArgs = Vec<String>;
 type CommandList = Vec<CommandArgs>;
 
@@ -24,6 +53,62 @@ pub struct Service {
     pub name: String,
     /// The command to execute to start this service.
     pub command: String,
+    /// The directory to run the command in. When this is read from a `Procfile`, relative paths
+    /// are resolved against the `Procfile`'s directory.
+    pub cwd: Option<PathBuf>,
+    /// Environment variables to set for the command. `$NAME` references in the command are
+    /// expanded from these.
+    pub env: BTreeMap<String, String>,
+    /// Glob patterns, relative to `cwd`, for the files that the service is restarted for when
+    /// they change. These are only watched when the server is started with `--watch`.
+    pub watch: Vec<String>,
+    /// Whether this is a long-lived service or a task that runs once before the others start.
+    pub kind: ServiceKind,
+    /// When to run the service, if it runs on a schedule instead of all the time.
+    pub schedule: Option<Schedule>,
+    /// What to do when a scheduled run is due while the last one's still going.
+    pub overlap: Overlap,
+    /// Resource limits and niceness for the service's processes.
+    pub limits: Limits,
+    /// Settings for the service's cgroup, when the server puts services in cgroups.
+    pub cgroup: CgroupLimits,
+    /// The user to run the service as, by name or ID.
+    pub user: Option<String>,
+    /// The group to run the service as, by name or ID. This defaults to the user's group.
+    pub group: Option<String>,
+    /// Commands to run before and after the service starts and stops.
+    pub hooks: Hooks,
+}
+
+/// What sort of process a service is.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ServiceKind {
+    /// A process that keeps running until it's stopped.
+    Service,
+    /// A task, like database migrations, that runs to completion before the long-lived services
+    /// start. These run one at a time, in the order they're declared, and each has to succeed
+    /// before anything else starts.
+    Oneshot,
+}
+
+impl Default for ServiceKind {
+    fn default() -> ServiceKind {
+        ServiceKind::Service
+    }
+}
+
+impl FromStr for ServiceKind {
+    type Err = Error;
+
+    /// Parse a kind from an annotation. `release` is another name for `oneshot`, after the
+    /// process type that Heroku runs before a release goes out.
+    fn from_str(s: &str) -> Result<ServiceKind> {
+        match s {
+            "service" => Ok(ServiceKind::Service),
+            "oneshot" | "release" => Ok(ServiceKind::Oneshot),
+            _ => Err(format_err!("Unknown service kind: {:?}", &s)),
+        }
+    }
 }
 
 impl Service {
@@ -51,11 +136,207 @@ impl Service {
         Service {
             name: String::from(name),
             command: String::from(command),
+            cwd: None,
+            env: BTreeMap::new(),
+            watch: Vec::new(),
+            kind: ServiceKind::default(),
+            schedule: None,
+            overlap: Overlap::default(),
+            limits: Limits::default(),
+            cgroup: CgroupLimits::default(),
+            user: None,
+            group: None,
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Set an attribute on the service from a `key=value` annotation in the `Procfile`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The name of the attribute. This is `cwd` for the working directory, `watch` for a
+    ///   comma-separated list of globs to watch, `kind` for `service` or `oneshot` (or `release`),
+    ///   `schedule` for a cron expression or an interval like `@every 5m`, `overlap` for `skip` or
+    ///   `queue`, `limit.NAME` for the resource limit `NAME` (`nofile`, `as`, `cpu`, or `core`),
+    ///   `nice` for the niceness, `memory.max`, `cpu.max`, or `pids.max` for the cgroup setting of
+    ///   the same name, `user` or `group` to run the service as, `hook.EVENT` for the command to
+    ///   run at `EVENT` (`pre-start`, `post-start`, `pre-stop`, or `post-exit`), `hook.timeout` for
+    ///   how many seconds each hook gets, or `env.NAME` to set the environment variable `NAME`.
+    /// * `value`: The attribute's value.
+    ///
+    /// # Returns
+    ///
+    /// An error if the key isn't a known attribute.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use straw_boss::service::{Service, ServiceKind};
+    ///
+    /// let mut service = Service::new("web", "start web-server");
+    /// service.annotate("cwd", "public").unwrap();
+    /// service.annotate("env.RACK_ENV", "production").unwrap();
+    /// service.annotate("kind", "oneshot").unwrap();
+    /// assert_eq!(Some(PathBuf::from("public")), service.cwd);
+    /// assert_eq!(ServiceKind::Oneshot, service.kind);
+    /// assert_eq!(Some(&String::from("production")), service.env.get("RACK_ENV"));
+    /// assert!(service.annotate("colour", "blue").is_err());
+    /// ```
+    pub fn annotate(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "cwd" => {
+                self.cwd = Some(PathBuf::from(value));
+                Ok(())
+            }
+            "watch" => {
+                self.watch.extend(
+                    value
+                        .split(',')
+                        .map(|glob| glob.trim())
+                        .filter(|glob| !glob.is_empty())
+                        .map(String::from),
+                );
+                Ok(())
+            }
+            "kind" => {
+                self.kind = self.parse_value(value)?;
+                Ok(())
+            }
+            "schedule" => {
+                self.schedule = Some(self.parse_value(value)?);
+                Ok(())
+            }
+            "overlap" => {
+                self.overlap = self.parse_value(value)?;
+                Ok(())
+            }
+            "user" => {
+                self.user = Some(String::from(value));
+                Ok(())
+            }
+            "group" => {
+                self.group = Some(String::from(value));
+                Ok(())
+            }
+            "nice" => self.limits.set_nice(value).map_err(|err| {
+                format_err!("Invalid annotation for service {}: {}", &self.name, &err)
+            }),
+            _ if CgroupLimits::is_setting(key) => self.cgroup.set(key, value).map_err(|err| {
+                format_err!("Invalid annotation for service {}: {}", &self.name, &err)
+            }),
+            _ if key.starts_with(HOOK_ANNOTATION_PREFIX) => {
+                let name = &key[HOOK_ANNOTATION_PREFIX.len()..];
+                let result = self.hooks.set(name, value);
+                result.map_err(|err| {
+                    format_err!("Invalid annotation for service {}: {}", &self.name, &err)
+                })
+            }
+            _ if key.starts_with(LIMIT_ANNOTATION_PREFIX) => {
+                let name = &key[LIMIT_ANNOTATION_PREFIX.len()..];
+                let result = self.limits.set(name, value);
+                result.map_err(|err| {
+                    format_err!("Invalid annotation for service {}: {}", &self.name, &err)
+                })
+            }
+            _ if key.starts_with(ENV_ANNOTATION_PREFIX)
+                && key.len() > ENV_ANNOTATION_PREFIX.len() =>
+            {
+                let name = &key[ENV_ANNOTATION_PREFIX.len()..];
+                self.env.insert(String::from(name), String::from(value));
+                Ok(())
+            }
+            _ => Err(format_err!(
+                "Unknown annotation for service {}: {:?}",
+                &self.name,
+                &key
+            )),
+        }
+    }
+
+    /// Look up the user and group that the service runs as. This fails if either doesn't exist.
+    pub fn credentials(&self) -> Result<Option<Credentials>> {
+        let user = self.user.as_ref().map(|user| user.as_str());
+        let group = self.group.as_ref().map(|group| group.as_str());
+        Credentials::lookup(user, group)
+            .map_err(|err| format_err!("Unable to run service {}: {}", &self.name, &err))
+    }
+
+    /// Parse an annotation's value, saying which service it's for if it's invalid.
+    fn parse_value<T: FromStr<Err = Error>>(&self, value: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|err| format_err!("Invalid annotation for service {}: {}", &self.name, &err))
+    }
+
+    /// Render the service as it would appear in a `Procfile`. Settings that the line can't hold
+    /// are written as an annotation line before it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    /// use straw_boss::service::Service;
+    ///
+    /// let mut service = Service::new("web", "start web-server");
+    /// assert_eq!("web: start web-server\n", service.to_procfile());
+    ///
+    /// service.cwd = Some(PathBuf::from("my app"));
+    /// service.env.insert(String::from("MODE"), String::from("dev"));
+    /// assert_eq!(
+    ///     "#@ cwd=my\\ app env.MODE=dev\nweb: start web-server\n",
+    ///     service.to_procfile()
+    /// );
+    /// ```
+    pub fn to_procfile(&self) -> String {
+        let mut annotations = Vec::new();
+        if let Some(ref cwd) = self.cwd {
+            annotations.push(format!("cwd={}", shellwords::escape(&cwd.to_string_lossy())));
+        }
+        if !self.watch.is_empty() {
+            annotations.push(format!("watch={}", shellwords::escape(&self.watch.join(","))));
+        }
+        if self.kind == ServiceKind::Oneshot {
+            annotations.push(String::from("kind=oneshot"));
+        }
+        if let Some(ref schedule) = self.schedule {
+            annotations.push(format!("schedule={}", shellwords::escape(&schedule.to_string())));
+        }
+        if self.overlap == Overlap::Queue {
+            annotations.push(String::from("overlap=queue"));
+        }
+        annotations.extend(self.limits.to_annotations());
+        annotations.extend(self.cgroup.to_annotations());
+        if let Some(ref user) = self.user {
+            annotations.push(format!("user={}", shellwords::escape(user)));
+        }
+        if let Some(ref group) = self.group {
+            annotations.push(format!("group={}", shellwords::escape(group)));
+        }
+        annotations.extend(self.hooks.to_annotations());
+        for (key, value) in &self.env {
+            annotations.push(format!(
+                "{}{}={}",
+                ENV_ANNOTATION_PREFIX,
+                key,
+                shellwords::escape(value)
+            ));
+        }
+
+        let line = format!("{}: {}\n", &self.name, &self.command);
+        if annotations.is_empty() {
+            line
+        } else {
+            format!("{} {}\n{}", ANNOTATION_PREFIX, annotations.join(" "), line)
         }
     }
 
     /// Parses the data from a Procfile into a sequence of `Service` objects.
     ///
+    /// Comment lines that start with `#@` are annotations. They hold whitespace-separated
+    /// `key=value` pairs that apply to the next service in the file. Because they're comments,
+    /// other Procfile tools just ignore them.
+    ///
     /// # Arguments
     ///
     /// * `input`: Something that implements `std::io::Read`.
@@ -68,28 +349,65 @@ impl Service {
     /// # Example
     ///
     /// ```rust
+    /// use std::path::PathBuf;
     /// use straw_boss::service::Service;
     ///
     /// let input = b"web: start web-server\n\
     ///               worker: start worker\n\
+    ///               #@ cwd=queues\n\
     ///               queue: queue-mgr\n";
     /// let services = Service::read_procfile(&input[..]).unwrap();
+    /// let mut queue = Service::new("queue", "queue-mgr");
+    /// queue.cwd = Some(PathBuf::from("queues"));
     /// assert_eq!(services, vec![
     ///     Service::new("web", "start web-server"),
     ///     Service::new("worker", "start worker"),
-    ///     Service::new("queue", "queue-mgr"),
+    ///     queue,
     /// ]);
     /// ```
     pub fn read_procfile<R: io::Read>(input: R) -> Result<Vec<Service>> {
-        io::BufReader::new(input)
-            .lines()
-            .filter_map(|result| result.ok())
-            .filter(|line| !line.trim_left().starts_with('#'))
-            .map(|s| s.parse())
-            .collect::<Result<Vec<Service>>>()
+        let mut services = Vec::new();
+        let mut annotations = Vec::new();
+
+        for line in io::BufReader::new(input).lines().filter_map(|result| result.ok()) {
+            let trimmed = line.trim_left();
+            if trimmed.starts_with(ANNOTATION_PREFIX) {
+                annotations.extend(parse_annotations(&trimmed[ANNOTATION_PREFIX.len()..])?);
+            } else if !trimmed.starts_with('#') {
+                let mut service: Service = line.parse()?;
+                for (key, value) in annotations.drain(..) {
+                    service.annotate(&key, &value)?;
+                }
+                services.push(service);
+            }
+        }
+
+        Ok(services)
     }
 }
 
+const ANNOTATION_PREFIX: &str = "#@";
+const ENV_ANNOTATION_PREFIX: &str = "env.";
+const LIMIT_ANNOTATION_PREFIX: &str = "limit.";
+const HOOK_ANNOTATION_PREFIX: &str = "hook.";
+
+/// Split the body of an annotation line into its `key=value` pairs. Values can be quoted like
+/// shell words.
+fn parse_annotations(line: &str) -> Result<Vec<(String, String)>> {
+    shellwords::split(line)
+        .map_err(|err| format_err!("Unable to parse annotation: {}: {:?}", &line, &err))?
+        .into_iter()
+        .map(|word| {
+            let mut parts = word.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if !key.is_empty() => {
+                    Ok((String::from(key), String::from(value)))
+                }
+                _ => Err(format_err!("Invalid annotation: {:?}", &word)),
+            }
+        }).collect()
+}
+
 impl FromStr for Service {
     type Err = Error;
 
@@ -144,7 +462,7 @@ impl FromStr for Service {
 ///
 /// ```rust
 /// use straw_boss::service::{index_services, Service};
-/// use std::collections::HashMap;
+/// use std::collections::{BTreeMap, HashMap};
 ///
 /// let services = vec![
 ///     Service::new("web", "start web-server"),
@@ -200,48 +518,289 @@ impl TryFrom<Service> for Expression {
     /// assert_eq!("hello, world", output.trim());
     /// ```
     fn try_from(service: Service) -> Result<Expression> {
-        let commands = split_piped_commands(&service.command)?;
+        // Variables are expanded before the command is split, so that quoting works like it does
+        // in the shell.
+        let commands = split_piped_commands(&expand(&service.command, &service.env))?;
         let mut commands = commands.into_iter();
         let initial = commands
             .next()
             .map(|command| cmd(&command[0], &command[1..]))
             .ok_or_else(|| format_err!("Invalid pipeline. No command."))?;
         let pipeline = commands.fold(initial, |p, c| p.pipe(cmd(&c[0], &c[1..])));
+        let pipeline = match service.cwd {
+            Some(ref cwd) => pipeline.dir(cwd),
+            None => pipeline,
+        };
+        let pipeline = service
+            .env
+            .iter()
+            .fold(pipeline, |p, (key, value)| p.env(key, value));
+        // The user's switched last, after the limits, because it can take away the permission to
+        // set them.
+        let pipeline = match service.credentials()? {
+            Some(credentials) => pipeline.before_spawn(move |command| {
+                let credentials = credentials.clone();
+                unsafe {
+                    command.pre_exec(move || credentials.apply());
+                }
+                Ok(())
+            }),
+            None => pipeline,
+        };
+        let pipeline = if service.limits.is_empty() {
+            pipeline
+        } else {
+            let limits = service.limits;
+            pipeline.before_spawn(move |command| {
+                unsafe {
+                    command.pre_exec(move || limits.apply());
+                }
+                Ok(())
+            })
+        };
 
         Ok(pipeline)
     }
 }
 
 /// This takes the channels to communicate over and the service to run, and it executes the
-/// service. This is meant to be run in a new thread.
-pub fn run(service: Service, rx: Receiver<TaskMessage>, tx: Sender<TaskResponse>) -> Result<()> {
+/// service. This is meant to be run in a new thread. It reports whether the service could be
+/// spawned, and then it handles messages until it's told to join or kill the service. Between
+/// messages, it watches for the service to exit, and it records how it did in `exit`. If the worker
+/// lets go of the channel, a service that's still running is killed. With a `slice`, the service
+/// runs in a cgroup of its own in it. Its output, and its hooks', is added to `log`. The thread
+/// doesn't finish until the hooks it started have.
+pub fn run(
+    service: Service,
+    slice: Option<Slice>,
+    log: Log,
+    rx: Receiver<TaskMessage>,
+    tx: Sender<TaskResponse>,
+    exit: Arc<Mutex<Option<ExitInfo>>>,
+) -> Result<()> {
     let service_name = service.name.clone();
-    let handle = Expression::try_from(service)?.start()?;
-
-    let message = rx.recv().map_err(|err| {
+    let mut hooks = ServiceHooks::new(&service, log.clone());
+    let spawned = hooks.before_start().and_then(|_| {
+        // The service can still run without its cgroup, just without its limits.
+        let cgroup = create_cgroup(&service, slice.as_ref()).unwrap_or_else(|err| {
+            eprintln!("Running {} without a cgroup. {}", &service.name, &err);
+            None
+        });
+        spawn(service, cgroup.as_ref(), log).map(|(handle, stderr)| (handle, stderr, cgroup))
+    });
+    let (handle, stderr, cgroup) = match spawned {
+        Ok(spawned) => spawned,
+        Err(err) => {
+            let _ = tx.send(TaskResponse::Failed(err.to_string()));
+            return Err(err);
+        }
+    };
+    tx.send(TaskResponse::Started(handle.pids())).map_err(|err| {
         format_err!(
-            "Unable to receive message for service {:?}: {:?}",
+            "Error while sending start for service {}: {:?}",
             &service_name,
             &err
         )
     })?;
-    match message {
-        TaskMessage::Join => {
-            let output = handle.output().map_err(|err| {
-                format_err!("Error waiting for service {}: {:?}", &service_name, &err)
-            })?;
-            tx.send(TaskResponse::Joined(output)).map_err(|err| {
-                format_err!(
-                    "Error while sending wait for service {}: {:?}",
-                    &service_name,
-                    &err
-                )
-            })
+    hooks.after_start();
+
+    // A stop's signal waits for the `pre-stop` hook, which runs alongside the task so that it can
+    // still be checked on. Once it's sent, the task has a grace period to exit before it's killed.
+    let mut pending_stop = None;
+    let mut kill_at = None;
+    let mut joining = false;
+    loop {
+        if let Some((signal, grace)) = pending_stop.filter(|_| !hooks.stopping()) {
+            pending_stop = None;
+            if !has_exited(&exit) {
+                signal_service(&handle, cgroup.as_ref(), signal);
+                kill_at = Some(Instant::now() + grace);
+            }
+        }
+        if has_exited(&exit) {
+            kill_at = None;
+        } else if kill_at.map_or(false, |kill_at| Instant::now() >= kill_at) {
+            kill_at = None;
+            eprintln!("{} didn't stop in time. Killing it.", &service_name);
+            signal_service(&handle, cgroup.as_ref(), libc::SIGKILL);
+        }
+        let stopping = pending_stop.is_some() || kill_at.is_some();
+        let received = if joining && !stopping {
+            Ok(TaskMessage::Join)
+        } else {
+            rx.recv_timeout(EXIT_POLL)
+        };
+        let message = match received {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => {
+                check_exit(&service_name, &handle, &stderr, &exit)?;
+                hooks.after_exit(&exit);
+                continue;
+            }
+            // The worker let go of the task. Its processes could be another run's by now, so an
+            // exited one only has its hooks finished.
+            Err(RecvTimeoutError::Disconnected) if has_exited(&exit) => {
+                hooks.after_exit(&exit);
+                hooks.join();
+                return Ok(());
+            }
+            Err(RecvTimeoutError::Disconnected) => TaskMessage::Kill,
+        };
+        match message {
+            // Waiting for the task would hold up a stop that's under way, so that goes first.
+            TaskMessage::Join if stopping => joining = true,
+            TaskMessage::Join => {
+                let output = handle.output().map_err(|err| {
+                    format_err!("Error waiting for service {}: {:?}", &service_name, &err)
+                })?;
+                record_exit(&service_name, &output.status, &stderr, &exit)?;
+                hooks.after_exit(&exit);
+                hooks.join();
+                return tx.send(TaskResponse::Joined(output)).map_err(|err| {
+                    format_err!(
+                        "Error while sending wait for service {}: {:?}",
+                        &service_name,
+                        &err
+                    )
+                });
+            }
+            TaskMessage::Kill => {
+                hooks.before_stop(&exit);
+                // Once the task's been reaped, its process IDs could belong to something else.
+                if !has_exited(&exit) {
+                    signal_groups(&handle, libc::SIGKILL);
+                }
+                // Anything that's left the service's process groups is still in its cgroup.
+                if let Some(ref cgroup) = cgroup {
+                    let _ = cgroup.kill();
+                }
+                handle.kill().map_err(|err| {
+                    format_err!("Error killing service {}: {:?}", &service_name, &err)
+                })?;
+                check_exit(&service_name, &handle, &stderr, &exit)?;
+                hooks.after_exit(&exit);
+                hooks.join();
+                return Ok(());
+            }
+            TaskMessage::Stop(signal, grace) => {
+                hooks.start_stop(&exit);
+                pending_stop = Some((signal, grace));
+            }
+            TaskMessage::Signal(signal) => {
+                if !has_exited(&exit) {
+                    signal_service(&handle, cgroup.as_ref(), signal);
+                }
+            }
+            TaskMessage::TryWait => {
+                let status = check_exit(&service_name, &handle, &stderr, &exit)?;
+                hooks.after_exit(&exit);
+                tx.send(TaskResponse::Waited(status)).map_err(|err| {
+                    format_err!(
+                        "Error while sending status for service {}: {:?}",
+                        &service_name,
+                        &err
+                    )
+                })?;
+            }
         }
-        TaskMessage::Kill => {
-            handle
-                .kill()
-                .map_err(|err| format_err!("Error killing service {}: {:?}", &service_name, &err))
+    }
+}
+
+/// Create the service's cgroup in the slice, if there is one.
+fn create_cgroup(service: &Service, slice: Option<&Slice>) -> Result<Option<Cgroup>> {
+    match slice {
+        Some(slice) => Cgroup::create(slice.cgroup_path(&service.name), &service.cgroup).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Start the service's pipeline. Its stdout and stderr go through pipes, so that they can be added
+/// to its log and the end of its stderr can be kept. This means the service never writes to a
+/// terminal, even when straw boss is running in one. With a cgroup, the processes join it before
+/// they run their commands, so nothing they start can get out of it.
+fn spawn(service: Service, cgroup: Option<&Cgroup>, log: Log) -> Result<(Handle, StderrTail)> {
+    let command = service.command.clone();
+    let service_name = service.name.clone();
+    let (reader, writer) =
+        pipe().map_err(|err| format_err!("Unable to create pipe for stderr: {:?}", &err))?;
+    let (stdout_reader, stdout_writer) =
+        pipe().map_err(|err| format_err!("Unable to create pipe for stdout: {:?}", &err))?;
+    let procs = cgroup.map(|cgroup| cgroup.procs_fd());
+    let handle = Expression::try_from(service)?
+        .unchecked()
+        .stdout_file(stdout_writer)
+        .stderr_file(writer)
+        .before_spawn(move |command| {
+            // Each process gets its own process group, so that signals reach everything it starts
+            // and the terminal's Ctrl-C only reaches straw boss.
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::setpgid(0, 0) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    match procs {
+                        Some(procs) => cgroup::join(procs),
+                        None => Ok(()),
+                    }
+                });
+            }
+            Ok(())
+        }).start()
+        .map_err(|err| format_err!("Unable to run `{}`: {}", &command, &err))?;
+    // The expression, with this process's copies of the pipes' write ends, is gone by now, so the
+    // pipes close when the service's processes do.
+    tee_stdout(stdout_reader, &service_name, log.clone())?;
+    let stderr = StderrTail::start(reader, &service_name, log)?;
+    Ok((handle, stderr))
+}
+
+/// Check whether the task has finished, and record how if it has.
+fn check_exit(
+    service_name: &str,
+    handle: &Handle,
+    stderr: &StderrTail,
+    exit: &Arc<Mutex<Option<ExitInfo>>>,
+) -> Result<Option<ExitStatus>> {
+    let status = handle
+        .try_wait()
+        .map_err(|err| format_err!("Error checking on service {}: {:?}", &service_name, &err))?
+        .map(|output| output.status);
+    if let Some(ref status) = status {
+        record_exit(service_name, status, stderr, exit)?;
+    }
+    Ok(status)
+}
+
+/// Record how the task finished. Only the first time counts.
+fn record_exit(
+    service_name: &str,
+    status: &ExitStatus,
+    stderr: &StderrTail,
+    exit: &Arc<Mutex<Option<ExitInfo>>>,
+) -> Result<()> {
+    let mut exit = exit
+        .lock()
+        .map_err(|err| format_err!("Unable to record exit for {}: {:?}", service_name, &err))?;
+    if exit.is_none() {
+        *exit = Some(ExitInfo::new(status, stderr.lines(STDERR_GRACE)));
+    }
+    Ok(())
+}
+
+/// Send a signal to the service. With a cgroup, that's every process in it. Without one, or if its
+/// processes can't be listed, it's the process groups of the service's pipeline.
+fn signal_service(handle: &Handle, cgroup: Option<&Cgroup>, signal: i32) {
+    let signaled = cgroup.map_or(false, |cgroup| cgroup.signal(signal).is_ok());
+    if !signaled {
+        signal_groups(handle, signal);
+    }
+}
+
+/// Send a signal to the process groups of all of the processes in the service's pipeline.
+fn signal_groups(handle: &Handle, signal: i32) {
+    for pid in handle.pids() {
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), signal);
         }
     }
 }
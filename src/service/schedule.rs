@@ -0,0 +1,206 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use cron;
+use failure::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+use Result;
+
+/// How many of a scheduled service's runs are kept for `status`.
+pub const RUN_HISTORY: usize = 10;
+
+/// When a scheduled service runs. This is either a cron expression, like `*/5 * * * *` or
+/// `@daily`, or a fixed interval, like `@every 90s`. Cron expressions can have five fields, or six
+/// with seconds first. Times are local.
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Schedule(String);
+
+/// What the parsed schedule does.
+enum Spec {
+    Cron(cron::Schedule),
+    Every(Duration),
+}
+
+impl Schedule {
+    /// The next time the service should run after `after`. This is `None` if it never does.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match parse_spec(&self.0).ok()? {
+            Spec::Cron(schedule) => schedule.after(&after).next(),
+            Spec::Every(interval) => Some(after + interval),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Schedule> {
+        let s = s.trim();
+        parse_spec(s)?;
+        Ok(Schedule(String::from(s)))
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+fn parse_spec(s: &str) -> Result<Spec> {
+    if s.starts_with("@every") {
+        return parse_interval(s["@every".len()..].trim()).map(Spec::Every);
+    }
+
+    // The cron crate wants seconds, but the usual crontab line doesn't have them.
+    let expression = if s.split_whitespace().count() == 5 {
+        format!("0 {}", s)
+    } else {
+        String::from(s)
+    };
+    cron::Schedule::from_str(&expression)
+        .map(Spec::Cron)
+        .map_err(|err| format_err!("Invalid schedule {:?}: {}", s, &err))
+}
+
+/// Parse an interval like `30s`, `5m`, `2h`, or `1d`.
+fn parse_interval(s: &str) -> Result<Duration> {
+    let invalid = || format_err!("Invalid interval {:?}. Try something like `5m`.", s);
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let count: i64 = s[..split].parse().map_err(|_| invalid())?;
+    let interval = match &s[split..] {
+        "s" => Duration::seconds(count),
+        "m" => Duration::minutes(count),
+        "h" => Duration::hours(count),
+        "d" => Duration::days(count),
+        _ => return Err(invalid()),
+    };
+    if count == 0 {
+        return Err(invalid());
+    }
+    Ok(interval)
+}
+
+/// What happens when a scheduled service is due to run while its last run is still going.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Overlap {
+    /// Don't run it this time.
+    Skip,
+    /// Run it again as soon as the last run finishes.
+    Queue,
+}
+
+impl Default for Overlap {
+    fn default() -> Overlap {
+        Overlap::Skip
+    }
+}
+
+impl FromStr for Overlap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Overlap> {
+        match s {
+            "skip" => Ok(Overlap::Skip),
+            "queue" => Ok(Overlap::Queue),
+            _ => Err(format_err!("Unknown overlap policy: {:?}", &s)),
+        }
+    }
+}
+
+/// Keeps track of when a scheduled service runs next, and how many runs are waiting for the one
+/// before them to finish.
+#[derive(Debug)]
+pub struct Timer {
+    schedule: Schedule,
+    next: Option<DateTime<Local>>,
+    /// Runs that are due but haven't been started.
+    pub queued: u32,
+}
+
+impl Timer {
+    pub fn new(schedule: Schedule, now: DateTime<Local>) -> Timer {
+        let next = schedule.next_after(now);
+        Timer {
+            schedule,
+            next,
+            queued: 0,
+        }
+    }
+
+    /// Is a run due? If one is, the timer moves on to the next time after `now`, so runs that were
+    /// missed while nothing was checking only count once.
+    pub fn due(&mut self, now: DateTime<Local>) -> bool {
+        match self.next {
+            Some(next) if next <= now => {
+                self.next = self.schedule.next_after(now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// When the service runs next.
+    pub fn next_run(&self) -> Option<SystemTime> {
+        self.next.map(SystemTime::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Overlap, Schedule, Timer};
+    use chrono::prelude::*;
+    use chrono::Duration;
+    use spectral::prelude::*;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.ymd(2018, 10, 1).and_hms(hour, minute, second)
+    }
+
+    #[test]
+    fn test_cron_with_five_fields() {
+        let schedule: Schedule = "*/15 * * * *".parse().unwrap();
+        assert_that(&schedule.next_after(at(12, 7, 30))).is_equal_to(Some(at(12, 15, 0)));
+    }
+
+    #[test]
+    fn test_cron_with_seconds() {
+        let schedule: Schedule = "30 0 3 * * *".parse().unwrap();
+        assert_that(&schedule.next_after(at(2, 0, 0))).is_equal_to(Some(at(3, 0, 30)));
+    }
+
+    #[test]
+    fn test_every_interval() {
+        let schedule: Schedule = "@every 90s".parse().unwrap();
+        assert_that(&schedule.next_after(at(12, 0, 0))).is_equal_to(Some(at(12, 1, 30)));
+        let schedule: Schedule = "@every 2h".parse().unwrap();
+        assert_that(&schedule.next_after(at(12, 0, 0))).is_equal_to(Some(at(14, 0, 0)));
+    }
+
+    #[test]
+    fn test_rejects_invalid_schedules() {
+        for schedule in &["", "every minute", "* * *", "@every", "@every 0s", "@every 5y"] {
+            assert_that(&schedule.parse::<Schedule>()).is_err();
+        }
+    }
+
+    #[test]
+    fn test_parses_overlap() {
+        assert_that(&"skip".parse::<Overlap>().unwrap()).is_equal_to(Overlap::Skip);
+        assert_that(&"queue".parse::<Overlap>().unwrap()).is_equal_to(Overlap::Queue);
+        assert_that(&"wait".parse::<Overlap>()).is_err();
+    }
+
+    #[test]
+    fn test_timer_is_due_once_per_run() {
+        let start = at(12, 0, 0);
+        let mut timer = Timer::new("@every 1m".parse().unwrap(), start);
+        assert_that(&timer.due(start + Duration::seconds(30))).is_false();
+        assert_that(&timer.due(start + Duration::seconds(60))).is_true();
+        assert_that(&timer.due(start + Duration::seconds(61))).is_false();
+        // Missing several runs only makes one due.
+        assert_that(&timer.due(start + Duration::minutes(10))).is_true();
+        assert_that(&timer.due(start + Duration::minutes(10))).is_false();
+    }
+}
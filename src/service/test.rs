@@ -106,9 +106,11 @@ mod index_services {
 }
 
 mod read_procfile {
-    use service::Service;
+    use service::schedule::Overlap;
+    use service::{Service, ServiceKind};
     use spectral::assert_that;
     use spectral::prelude::*;
+    use std::path::PathBuf;
 
     #[test]
     fn test_skips_comments() {
@@ -143,6 +145,138 @@ mod read_procfile {
         assert_that(&names).contains(String::from("worker"));
     }
 
+    #[test]
+    fn test_applies_annotations_to_next_service() {
+        let input = b"#@ cwd=public\nweb: start web-server\nworker: start worker\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        let cwds = services
+            .into_iter()
+            .map(|s| s.cwd)
+            .collect::<Vec<Option<PathBuf>>>();
+        assert_that(&cwds).equals_iterator(&vec![Some(PathBuf::from("public")), None].iter());
+    }
+
+    #[test]
+    fn test_reads_quoted_annotations() {
+        let input = b"#@ cwd=\"my app\"\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].cwd).is_equal_to(Some(PathBuf::from("my app")));
+    }
+
+    #[test]
+    fn test_reads_watch_globs() {
+        let input = b"#@ watch=src/**/*.rs,Cargo.toml\n#@ watch=templates/*\nweb: cargo run\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].watch).equals_iterator(
+            &vec![
+                String::from("src/**/*.rs"),
+                String::from("Cargo.toml"),
+                String::from("templates/*"),
+            ].iter(),
+        );
+    }
+
+    #[test]
+    fn test_reads_kind() {
+        let input = b"#@ kind=oneshot\nmigrate: rake db:migrate\n\
+                      #@ kind=release\nseed: rake db:seed\nweb: rails server\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        let kinds = services.into_iter().map(|s| s.kind).collect::<Vec<_>>();
+        assert_that(&kinds).is_equal_to(vec![
+            ServiceKind::Oneshot,
+            ServiceKind::Oneshot,
+            ServiceKind::Service,
+        ]);
+    }
+
+    #[test]
+    fn test_errors_on_unknown_kind() {
+        let input = b"#@ kind=cron\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]);
+        assert_that(&services).is_err();
+    }
+
+    #[test]
+    fn test_reads_schedule() {
+        let input = b"#@ schedule=\"*/5 * * * *\" overlap=queue\nreport: send-report\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].schedule.as_ref().map(|s| s.to_string()))
+            .is_equal_to(Some(String::from("*/5 * * * *")));
+        assert_that(&services[0].overlap).is_equal_to(Overlap::Queue);
+    }
+
+    #[test]
+    fn test_errors_on_invalid_schedule() {
+        let input = b"#@ schedule=sometimes\nreport: send-report\n";
+        let services = Service::read_procfile(&input[..]);
+        assert_that(&services).is_err();
+    }
+
+    #[test]
+    fn test_reads_user_and_group() {
+        let input = b"#@ user=www-data group=www\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].user).is_equal_to(Some(String::from("www-data")));
+        assert_that(&services[0].group).is_equal_to(Some(String::from("www")));
+    }
+
+    #[test]
+    fn test_reads_limits() {
+        let input = b"#@ limit.nofile=1024 limit.core=0 nice=10\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].limits.open_files).is_equal_to(Some(1024));
+        assert_that(&services[0].limits.core_size).is_equal_to(Some(0));
+        assert_that(&services[0].limits.nice).is_equal_to(Some(10));
+    }
+
+    #[test]
+    fn test_reads_cgroup_settings() {
+        let input = b"#@ memory.max=512M cpu.max=\"50000 100000\"\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].cgroup.settings()).is_equal_to(vec![
+            ("memory.max", "512M"),
+            ("cpu.max", "50000 100000"),
+        ]);
+    }
+
+    #[test]
+    fn test_reads_hooks() {
+        let input = b"#@ hook.pre-start=\"./bin/setup --quick\" hook.timeout=5\n\
+                      web: start web-server\n";
+        let services = Service::read_procfile(&input[..]).expect("To read the services.");
+        assert_that(&services[0].hooks.pre_start)
+            .is_equal_to(Some(String::from("./bin/setup --quick")));
+        assert_that(&services[0].hooks.timeout).is_equal_to(Some(5));
+    }
+
+    #[test]
+    fn test_errors_on_unknown_hook() {
+        let input = b"#@ hook.pre-boot=./bin/setup\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]);
+        assert_that(&services).is_err();
+    }
+
+    #[test]
+    fn test_errors_on_invalid_limit() {
+        let input = b"#@ limit.nofile=lots\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]);
+        assert_that(&services).is_err();
+    }
+
+    #[test]
+    fn test_errors_on_unknown_annotation() {
+        let input = b"#@ colour=blue\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]);
+        assert_that(&services).is_err();
+    }
+
+    #[test]
+    fn test_errors_on_annotation_without_value() {
+        let input = b"#@ cwd\nweb: start web-server\n";
+        let services = Service::read_procfile(&input[..]);
+        assert_that(&services).is_err();
+    }
+
     #[test]
     fn test_reads_commands() {
         let input = b"web: start web-server\nworker: start worker\n";
@@ -156,6 +290,85 @@ mod read_procfile {
     }
 }
 
+mod try_from {
+    use duct::Expression;
+    use libc;
+    use service::Service;
+    use spectral::prelude::*;
+    use std::convert::TryFrom;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_runs_in_cwd() {
+        let mut service = Service::new("pwd", "pwd");
+        service.cwd = Some(PathBuf::from("/tmp"));
+        let output = Expression::try_from(service).unwrap().read();
+        assert_that(&output).is_ok().is_equal_to(String::from("/tmp"));
+    }
+
+    #[test]
+    fn test_sets_env() {
+        // The single quotes leave the variable for the shell to read from its environment.
+        let mut service = Service::new("env", "sh -c 'echo $GREETING'");
+        service
+            .env
+            .insert(String::from("GREETING"), String::from("hi"));
+        let output = Expression::try_from(service).unwrap().read();
+        assert_that(&output).is_ok().is_equal_to(String::from("hi"));
+    }
+
+    #[test]
+    fn test_expands_env_in_arguments() {
+        let mut service = Service::new("port", "echo --port=$PORT");
+        service.env.insert(String::from("PORT"), String::from("5100"));
+        let output = Expression::try_from(service).unwrap().read();
+        assert_that(&output)
+            .is_ok()
+            .is_equal_to(String::from("--port=5100"));
+    }
+
+    #[test]
+    fn test_leaves_quoted_and_unknown_variables() {
+        let mut service = Service::new(
+            "quoted",
+            "echo '$PORT' \\$PORT \"$STRAW_BOSS_NO_SUCH_VAR\" \"$PORT\"",
+        );
+        service.env.insert(String::from("PORT"), String::from("5100"));
+        let output = Expression::try_from(service).unwrap().read();
+        assert_that(&output)
+            .is_ok()
+            .is_equal_to(String::from("$PORT $PORT $STRAW_BOSS_NO_SUCH_VAR 5100"));
+    }
+
+    #[test]
+    fn test_runs_as_user() {
+        // Only root can switch users.
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let mut service = Service::new("id", "id -u");
+        service.user = Some(String::from("nobody"));
+        let output = Expression::try_from(service).unwrap().read();
+        assert_that(&output).is_ok().is_equal_to(String::from("65534"));
+    }
+
+    #[test]
+    fn test_errors_on_unknown_user() {
+        let mut service = Service::new("id", "id -u");
+        service.user = Some(String::from("straw-boss-no-such-user"));
+        assert_that(&Expression::try_from(service)).is_err();
+    }
+
+    #[test]
+    fn test_applies_limits() {
+        let mut service = Service::new("limits", "sh -c \"ulimit -n; nice\"");
+        service.limits.set("nofile", "64").unwrap();
+        service.limits.set_nice("5").unwrap();
+        let output = Expression::try_from(service).unwrap().read();
+        assert_that(&output).is_ok().is_equal_to(String::from("64\n5"));
+    }
+}
+
 mod split_piped_commands {
     use super::super::split_piped_commands;
     use spectral::prelude::*;
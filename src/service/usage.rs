@@ -0,0 +1,100 @@
+use libc;
+use std::fs;
+use std::time::Duration;
+
+/// What a service's processes are using. This comes from the service's cgroup when it has one,
+/// and from `/proc` for the processes in its pipeline when it doesn't.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Usage {
+    /// The memory in use, in bytes.
+    pub memory: Option<u64>,
+    /// The CPU time used so far.
+    pub cpu: Option<Duration>,
+}
+
+impl Usage {
+    /// Add up the usage of the processes with these IDs. This is `None` if none of them could be
+    /// read, which is always the case where there's no `/proc`.
+    pub fn of_processes(pids: &[u32]) -> Option<Usage> {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if ticks <= 0 || page_size <= 0 {
+            return None;
+        }
+        let stats = pids
+            .iter()
+            .filter_map(|pid| fs::read_to_string(format!("/proc/{}/stat", pid)).ok())
+            .filter_map(|stat| parse_stat(&stat))
+            .collect::<Vec<_>>();
+        if stats.is_empty() {
+            return None;
+        }
+        let cpu_ticks = stats.iter().map(|(cpu, _)| cpu).sum::<u64>();
+        let pages = stats.iter().map(|(_, pages)| pages).sum::<u64>();
+        Some(Usage {
+            memory: Some(pages * page_size as u64),
+            cpu: Some(Duration::from_micros(cpu_ticks * 1_000_000 / ticks as u64)),
+        })
+    }
+
+    /// Describe the usage for people, like `12.5M memory, 1.25s CPU`.
+    pub fn describe(&self) -> String {
+        let memory = self.memory.map(|memory| format!("{} memory", show_size(memory)));
+        let cpu = self.cpu.map(|cpu| format!("{:.2}s CPU", seconds(cpu)));
+        memory.into_iter().chain(cpu).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// Show a number of bytes in the largest unit that fits, like `12.5M`.
+pub fn show_size(bytes: u64) -> String {
+    let units = [(1 << 30, "G"), (1 << 20, "M"), (1 << 10, "K")];
+    units
+        .iter()
+        .find(|(size, _)| bytes >= *size)
+        .map(|(size, unit)| format!("{:.1}{}", bytes as f64 / *size as f64, unit))
+        .unwrap_or_else(|| format!("{}B", bytes))
+}
+
+/// A duration as fractional seconds.
+pub fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_micros()) / 1_000_000.0
+}
+
+/// Read the CPU ticks (user and system) and resident pages out of `/proc/PID/stat`. The command
+/// name is in parentheses and can have spaces, so the fields are counted from after it.
+fn parse_stat(stat: &str) -> Option<(u64, u64)> {
+    let fields = stat[stat.rfind(')')? + 1..].split_whitespace().collect::<Vec<_>>();
+    let user = fields.get(11)?.parse::<u64>().ok()?;
+    let system = fields.get(12)?.parse::<u64>().ok()?;
+    let pages = fields.get(21)?.parse::<u64>().ok()?;
+    Some((user + system, pages))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_stat, show_size, Usage};
+    use spectral::prelude::*;
+    use std::process;
+
+    #[test]
+    fn test_parses_stat() {
+        let stat = "4242 (a (weird) name) S 1 4242 4242 0 -1 4194560 1165 0 0 0 \
+                    150 25 0 0 20 0 1 0 2718 12345678 3200 18446744073709551615";
+        assert_that(&parse_stat(stat)).is_equal_to(Some((175, 3200)));
+        assert_that(&parse_stat("4242 (short)")).is_none();
+    }
+
+    #[test]
+    fn test_reads_own_usage() {
+        let usage = Usage::of_processes(&[process::id()]).unwrap();
+        assert_that(&usage.memory).is_some().is_greater_than(0);
+        assert_that(&Usage::of_processes(&[])).is_none();
+    }
+
+    #[test]
+    fn test_shows_sizes() {
+        assert_that(&show_size(512).as_str()).is_equal_to("512B");
+        assert_that(&show_size(1536).as_str()).is_equal_to("1.5K");
+        assert_that(&show_size(3 << 30).as_str()).is_equal_to("3.0G");
+    }
+}
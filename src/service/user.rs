@@ -0,0 +1,235 @@
+use libc;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem;
+use std::ptr;
+use Result;
+
+/// The most room to give the `getpw*_r` and `getgr*_r` functions for an entry.
+const MAX_ENTRY_BUFFER: usize = 1 << 20;
+
+/// Who a service's processes run as. This is looked up before the service is spawned, so that
+/// switching to it in the child only takes system calls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    /// The user to switch to. Without one, the processes keep straw boss's user.
+    pub uid: Option<libc::uid_t>,
+    /// The primary group.
+    pub gid: libc::gid_t,
+    /// The supplementary groups.
+    pub groups: Vec<libc::gid_t>,
+}
+
+/// A user from the password database.
+struct User {
+    name: Option<CString>,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+}
+
+impl Credentials {
+    /// Look up a service's user and group. Either can be a name or an ID. Without a group, it's
+    /// the user's primary group, and the supplementary groups are the user's groups. A numeric
+    /// user that isn't in the password database, like containers often use, gets the group with
+    /// the same ID.
+    ///
+    /// # Returns
+    ///
+    /// `None` if there's neither a user nor a group to switch to, and an error if either doesn't
+    /// exist.
+    pub fn lookup(user: Option<&str>, group: Option<&str>) -> Result<Option<Credentials>> {
+        let user = match user {
+            Some(user) => Some(find_user(user)?),
+            None => None,
+        };
+        let gid = match group {
+            Some(group) => Some(find_group(group)?),
+            None => None,
+        };
+        let credentials = match (user, gid) {
+            (None, None) => return Ok(None),
+            (None, Some(gid)) => Credentials {
+                uid: None,
+                gid,
+                groups: vec![gid],
+            },
+            (Some(user), gid) => {
+                let gid = gid.unwrap_or(user.gid);
+                let groups = match user.name {
+                    Some(ref name) => group_list(name, gid)?,
+                    None => vec![gid],
+                };
+                Credentials {
+                    uid: Some(user.uid),
+                    gid,
+                    groups,
+                }
+            }
+        };
+        Ok(Some(credentials))
+    }
+
+    /// Switch the current process to the user and groups. This is meant to run in a forked child,
+    /// right before it runs the command, so it only makes system calls. The groups go first,
+    /// because once the user's switched, changing them isn't allowed.
+    pub fn apply(&self) -> io::Result<()> {
+        unsafe {
+            if libc::setgroups(self.groups.len() as _, self.groups.as_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::setgid(self.gid) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if let Some(uid) = self.uid {
+                if libc::setuid(uid) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_user(user: &str) -> Result<User> {
+    let read = |passwd: &libc::passwd| unsafe {
+        User {
+            name: Some(CStr::from_ptr(passwd.pw_name).to_owned()),
+            uid: passwd.pw_uid,
+            gid: passwd.pw_gid,
+        }
+    };
+    match user.parse::<libc::uid_t>() {
+        Ok(uid) => {
+            let found = get_entry(
+                |passwd, buffer, len, result| unsafe {
+                    libc::getpwuid_r(uid, passwd, buffer, len, result)
+                },
+                read,
+            )?;
+            Ok(found.unwrap_or(User {
+                name: None,
+                uid,
+                gid: uid as libc::gid_t,
+            }))
+        }
+        Err(_) => {
+            let name = CString::new(user).map_err(|_| format_err!("Invalid user: {:?}", user))?;
+            get_entry(
+                |passwd, buffer, len, result| unsafe {
+                    libc::getpwnam_r(name.as_ptr(), passwd, buffer, len, result)
+                },
+                read,
+            )?.ok_or_else(|| format_err!("There's no user named {:?}.", user))
+        }
+    }
+}
+
+fn find_group(group: &str) -> Result<libc::gid_t> {
+    if let Ok(gid) = group.parse() {
+        return Ok(gid);
+    }
+    let name = CString::new(group).map_err(|_| format_err!("Invalid group: {:?}", group))?;
+    get_entry(
+        |entry, buffer, len, result| unsafe {
+            libc::getgrnam_r(name.as_ptr(), entry, buffer, len, result)
+        },
+        |entry: &libc::group| entry.gr_gid,
+    )?.ok_or_else(|| format_err!("There's no group named {:?}.", group))
+}
+
+/// The groups that a user's in, starting with `gid`.
+fn group_list(user: &CStr, gid: libc::gid_t) -> Result<Vec<libc::gid_t>> {
+    let mut count: libc::c_int = 32;
+    loop {
+        let mut groups: Vec<libc::gid_t> = vec![0; count as usize];
+        let room = count;
+        let found = unsafe {
+            libc::getgrouplist(
+                user.as_ptr(),
+                gid as _,
+                groups.as_mut_ptr() as *mut _,
+                &mut count,
+            )
+        };
+        if found >= 0 {
+            groups.truncate(count as usize);
+            return Ok(groups);
+        }
+        if count as usize > MAX_ENTRY_BUFFER {
+            return Err(format_err!("Unable to list the groups for {:?}.", user));
+        }
+        // This says how much room it needs, but not everywhere.
+        if count <= room {
+            count = room * 2;
+        }
+    }
+}
+
+/// Call one of the reentrant `getpw*_r` or `getgr*_r` functions, with a buffer that grows until
+/// the entry fits. `read` takes what's needed from the entry, which points into the buffer.
+fn get_entry<E, T, F, R>(mut get: F, read: R) -> Result<Option<T>>
+where
+    F: FnMut(*mut E, *mut libc::c_char, libc::size_t, *mut *mut E) -> libc::c_int,
+    R: Fn(&E) -> T,
+{
+    let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        let mut entry: E = unsafe { mem::zeroed() };
+        let mut result = ptr::null_mut();
+        let code = get(&mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result);
+        if code == libc::ERANGE && buffer.len() < MAX_ENTRY_BUFFER {
+            let len = buffer.len() * 2;
+            buffer.resize(len, 0);
+            continue;
+        }
+        if code != 0 {
+            return Err(format_err!("{}", io::Error::from_raw_os_error(code)));
+        }
+        return Ok(if result.is_null() {
+            None
+        } else {
+            Some(read(&entry))
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Credentials;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_looks_up_user() {
+        let credentials = Credentials::lookup(Some("root"), None).unwrap().unwrap();
+        assert_that(&credentials.uid).is_equal_to(Some(0));
+        assert_that(&credentials.gid).is_equal_to(0);
+        assert_that(&credentials.groups).contains(0);
+    }
+
+    #[test]
+    fn test_looks_up_group() {
+        let credentials = Credentials::lookup(Some("0"), Some("5000")).unwrap().unwrap();
+        assert_that(&credentials.uid).is_equal_to(Some(0));
+        assert_that(&credentials.gid).is_equal_to(5000);
+        let credentials = Credentials::lookup(None, Some("root")).unwrap().unwrap();
+        assert_that(&credentials.uid).is_none();
+        assert_that(&credentials.groups).is_equal_to(vec![0]);
+    }
+
+    #[test]
+    fn test_numeric_user_needs_no_entry() {
+        let credentials = Credentials::lookup(Some("54321"), None).unwrap().unwrap();
+        assert_that(&credentials).is_equal_to(Credentials {
+            uid: Some(54321),
+            gid: 54321,
+            groups: vec![54321],
+        });
+    }
+
+    #[test]
+    fn test_errors_on_unknown_names() {
+        assert_that(&Credentials::lookup(None, None)).is_ok().is_none();
+        assert_that(&Credentials::lookup(Some("straw-boss-no-such-user"), None)).is_err();
+        assert_that(&Credentials::lookup(None, Some("straw-boss-no-such-group"))).is_err();
+    }
+}
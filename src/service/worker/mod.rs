@@ -1,8 +1,17 @@
+use libc;
+use service::cgroup::Slice;
+use service::log::Log;
+use service::exit::ExitInfo;
 use service::messages::{TaskMessage, TaskResponse};
-use service::{run, Service};
-use std::process::Output;
+use service::schedule::RUN_HISTORY;
+use service::usage::Usage;
+use service::{run, Service, ServiceKind};
+use std::collections::VecDeque;
+use std::process::{ExitStatus, Output};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, SystemTime};
 use Result;
 
 pub trait Worker {
@@ -10,7 +19,85 @@ pub trait Worker {
     fn thread_id(&self) -> Option<thread::ThreadId>;
     fn join(&mut self) -> Result<Output>;
     fn kill(&mut self) -> Result<()>;
+    fn signal(&mut self, signal: i32) -> Result<()>;
+    fn pause(&mut self) -> Result<()>;
+    fn resume(&mut self) -> Result<()>;
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>>;
     fn is_running(&self) -> bool;
+    fn state(&self) -> WorkerState;
+}
+
+/// What a worker's task is doing.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum WorkerState {
+    /// The task is running.
+    Running,
+    /// The task is stopped with `SIGSTOP` and waiting to be resumed.
+    Paused,
+    /// The task isn't running.
+    Stopped,
+    /// The task couldn't be started.
+    Failed,
+    /// The task was a one-shot, and it finished successfully.
+    Completed,
+    /// The task runs on a schedule, and it's waiting for its next run.
+    Scheduled,
+}
+
+/// A service and the state of its worker. This is what the server reports to clients.
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct WorkerStatus {
+    pub service: Service,
+    pub state: WorkerState,
+    /// Why the task couldn't be started, if it failed.
+    pub error: Option<String>,
+    /// How the task finished, if it has.
+    pub exit: Option<ExitInfo>,
+    /// How the last runs of a scheduled task went, oldest first.
+    pub runs: Vec<ExitInfo>,
+    /// When a scheduled task runs next.
+    pub next_run: Option<SystemTime>,
+    /// The ID of the first process in the task's pipeline, while it's running.
+    pub pid: Option<u32>,
+    /// What the task's processes are using, while it's running.
+    pub usage: Option<Usage>,
+    /// When the task was last started.
+    pub started_at: Option<SystemTime>,
+    /// How many times the task's been started again since its first start.
+    pub restarts: u32,
+}
+
+impl WorkerStatus {
+    pub fn new(service: Service, state: WorkerState) -> WorkerStatus {
+        WorkerStatus {
+            service,
+            state,
+            error: None,
+            exit: None,
+            runs: Vec::new(),
+            next_run: None,
+            pid: None,
+            usage: None,
+            started_at: None,
+            restarts: 0,
+        }
+    }
+
+    /// The status of a worker whose task couldn't be started.
+    pub fn failed(service: Service, error: &str) -> WorkerStatus {
+        WorkerStatus {
+            service,
+            state: WorkerState::Failed,
+            error: Some(String::from(error)),
+            exit: None,
+            runs: Vec::new(),
+            next_run: None,
+            pid: None,
+            usage: None,
+            started_at: None,
+            restarts: 0,
+        }
+    }
 }
 
 /// Information about a running service task.
@@ -37,8 +124,38 @@ impl RunningWorker {
             )
         })?;
 
-        let TaskResponse::Joined(output) = response;
-        Ok(output)
+        match response {
+            TaskResponse::Joined(output) => Ok(output),
+            response => Err(format_err!(
+                "Unexpected response from {}: {:?}",
+                &service_name,
+                &response
+            )),
+        }
+    }
+
+    /// Message to check on the task without waiting for it.
+    fn try_wait(&self, service_name: &str) -> Result<Option<ExitStatus>> {
+        let RunningWorker(_, ref tx, ref rx) = *self;
+        tx.send(TaskMessage::TryWait).map_err(|err| {
+            format_err!("Unable to send message to {}: {:?}", &service_name, &err)
+        })?;
+        let response = rx.recv().map_err(|err| {
+            format_err!(
+                "Unable to receive message from {}: {:?}",
+                &service_name,
+                &err
+            )
+        })?;
+
+        match response {
+            TaskResponse::Waited(status) => Ok(status),
+            response => Err(format_err!(
+                "Unexpected response from {}: {:?}",
+                &service_name,
+                &response
+            )),
+        }
     }
 }
 
@@ -47,32 +164,180 @@ impl RunningWorker {
 pub struct ServiceWorker {
     service: Service,
     worker: Option<RunningWorker>,
+    paused: bool,
+    error: Option<String>,
+    exit: Arc<Mutex<Option<ExitInfo>>>,
+    /// The runs before this one, for scheduled tasks.
+    runs: VecDeque<ExitInfo>,
+    /// The cgroup slice that the task runs in, if there is one.
+    slice: Option<Slice>,
+    /// What the task's written, across all of its runs.
+    log: Log,
+    /// The IDs of the processes in the task's pipeline, from when it was last started.
+    pids: Vec<u32>,
+    started_at: Option<SystemTime>,
+    restarts: u32,
 }
 
 impl ServiceWorker {
     /// Create a new `ServiceWorker` from a `Service`. This takes ownership of the `Service`.
     pub fn new(service: Service) -> ServiceWorker {
+        ServiceWorker::with_slice(service, None)
+    }
+
+    /// Create a new `ServiceWorker` whose task runs in a cgroup of its own in `slice`.
+    pub fn with_slice(service: Service, slice: Option<Slice>) -> ServiceWorker {
         ServiceWorker {
             service,
             worker: None,
+            paused: false,
+            error: None,
+            exit: Arc::new(Mutex::new(None)),
+            runs: VecDeque::new(),
+            slice,
+            log: Log::new(),
+            pids: Vec::new(),
+            started_at: None,
+            restarts: 0,
         }
     }
 
     pub fn service(&self) -> &Service {
         &self.service
     }
+
+    /// Why the task couldn't be started the last time it was tried.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_ref().map(|error| error.as_str())
+    }
+
+    /// How the task finished, if it has. This is recorded in the background, so it's there even
+    /// if nothing waits on the task.
+    pub fn exit_info(&self) -> Option<ExitInfo> {
+        self.exit.lock().ok().and_then(|exit| exit.clone())
+    }
+
+    /// When the task was last started.
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.started_at
+    }
+
+    /// The ID of the first process in the task's pipeline, from when it was last started.
+    pub fn pid(&self) -> Option<u32> {
+        self.pids.first().cloned()
+    }
+
+    /// How the last `RUN_HISTORY` runs of a scheduled task went, oldest first. This includes the
+    /// latest run, once it's finished.
+    pub fn runs(&self) -> Vec<ExitInfo> {
+        let mut runs = self.runs.iter().cloned().collect::<Vec<_>>();
+        runs.extend(self.exit_info());
+        let skip = runs.len().saturating_sub(RUN_HISTORY);
+        runs.split_off(skip)
+    }
+
+    /// The status to report to clients. The server fills in when a scheduled task runs next.
+    pub fn status(&self) -> WorkerStatus {
+        let state = self.state();
+        let running = state == WorkerState::Running || state == WorkerState::Paused;
+        let usage = match self.slice {
+            _ if !running => None,
+            Some(ref slice) => slice.usage(&self.service.name),
+            None => Usage::of_processes(&self.pids),
+        };
+        WorkerStatus {
+            service: self.service.clone(),
+            state,
+            error: self.error.clone(),
+            exit: self.exit_info(),
+            runs: self.runs(),
+            next_run: None,
+            pid: self.pids.first().cloned().filter(|_| running),
+            usage,
+            started_at: self.started_at,
+            restarts: self.restarts,
+        }
+    }
+
+    /// The last `count` lines that the task wrote to stdout or stderr, oldest first.
+    pub fn log(&self, count: usize) -> Vec<String> {
+        self.log.tail(count)
+    }
+
+    /// Kill the task, wait for its thread to finish, and start it again.
+    pub fn restart(&mut self) -> Result<()> {
+        self.stop()?;
+        self.start()
+    }
+
+    /// Ask the task to stop with a signal, once its `pre-stop` hook has run. If it hasn't exited
+    /// `grace` after the signal, it's killed. This doesn't wait for any of it.
+    pub fn terminate(&mut self, signal: i32, grace: Duration) -> Result<()> {
+        if let Some(RunningWorker(_, ref tx, _)) = self.worker {
+            tx.send(TaskMessage::Stop(signal, grace)).map_err(|err| {
+                format_err!(
+                    "Error sending signal {} to {}: {:?}",
+                    signal,
+                    &self.service.name,
+                    &err
+                )
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Let go of the task's thread without waiting for it. A task that's still running is killed,
+    /// and the thread finishes in the background once the task's hooks have. How the task exited
+    /// stays recorded until it's started again.
+    pub fn release(&mut self) {
+        self.worker.take();
+    }
+
+    /// Kill the task and wait for its thread to finish, so that it's really gone. Its exit stays
+    /// recorded until it's started again.
+    pub fn stop(&mut self) -> Result<()> {
+        // A task that's already exited only needs its thread collected. Its processes are gone,
+        // and their IDs could belong to something else by now.
+        if self.worker.is_some() && self.exit_info().is_some() {
+            return self.join().and(Ok(()));
+        }
+        if let Some(RunningWorker(join_handle, tx, _)) = self.worker.take() {
+            tx.send(TaskMessage::Kill).map_err(|err| {
+                format_err!("Error sending KILL to {}: {:?}", &self.service.name, &err)
+            })?;
+            join_handle
+                .join()
+                .map_err(|err| format_err!("Error stopping {}: {:?}", &self.service.name, &err))??;
+        }
+        Ok(())
+    }
 }
 
 impl Worker for ServiceWorker {
-    /// Start the service executing on a separate thread.
+    /// Start the service executing on a separate thread. This waits until the task's been
+    /// spawned. If it can't be, the error's kept to report, and the worker isn't running.
     fn start(&mut self) -> Result<()> {
         let service_run = self.service.clone();
         let service_name = self.service.name.clone();
         let (manager_tx, manager_rx) = channel();
         let (worker_tx, worker_rx) = channel();
+        if self.service.schedule.is_some() {
+            if let Some(exit) = self.exit_info() {
+                if self.runs.len() == RUN_HISTORY {
+                    self.runs.pop_front();
+                }
+                self.runs.push_back(exit);
+            }
+        }
+        // Each run gets its own exit, so a task that's still winding down can't overwrite it.
+        self.exit = Arc::new(Mutex::new(None));
+        let exit = self.exit.clone();
+        let slice = self.slice.clone();
+        let log = self.log.clone();
 
         let join_handle = thread::Builder::new()
-            .spawn(|| run(service_run, manager_rx, worker_tx))
+            .spawn(|| run(service_run, slice, log, manager_rx, worker_tx, exit))
             .map_err(|err| {
                 format_err!(
                     "Error spawning thread for service {}: {:?}",
@@ -81,8 +346,31 @@ impl Worker for ServiceWorker {
                 )
             })?;
 
-        self.worker = Some(RunningWorker(join_handle, manager_tx, worker_rx));
+        let response = worker_rx.recv().map_err(|err| {
+            format_err!("Unable to receive message from {}: {:?}", &service_name, &err)
+        });
+        let failure = match response {
+            Ok(TaskResponse::Started(pids)) => {
+                self.pids = pids;
+                None
+            }
+            Ok(TaskResponse::Failed(error)) => Some(error),
+            Ok(response) => Some(format!("Unexpected response: {:?}", &response)),
+            Err(err) => Some(err.to_string()),
+        };
+        self.paused = false;
+        if let Some(error) = failure {
+            let _ = join_handle.join();
+            self.error = Some(error.clone());
+            return Err(format_err!("{}: {}", &service_name, &error));
+        }
 
+        self.worker = Some(RunningWorker(join_handle, manager_tx, worker_rx));
+        self.error = None;
+        if self.started_at.is_some() {
+            self.restarts += 1;
+        }
+        self.started_at = Some(SystemTime::now());
         Ok(())
     }
 
@@ -115,15 +403,80 @@ impl Worker for ServiceWorker {
         }
     }
 
-    /// Is this task running?
+    /// Send a signal to the task's processes.
+    fn signal(&mut self, signal: i32) -> Result<()> {
+        if let Some(RunningWorker(_, ref tx, _)) = self.worker {
+            tx.send(TaskMessage::Signal(signal)).map_err(|err| {
+                format_err!(
+                    "Error sending signal {} to {}: {:?}",
+                    signal,
+                    &self.service.name,
+                    &err
+                )
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Freeze the task with `SIGSTOP`. It keeps all of its state until it's resumed.
+    fn pause(&mut self) -> Result<()> {
+        if self.worker.is_some() {
+            self.signal(libc::SIGSTOP)?;
+            self.paused = true;
+        }
+        Ok(())
+    }
+
+    /// Let a paused task carry on with `SIGCONT`.
+    fn resume(&mut self) -> Result<()> {
+        if self.worker.is_some() {
+            self.signal(libc::SIGCONT)?;
+        }
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Check whether the task has finished. This returns its `ExitStatus` if it has, and `None`
+    /// if it's still going or was never started.
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        match self.worker {
+            Some(ref worker) => worker.try_wait(&self.service.name),
+            None => Ok(None),
+        }
+    }
+
+    /// Is this task running? One whose exit has been recorded isn't, even before its thread's
+    /// been collected.
     fn is_running(&self) -> bool {
-        self.worker.is_some()
+        self.worker.is_some() && self.exit_info().is_none()
+    }
+
+    /// Is this task running, paused, stopped, failed, completed, or scheduled? A paused task still
+    /// counts as running, and one that's exited counts as stopped, unless it's a one-shot that
+    /// succeeded or it runs on a schedule.
+    fn state(&self) -> WorkerState {
+        let exit = self.exit_info();
+        let succeeded = exit.as_ref().map_or(false, |exit| exit.code == Some(0));
+        let scheduled = self.service.schedule.is_some();
+        match self.worker {
+            _ if succeeded && self.service.kind == ServiceKind::Oneshot => WorkerState::Completed,
+            Some(_) if exit.is_some() && scheduled => WorkerState::Scheduled,
+            Some(_) if exit.is_some() => WorkerState::Stopped,
+            Some(_) if self.paused => WorkerState::Paused,
+            Some(_) => WorkerState::Running,
+            None if self.error.is_some() => WorkerState::Failed,
+            None if scheduled => WorkerState::Scheduled,
+            None => WorkerState::Stopped,
+        }
     }
 }
 
 impl Drop for ServiceWorker {
+    /// Kill the task. This waits for it, because the server dropping its workers is often the last
+    /// thing before the process exits, and that would take the worker's thread with it.
     fn drop(&mut self) {
-        let _ = self.kill();
+        let _ = self.stop();
     }
 }
 
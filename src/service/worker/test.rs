@@ -13,9 +13,14 @@ fn setup(name: &str, command: &str) -> ServiceWorker {
 mod start {
     use super::setup;
     use reqwest;
-    use service::worker::Worker;
+    use service::cgroup::Slice;
+    use service::worker::{ServiceWorker, Worker, WorkerState};
+    use service::Service;
     use spectral::assert_that;
     use spectral::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::process;
     use std::thread;
     use std::time;
 
@@ -50,6 +55,66 @@ mod start {
         assert_that(&response).is_ok();
         assert_that(&response.unwrap().status().is_success()).is_true();
     }
+
+    #[test]
+    fn test_reports_spawn_failure() {
+        let service = Service::new("missing", "straw-boss-no-such-command --flag");
+        let mut worker = ServiceWorker::new(service);
+
+        let result = worker.start();
+        assert_that(&result).is_err();
+        assert_that(&worker.is_running()).is_false();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Failed);
+        assert_that(&worker.error())
+            .is_some()
+            .starts_with("Unable to run `straw-boss-no-such-command --flag`");
+    }
+
+    #[test]
+    fn test_runs_in_cgroup() {
+        // This only looks like a cgroup, so the processes joining it just write to a file.
+        let dir = env::temp_dir().join(format!("straw-boss.worker-cgroup.{}", process::id()));
+        let cgroup = dir.join("services/limited");
+        fs::create_dir_all(&cgroup).unwrap();
+        fs::write(dir.join("cgroup.controllers"), "").unwrap();
+        fs::write(dir.join("cgroup.procs"), "").unwrap();
+        fs::write(cgroup.join("cgroup.procs"), "").unwrap();
+        let slice = Slice::create(&dir).unwrap();
+
+        let mut service = Service::new("limited", "true");
+        service.cgroup.set("pids.max", "10").unwrap();
+        let mut worker = ServiceWorker::with_slice(service, Some(slice));
+        worker.start().unwrap();
+        worker.join().unwrap();
+
+        let procs = fs::read_to_string(cgroup.join("cgroup.procs")).unwrap();
+        let pids_max = fs::read_to_string(cgroup.join("pids.max")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_that(&procs.as_str()).is_equal_to("0");
+        assert_that(&pids_max.as_str()).is_equal_to("10");
+    }
+
+    #[test]
+    fn test_runs_without_cgroup_it_cannot_create() {
+        let dir = env::temp_dir().join(format!("straw-boss.worker-no-cgroup.{}", process::id()));
+        let cgroup = dir.join("services/limited");
+        // A directory where the setting should go can't be written to.
+        fs::create_dir_all(cgroup.join("pids.max")).unwrap();
+        fs::write(dir.join("cgroup.controllers"), "").unwrap();
+        fs::write(dir.join("cgroup.procs"), "").unwrap();
+        let slice = Slice::create(&dir).unwrap();
+
+        let mut service = Service::new("limited", "true");
+        service.cgroup.set("pids.max", "10").unwrap();
+        let mut worker = ServiceWorker::with_slice(service, Some(slice));
+        let started = worker.start();
+        let output = worker.join();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_that(&started).is_ok();
+        assert_that(&output).is_ok();
+        assert_that(&worker.state()).is_not_equal_to(WorkerState::Failed);
+    }
 }
 
 mod join {
@@ -57,6 +122,7 @@ mod join {
     use service::worker::Worker;
     use spectral::assert_that;
     use spectral::prelude::*;
+    use std::thread;
     use std::time::{Duration, Instant};
 
     #[test]
@@ -81,6 +147,16 @@ mod join {
 
         assert_that(&worker.is_running()).is_false();
     }
+
+    #[test]
+    fn test_not_running_once_exited() {
+        let mut worker = setup("test_not_running_once_exited", "true");
+        thread::sleep(Duration::from_millis(500));
+
+        assert_that(&worker.is_running()).is_false();
+        assert_that(&worker.thread_id()).is_some();
+        assert_that(&worker.kill()).is_ok();
+    }
 }
 
 mod kill {
@@ -104,6 +180,284 @@ mod kill {
     }
 }
 
+mod restart {
+    use super::setup;
+    use service::worker::Worker;
+    use spectral::assert_that;
+    use spectral::prelude::*;
+
+    #[test]
+    fn test_starts_a_new_thread() {
+        let mut worker = setup("test_starts_a_new_thread", "sleep 3");
+        let before = worker.thread_id();
+
+        assert_that(&worker.restart()).is_ok();
+        assert_that(&worker.is_running()).is_true();
+        assert_that(&worker.thread_id()).is_not_equal_to(&before);
+    }
+
+    #[test]
+    fn test_counts_restarts() {
+        let mut worker = setup("test_counts_restarts", "sleep 3");
+        let before = worker.status();
+        assert_that(&before.restarts).is_equal_to(0);
+        assert_that(&before.pid).is_some();
+        assert_that(&before.started_at).is_some();
+
+        worker.restart().unwrap();
+        let after = worker.status();
+        assert_that(&after.restarts).is_equal_to(1);
+        assert_that(&after.pid).is_some().is_not_equal_to(before.pid.unwrap());
+    }
+}
+
+mod signal {
+    use super::setup;
+    use service::worker::Worker;
+    use signal_hook::consts::signal::SIGUSR1;
+    use spectral::assert_that;
+    use spectral::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sends_signal_to_child_task() {
+        let mut worker = setup(
+            "test_sends_signal_to_child_task",
+            "sh -c 'trap \"exit 3\" USR1; while true; do sleep 0.1; done'",
+        );
+        thread::sleep(Duration::from_millis(200));
+
+        assert_that(&worker.signal(SIGUSR1)).is_ok();
+        thread::sleep(Duration::from_millis(500));
+
+        let status = worker.try_wait().unwrap();
+        assert_that(&status.and_then(|s| s.code())).is_equal_to(Some(3));
+    }
+
+    #[test]
+    fn test_sends_signal_to_process_group() {
+        let tmpfile = env::temp_dir().join(format!("straw-boss.process-group.{}", process::id()));
+        if tmpfile.exists() {
+            fs::remove_file(&tmpfile).unwrap();
+        }
+        let mut worker = setup(
+            "test_sends_signal_to_process_group",
+            &format!("sh fixtures/signal/grandchild.sh {}", tmpfile.to_string_lossy()),
+        );
+        thread::sleep(Duration::from_millis(200));
+
+        assert_that(&worker.signal(SIGUSR1)).is_ok();
+        thread::sleep(Duration::from_millis(500));
+
+        assert_that(&fs::read_to_string(&tmpfile).unwrap()).is_equal_to(String::from("USR1\n"));
+        fs::remove_file(&tmpfile).unwrap();
+    }
+}
+
+mod pause {
+    use super::setup;
+    use service::worker::{Worker, WorkerState};
+    use spectral::assert_that;
+    use spectral::prelude::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_paused_task_is_not_finished() {
+        let mut worker = setup("test_paused_task_is_not_finished", "sleep 3");
+
+        assert_that(&worker.pause()).is_ok();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Paused);
+        thread::sleep(Duration::from_millis(200));
+        assert_that(&worker.try_wait()).is_ok().is_none();
+
+        assert_that(&worker.resume()).is_ok();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Running);
+    }
+
+    #[test]
+    fn test_killed_task_is_stopped() {
+        let mut worker = setup("test_killed_task_is_stopped", "sleep 3");
+        worker.pause().unwrap();
+        worker.kill().unwrap();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Stopped);
+    }
+}
+
+mod try_wait {
+    use super::setup;
+    use service::worker::Worker;
+    use spectral::assert_that;
+    use spectral::prelude::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_returns_none_while_running() {
+        let mut worker = setup("test_returns_none_while_running", "sleep 3");
+        assert_that(&worker.try_wait()).is_ok().is_none();
+    }
+
+    #[test]
+    fn test_returns_status_when_finished() {
+        let mut worker = setup("test_returns_status_when_finished", "true");
+        thread::sleep(Duration::from_millis(500));
+        let status = worker.try_wait().unwrap();
+        assert_that(&status.map(|s| s.success())).is_equal_to(Some(true));
+    }
+}
+
+mod exit_info {
+    use super::setup;
+    use service::worker::{Worker, WorkerState};
+    use spectral::assert_that;
+    use spectral::prelude::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_none_while_running() {
+        let worker = setup("test_none_while_running", "sleep 3");
+        thread::sleep(Duration::from_millis(300));
+        assert_that(&worker.exit_info()).is_none();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Running);
+    }
+
+    #[test]
+    fn test_records_exit_in_background() {
+        let worker = setup(
+            "test_records_exit_in_background",
+            "sh -c 'echo starting >&2; echo oops >&2; exit 3'",
+        );
+        thread::sleep(Duration::from_millis(500));
+
+        let exit = worker.exit_info();
+        assert_that(&exit).is_some();
+        let exit = exit.unwrap();
+        assert_that(&exit.code).is_equal_to(Some(3));
+        assert_that(&exit.stderr)
+            .is_equal_to(vec![String::from("starting"), String::from("oops")]);
+        assert_that(&worker.state()).is_equal_to(WorkerState::Stopped);
+    }
+
+    #[test]
+    fn test_records_signal() {
+        let mut worker = setup("test_records_signal", "sleep 30");
+        worker.signal(15).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        assert_that(&worker.exit_info().and_then(|exit| exit.signal)).is_equal_to(Some(15));
+    }
+}
+
+mod log {
+    use super::setup;
+    use spectral::assert_that;
+    use spectral::prelude::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_keeps_stdout_and_stderr() {
+        let worker = setup("test_keeps_stdout_and_stderr", "sh -c 'echo out; echo err >&2'");
+        thread::sleep(Duration::from_millis(500));
+
+        let mut log = worker.log(10);
+        log.sort();
+        assert_that(&log).is_equal_to(vec![String::from("err"), String::from("out")]);
+        assert_that(&worker.log(1)).has_length(1);
+        assert_that(&worker.status().pid).is_none();
+    }
+}
+
+mod hooks {
+    use service::worker::{ServiceWorker, Worker, WorkerState};
+    use service::Service;
+    use signal_hook::consts::signal::SIGTERM;
+    use spectral::assert_that;
+    use spectral::prelude::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const GRACE: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_does_not_start_when_pre_start_fails() {
+        let mut service = Service::new("web", "sleep 3");
+        service.annotate("hook.pre-start", "echo not ready; exit 1").unwrap();
+        let mut worker = ServiceWorker::new(service);
+
+        assert_that(&worker.start()).is_err();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Failed);
+        assert_that(&worker.error())
+            .is_some()
+            .is_equal_to("The pre-start hook for web exited with code 1.");
+        assert_that(&worker.log(10)).is_equal_to(vec![String::from("[web pre-start] not ready")]);
+    }
+
+    #[test]
+    fn test_runs_hooks_around_run() {
+        let mut service = Service::new("web", "sleep 3");
+        service.annotate("hook.pre-start", "echo $STRAWBOSS_EVENT").unwrap();
+        service.annotate("hook.post-start", "echo $STRAWBOSS_EVENT").unwrap();
+        service.annotate("hook.pre-stop", "echo $STRAWBOSS_EVENT").unwrap();
+        service
+            .annotate("hook.post-exit", "echo $STRAWBOSS_EVENT $STRAWBOSS_EXIT_CODE")
+            .unwrap();
+        let mut worker = ServiceWorker::new(service);
+        worker.start().unwrap();
+        thread::sleep(Duration::from_millis(500));
+
+        // The pre-stop hook only runs once, however many times the task's asked to stop.
+        worker.terminate(SIGTERM, GRACE).unwrap();
+        worker.terminate(SIGTERM, GRACE).unwrap();
+        // Joining waits for the post-exit hook.
+        assert_that(&worker.join()).is_ok();
+
+        assert_that(&worker.log(10)).is_equal_to(vec![
+            String::from("[web pre-start] pre-start"),
+            String::from("[web post-start] post-start"),
+            String::from("[web pre-stop] pre-stop"),
+            String::from("[web post-exit] post-exit 143"),
+        ]);
+    }
+
+    #[test]
+    fn test_checks_on_task_during_pre_stop() {
+        let mut service = Service::new("web", "sleep 3");
+        service.annotate("hook.pre-stop", "sleep 1").unwrap();
+        let mut worker = ServiceWorker::new(service);
+        worker.start().unwrap();
+
+        worker.terminate(SIGTERM, GRACE).unwrap();
+        let start = Instant::now();
+        assert_that(&worker.try_wait()).is_ok().is_none();
+        assert_that(&(Instant::now() - start)).is_less_than(Duration::from_millis(500));
+
+        thread::sleep(Duration::from_millis(1500));
+        assert_that(&worker.exit_info().and_then(|exit| exit.signal)).is_equal_to(Some(SIGTERM));
+    }
+
+    #[test]
+    fn test_kills_task_that_does_not_stop_in_time() {
+        let service = Service::new(
+            "stubborn",
+            "sh -c 'trap \"\" TERM; while true; do sleep 0.1; done'",
+        );
+        let mut worker = ServiceWorker::new(service);
+        worker.start().unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        worker.terminate(SIGTERM, Duration::from_millis(300)).unwrap();
+        thread::sleep(Duration::from_millis(1000));
+        assert_that(&worker.is_running()).is_false();
+        assert_that(&worker.state()).is_equal_to(WorkerState::Stopped);
+    }
+}
+
 mod thread_id {
     use super::setup;
     use service::worker::Worker;
@@ -0,0 +1,59 @@
+use libc;
+use Result;
+
+/// The signals that can be named, without their `SIG` prefixes.
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// Parse a signal given by name, like `HUP` or `SIGHUP`, or by number.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate libc;
+/// extern crate straw_boss;
+///
+/// use straw_boss::signal::parse_signal;
+///
+/// # fn main() {
+/// assert_eq!(libc::SIGHUP, parse_signal("HUP").unwrap());
+/// assert_eq!(libc::SIGUSR1, parse_signal("sigusr1").unwrap());
+/// assert_eq!(libc::SIGTERM, parse_signal("15").unwrap());
+/// assert!(parse_signal("BOGUS").is_err());
+/// # }
+/// ```
+pub fn parse_signal(signal: &str) -> Result<i32> {
+    if let Ok(number) = signal.parse::<i32>() {
+        return if number > 0 && number < 32 {
+            Ok(number)
+        } else {
+            Err(format_err!("Invalid signal number: {}", number))
+        };
+    }
+
+    let name = signal.to_uppercase();
+    let name = if name.starts_with("SIG") {
+        &name[3..]
+    } else {
+        &name[..]
+    };
+    SIGNALS
+        .iter()
+        .find(|&&(n, _)| n == name)
+        .map(|&(_, number)| number)
+        .ok_or_else(|| format_err!("Unknown signal: {:?}", &signal))
+}
@@ -1,5 +1,31 @@
-#[derive(Debug)]
+/// Which of the running tasks a request applies to.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum TaskSpec {
     All,
     List(Vec<String>),
 }
+
+impl TaskSpec {
+    /// Does this include the named task? Naming a process type, like `web`, includes all of its
+    /// instances, like `web.1` and `web.2`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use straw_boss::tasks::TaskSpec;
+    ///
+    /// let tasks = TaskSpec::List(vec![String::from("web")]);
+    /// assert!(tasks.matches("web"));
+    /// assert!(tasks.matches("web.2"));
+    /// assert!(!tasks.matches("webhooks"));
+    /// ```
+    pub fn matches(&self, name: &str) -> bool {
+        match *self {
+            TaskSpec::All => true,
+            TaskSpec::List(ref tasks) => tasks.iter().any(|task| {
+                name == task
+                    || (name.starts_with(task.as_str()) && name[task.len()..].starts_with('.'))
+            }),
+        }
+    }
+}
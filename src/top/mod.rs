@@ -0,0 +1,429 @@
+use client::{ManagerClient, ManagerStatus};
+use server::ProcessScale;
+use service::log::LOG_LINES;
+use service::usage::{seconds, show_size};
+use service::worker::{WorkerState, WorkerStatus};
+use signal::parse_signal;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tasks::TaskSpec;
+use termion;
+use termion::event;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use Result;
+
+/// How often the dashboard asks the server for the services' status.
+const REFRESH: Duration = Duration::from_secs(1);
+
+const HELP: &str = "j/k: select  enter: logs  r: restart  s: stop  x: signal  +/-: scale  q: quit";
+
+/// The keys that the dashboard responds to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Backspace,
+    /// Control-C, which always quits.
+    Interrupt,
+    Char(char),
+}
+
+/// What the dashboard's showing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    /// The services.
+    List,
+    /// The output of the selected service.
+    Logs,
+    /// Asking which signal to send to the selected service, with what's been typed so far.
+    Signal(String),
+}
+
+/// One service's line on the dashboard.
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    name: String,
+    state: WorkerState,
+    pid: Option<u32>,
+    /// The share of a CPU that the service used since the last refresh, as a percentage.
+    cpu: Option<f64>,
+    memory: Option<u64>,
+    restarts: u32,
+    uptime: Option<Duration>,
+}
+
+/// A live view of the services that a server's running, like `top`. This holds what's shown and
+/// responds to keys. It doesn't touch the terminal itself, that's `run`'s job.
+pub struct Top<'a, C: ManagerClient + 'a> {
+    client: &'a C,
+    rows: Vec<Row>,
+    /// The CPU time each service had used at the last refresh, and when that was.
+    samples: BTreeMap<String, (Instant, Duration)>,
+    selected: usize,
+    mode: Mode,
+    logs: Vec<String>,
+    /// What happened with the last thing the user asked for.
+    message: Option<String>,
+}
+
+impl<'a, C: ManagerClient> Top<'a, C> {
+    pub fn new(client: &'a C) -> Top<'a, C> {
+        Top {
+            client,
+            rows: Vec::new(),
+            samples: BTreeMap::new(),
+            selected: 0,
+            mode: Mode::List,
+            logs: Vec::new(),
+            message: None,
+        }
+    }
+
+    /// Ask the server for the services' status, and for the selected service's output if that's
+    /// showing. Errors are shown on the message line, so the dashboard carries on if the server
+    /// goes away for a moment.
+    pub fn refresh(&mut self) {
+        match self.client.get_workers() {
+            Ok(workers) => self.update(&workers, Instant::now(), SystemTime::now()),
+            Err(err) => self.message = Some(format!("Unable to query workers: {}", err)),
+        }
+        if self.mode == Mode::Logs {
+            self.fetch_logs();
+        }
+    }
+
+    /// Respond to a key. The message line's cleared, so it only says how the last key went.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the dashboard should close.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        if key == Key::Interrupt {
+            return false;
+        }
+        self.message = None;
+        if let Mode::Signal(mut input) = self.mode.clone() {
+            match key {
+                Key::Char(c) => input.push(c),
+                Key::Backspace => {
+                    input.pop();
+                }
+                Key::Enter => {
+                    self.mode = Mode::List;
+                    self.send_signal(&input);
+                    return true;
+                }
+                Key::Esc => {
+                    self.mode = Mode::List;
+                    return true;
+                }
+                _ => {}
+            }
+            self.mode = Mode::Signal(input);
+            return true;
+        }
+        match key {
+            Key::Up | Key::Char('k') if self.mode == Mode::List => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            Key::Down | Key::Char('j')
+                if self.mode == Mode::List && self.selected + 1 < self.rows.len() =>
+            {
+                self.selected += 1;
+            }
+            Key::Enter if self.mode == Mode::List && self.selected_name().is_some() => {
+                self.mode = Mode::Logs;
+                self.fetch_logs();
+            }
+            Key::Esc | Key::Backspace | Key::Char('q') if self.mode == Mode::Logs => {
+                self.mode = Mode::List;
+            }
+            Key::Esc | Key::Char('q') => return false,
+            Key::Char('r') => {
+                self.act("restart", "Restarting", |client, tasks| client.restart(tasks))
+            }
+            Key::Char('s') => self.act("stop", "Stopping", |client, tasks| client.stop(tasks)),
+            Key::Char('x') if self.selected_name().is_some() => {
+                self.mode = Mode::Signal(String::new());
+            }
+            Key::Char('+') => self.scale_by(1),
+            Key::Char('-') => self.scale_by(-1),
+            _ => {}
+        }
+        true
+    }
+
+    /// Lay out the dashboard for a terminal of the given size, one string per line.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let body = height.saturating_sub(2);
+        let mut lines = match self.mode {
+            Mode::Logs => {
+                let name = self.selected_name().unwrap_or_default();
+                let skip = self.logs.len().saturating_sub(body);
+                let mut lines = vec![format!("{} output (esc to go back)", name)];
+                lines.extend(self.logs.iter().skip(skip).cloned());
+                lines
+            }
+            _ => {
+                let skip = (self.selected + 1).saturating_sub(body);
+                let mut lines = vec![format!(
+                    "  {:<20} {:<10} {:>7} {:>6} {:>8} {:>8} {:>8}",
+                    "NAME", "STATE", "PID", "CPU%", "MEMORY", "RESTARTS", "UPTIME"
+                )];
+                lines.extend(
+                    self.rows
+                        .iter()
+                        .enumerate()
+                        .skip(skip)
+                        .take(body)
+                        .map(|(i, row)| describe_row(row, i == self.selected)),
+                );
+                lines
+            }
+        };
+        lines.truncate(height.saturating_sub(1));
+        while lines.len() < height.saturating_sub(1) {
+            lines.push(String::new());
+        }
+        let status = match self.mode {
+            Mode::Signal(ref input) => format!(
+                "Signal to send to {}: {}",
+                self.selected_name().unwrap_or_default(),
+                input
+            ),
+            _ => self.message.clone().unwrap_or_else(|| String::from(HELP)),
+        };
+        if height > 0 {
+            lines.push(status);
+        }
+        lines
+            .into_iter()
+            .map(|line| line.chars().take(width).collect())
+            .collect()
+    }
+
+    /// Replace the rows with the latest status. The selection stays on the same service if it's
+    /// still there.
+    fn update(&mut self, workers: &[WorkerStatus], now: Instant, wall_time: SystemTime) {
+        let selected = self.selected_name();
+        let mut samples = BTreeMap::new();
+        self.rows = workers
+            .iter()
+            .map(|status| {
+                let name = status.service.name.clone();
+                let used = status.usage.and_then(|usage| usage.cpu);
+                let cpu = used.and_then(|used| {
+                    let (then, before) = self.samples.get(&name)?;
+                    let elapsed = seconds(now.duration_since(*then));
+                    if used < *before || elapsed <= 0.0 {
+                        return None;
+                    }
+                    Some(seconds(used - *before) / elapsed * 100.0)
+                });
+                if let Some(used) = used {
+                    samples.insert(name.clone(), (now, used));
+                }
+                let running = status.state == WorkerState::Running
+                    || status.state == WorkerState::Paused;
+                Row {
+                    name,
+                    state: status.state,
+                    pid: status.pid,
+                    cpu,
+                    memory: status.usage.and_then(|usage| usage.memory),
+                    restarts: status.restarts,
+                    uptime: status
+                        .started_at
+                        .filter(|_| running && status.exit.is_none())
+                        .and_then(|started_at| wall_time.duration_since(started_at).ok()),
+                }
+            }).collect();
+        self.samples = samples;
+        self.selected = selected
+            .and_then(|name| self.rows.iter().position(|row| row.name == name))
+            .unwrap_or_else(|| self.selected.min(self.rows.len().saturating_sub(1)));
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.rows.get(self.selected).map(|row| row.name.clone())
+    }
+
+    fn fetch_logs(&mut self) {
+        if let Some(name) = self.selected_name() {
+            match self.client.get_logs(&name, LOG_LINES) {
+                Ok(logs) => self.logs = logs,
+                Err(err) => self.message = Some(format!("Unable to get output: {}", err)),
+            }
+        }
+    }
+
+    /// Do something to the selected service, and say how it went.
+    fn act<F>(&mut self, verb: &str, done: &str, action: F)
+    where
+        F: FnOnce(&C, TaskSpec) -> Result<()>,
+    {
+        if let Some(name) = self.selected_name() {
+            let tasks = TaskSpec::List(vec![name.clone()]);
+            self.message = Some(match action(self.client, tasks) {
+                Ok(()) => format!("{} {}.", done, name),
+                Err(err) => format!("Unable to {} {}: {}", verb, name, err),
+            });
+        }
+    }
+
+    fn send_signal(&mut self, input: &str) {
+        match parse_signal(input.trim()) {
+            Ok(signal) => {
+                let done = format!("Sent signal {} to", signal);
+                self.act("signal", &done, |client, tasks| client.signal(tasks, signal))
+            }
+            Err(err) => self.message = Some(err.to_string()),
+        }
+    }
+
+    /// Run one more or one fewer instance of the selected service's process type.
+    fn scale_by(&mut self, change: isize) {
+        let name = match self.selected_name() {
+            Some(name) => name,
+            None => return,
+        };
+        let result = self.client.get_scale().and_then(|scale| {
+            let process = process_type(&scale, &name)
+                .ok_or_else(|| format_err!("{} isn't a long-lived process type.", name))?;
+            let desired = (process.desired as isize + change).max(0);
+            let formation = format!("{}={}", process.name, desired).parse()?;
+            self.client.scale(formation)?;
+            Ok(format!("Scaling {} to {}.", process.name, desired))
+        });
+        self.message = Some(result.unwrap_or_else(|err| format!("Unable to scale: {}", err)));
+    }
+}
+
+/// Show the dashboard in the terminal until the user quits.
+pub fn run<C: ManagerClient>(client: &C) -> Result<i32> {
+    if !client.is_running() {
+        return Err(format_err!("{}", ManagerStatus::NotFound.get_message()));
+    }
+    let stdout = io::stdout()
+        .into_raw_mode()
+        .map_err(|err| format_err!("Unable to set up the terminal: {:?}", &err))?;
+    let mut screen = AlternateScreen::from(stdout);
+
+    let (tx, rx) = channel();
+    thread::Builder::new()
+        .name(String::from("top-keys"))
+        .spawn(move || {
+            for key in io::stdin().keys() {
+                let key = match key {
+                    Ok(event::Key::Up) => Key::Up,
+                    Ok(event::Key::Down) => Key::Down,
+                    Ok(event::Key::Char('\n')) => Key::Enter,
+                    Ok(event::Key::Esc) => Key::Esc,
+                    Ok(event::Key::Backspace) => Key::Backspace,
+                    Ok(event::Key::Ctrl('c')) => Key::Interrupt,
+                    Ok(event::Key::Char(c)) => Key::Char(c),
+                    Ok(_) => continue,
+                    Err(_) => break,
+                };
+                if tx.send(key).is_err() {
+                    break;
+                }
+            }
+        }).map_err(|err| format_err!("Unable to read keys: {:?}", &err))?;
+
+    let mut top = Top::new(client);
+    let mut refreshed = Instant::now();
+    top.refresh();
+    loop {
+        draw(&mut screen, &top)?;
+        let wait = REFRESH
+            .checked_sub(refreshed.elapsed())
+            .unwrap_or_default();
+        match rx.recv_timeout(wait) {
+            Ok(key) => {
+                if !top.handle_key(key) {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                refreshed = Instant::now();
+                top.refresh();
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    write!(screen, "{}", termion::cursor::Show)
+        .and_then(|_| screen.flush())
+        .map_err(|err| format_err!("Unable to write output: {:?}", &err))?;
+    Ok(0)
+}
+
+fn draw<W: Write, C: ManagerClient>(screen: &mut W, top: &Top<C>) -> Result<()> {
+    let (width, height) = termion::terminal_size().unwrap_or((80, 24));
+    let lines = top.render(width as usize, height as usize);
+    write!(
+        screen,
+        "{}{}{}{}",
+        termion::cursor::Hide,
+        termion::clear::All,
+        termion::cursor::Goto(1, 1),
+        lines.join("\r\n")
+    ).and_then(|_| screen.flush())
+    .map_err(|err| format_err!("Unable to write output: {:?}", &err))
+}
+
+/// The process type that a service is an instance of, like `web` for `web.2`.
+fn process_type<'s>(scale: &'s [ProcessScale], name: &str) -> Option<&'s ProcessScale> {
+    scale.iter().find(|process| {
+        name == process.name
+            || (name.starts_with(&process.name) && name[process.name.len()..].starts_with('.'))
+    })
+}
+
+fn describe_row(row: &Row, selected: bool) -> String {
+    let dash = || String::from("-");
+    format!(
+        "{} {:<20.20} {:<10} {:>7} {:>6} {:>8} {:>8} {:>8}",
+        if selected { ">" } else { " " },
+        row.name,
+        state_name(row.state),
+        row.pid.map(|pid| pid.to_string()).unwrap_or_else(dash),
+        row.cpu.map(|cpu| format!("{:.1}", cpu)).unwrap_or_else(dash),
+        row.memory.map(show_size).unwrap_or_else(dash),
+        row.restarts,
+        row.uptime.map(show_uptime).unwrap_or_else(dash),
+    )
+}
+
+fn state_name(state: WorkerState) -> &'static str {
+    match state {
+        WorkerState::Running => "running",
+        WorkerState::Paused => "paused",
+        WorkerState::Stopped => "stopped",
+        WorkerState::Failed => "failed",
+        WorkerState::Completed => "completed",
+        WorkerState::Scheduled => "scheduled",
+    }
+}
+
+/// Show how long something's been up in its two largest units, like `3h05m`.
+fn show_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        3600..=86_399 => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d{:02}h", secs / 86_400, secs % 86_400 / 3600),
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,200 @@
+use super::{show_uptime, Key, Mode, Top};
+use client::fake::FakeManagerClient;
+use server::ProcessScale;
+use service::usage::Usage;
+use service::worker::{WorkerState, WorkerStatus};
+use service::Service;
+use spectral::prelude::*;
+use std::time::{Duration, Instant, SystemTime};
+
+fn running(name: &str, pid: u32) -> WorkerStatus {
+    let mut status = WorkerStatus::new(Service::new(name, "spawn server"), WorkerState::Running);
+    status.pid = Some(pid);
+    status
+}
+
+fn client() -> FakeManagerClient {
+    FakeManagerClient::new(vec![
+        running("web", 100),
+        running("web.2", 200),
+        WorkerStatus::new(Service::new("worker", "spawn worker"), WorkerState::Failed),
+    ])
+}
+
+#[test]
+fn test_lists_services() {
+    let client = client();
+    let mut top = Top::new(&client);
+    top.refresh();
+    let lines = top.render(80, 6);
+
+    assert_that(&lines).has_length(6);
+    assert_that(&lines[0]).starts_with("  NAME");
+    assert_that(&lines[1]).starts_with("> web ");
+    assert_that(&lines[1]).contains("running");
+    assert_that(&lines[1]).contains(" 100 ");
+    assert_that(&lines[2]).starts_with("  web.2 ");
+    assert_that(&lines[3]).starts_with("  worker ");
+    assert_that(&lines[3]).contains("failed");
+    assert_that(&lines[5]).starts_with("j/k: select");
+}
+
+#[test]
+fn test_fits_terminal() {
+    let client = client();
+    let mut top = Top::new(&client);
+    top.refresh();
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Down);
+    let lines = top.render(10, 4);
+
+    assert_that(&lines).has_length(4);
+    assert_that(&lines.iter().all(|line| line.chars().count() <= 10)).is_true();
+    assert_that(&lines[1]).starts_with("  web.2");
+    assert_that(&lines[2]).starts_with("> worker");
+}
+
+#[test]
+fn test_moves_selection() {
+    let client = client();
+    let mut top = Top::new(&client);
+    top.refresh();
+
+    top.handle_key(Key::Up);
+    assert_that(&top.selected).is_equal_to(0);
+    top.handle_key(Key::Char('j'));
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Down);
+    assert_that(&top.selected).is_equal_to(2);
+    top.handle_key(Key::Char('k'));
+    assert_that(&top.selected).is_equal_to(1);
+}
+
+#[test]
+fn test_keeps_selection_across_refreshes() {
+    let client = client();
+    let mut top = Top::new(&client);
+    let mut workers = client.workers.clone();
+    top.update(&workers, Instant::now(), SystemTime::now());
+    top.handle_key(Key::Down);
+
+    workers.remove(0);
+    top.update(&workers, Instant::now(), SystemTime::now());
+    assert_that(&top.selected_name()).is_equal_to(Some(String::from("web.2")));
+    workers.remove(0);
+    top.update(&workers, Instant::now(), SystemTime::now());
+    assert_that(&top.selected_name()).is_equal_to(Some(String::from("worker")));
+}
+
+#[test]
+fn test_computes_cpu_share() {
+    let client = FakeManagerClient::new(vec![]);
+    let mut top = Top::new(&client);
+    let mut status = running("web", 100);
+    let start = Instant::now();
+    let now = SystemTime::now();
+    status.started_at = Some(now - Duration::from_secs(125));
+    status.restarts = 3;
+    status.usage = Some(Usage {
+        memory: Some(3 << 20),
+        cpu: Some(Duration::from_secs(10)),
+    });
+    top.update(&[status.clone()], start, now);
+    assert_that(&top.rows[0].cpu).is_none();
+
+    status.usage = Some(Usage {
+        memory: Some(3 << 20),
+        cpu: Some(Duration::from_millis(10_500)),
+    });
+    top.update(&[status], start + Duration::from_secs(2), now);
+    assert_that(&top.rows[0].cpu).is_equal_to(Some(25.0));
+    let line = &top.render(80, 3)[1];
+    assert_that(line).contains(" 25.0 ");
+    assert_that(line).contains(" 3.0M ");
+    assert_that(line).contains(" 3 ");
+    assert_that(line).ends_with(" 2m05s");
+}
+
+#[test]
+fn test_shows_logs() {
+    let client = client();
+    let mut top = Top::new(&client);
+    top.refresh();
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Enter);
+    assert_that(&top.mode).is_equal_to(Mode::Logs);
+
+    let lines = top.render(80, 5);
+    assert_that(&lines[0]).starts_with("web.2 output");
+    assert_that(&lines[1].as_str()).is_equal_to("web.2 says hello");
+    assert_that(&lines[2].as_str()).is_equal_to("web.2 says bye");
+
+    assert_that(&top.handle_key(Key::Char('q'))).is_true();
+    assert_that(&top.mode).is_equal_to(Mode::List);
+    assert_that(&top.handle_key(Key::Char('q'))).is_false();
+}
+
+#[test]
+fn test_restarts_and_stops_selected() {
+    let client = client();
+    let mut top = Top::new(&client);
+    top.refresh();
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Char('r'));
+    assert_that(&top.render(80, 5)[4].as_str()).is_equal_to("Restarting web.2.");
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Char('s'));
+
+    assert_that(&*client.calls.borrow())
+        .is_equal_to(vec![String::from("restart web.2"), String::from("stop worker")]);
+}
+
+#[test]
+fn test_signals_selected() {
+    let client = client();
+    let mut top = Top::new(&client);
+    top.refresh();
+    top.handle_key(Key::Char('x'));
+    for c in "HUPP".chars() {
+        top.handle_key(Key::Char(c));
+    }
+    top.handle_key(Key::Backspace);
+    assert_that(&top.render(80, 5)[4].as_str()).is_equal_to("Signal to send to web: HUP");
+    top.handle_key(Key::Enter);
+    top.handle_key(Key::Char('x'));
+    top.handle_key(Key::Char('?'));
+    top.handle_key(Key::Enter);
+    assert_that(&top.render(80, 5)[4]).starts_with("Unknown signal");
+
+    assert_that(&*client.calls.borrow()).is_equal_to(vec![String::from("signal web 1")]);
+}
+
+#[test]
+fn test_scales_process_type() {
+    let mut client = client();
+    client.scale = vec![ProcessScale {
+        name: String::from("web"),
+        desired: 2,
+        running: 2,
+    }];
+    let mut top = Top::new(&client);
+    top.refresh();
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Char('+'));
+    top.handle_key(Key::Char('-'));
+    top.handle_key(Key::Down);
+    top.handle_key(Key::Char('+'));
+    assert_that(&top.render(80, 5)[4].as_str())
+        .is_equal_to("Unable to scale: worker isn't a long-lived process type.");
+
+    assert_that(&*client.calls.borrow())
+        .is_equal_to(vec![String::from("scale web=3"), String::from("scale web=1")]);
+}
+
+#[test]
+fn test_shows_uptime() {
+    assert_that(&show_uptime(Duration::from_secs(42)).as_str()).is_equal_to("42s");
+    assert_that(&show_uptime(Duration::from_secs(3 * 3600 + 300)).as_str()).is_equal_to("3h05m");
+    assert_that(&show_uptime(Duration::from_secs(2 * 86_400 + 3600)).as_str())
+        .is_equal_to("2d01h");
+}
@@ -0,0 +1,47 @@
+extern crate assert_cmd;
+#[macro_use]
+extern crate failure;
+extern crate spectral;
+extern crate straw_boss;
+extern crate sysinfo;
+
+use assert_cmd::prelude::*;
+use spectral::assert_that;
+use spectral::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+mod utils;
+
+use utils::poll::poll_processes;
+
+#[test]
+fn test_ctrl_c_stops_services() {
+    let socket_path = PathBuf::from("/tmp/straw-boss.test-ctrl-c.sock");
+    if socket_path.exists() {
+        fs::remove_file(&socket_path).unwrap();
+    }
+
+    let mut server = Command::main_binary()
+        .unwrap()
+        .env("STRAWBOSS_SOCKET_PATH", &*socket_path.to_string_lossy())
+        .arg("start")
+        .arg("--procfile")
+        .arg("./fixtures/Procfile.ctrl_c")
+        .spawn()
+        .unwrap();
+    assert_that(&poll_processes("http.server", "3042", 10)).is_some();
+
+    Command::new("kill")
+        .arg("-INT")
+        .arg(server.id().to_string())
+        .status()
+        .unwrap();
+    let status = server.wait().unwrap();
+
+    assert_that(&status.success()).is_true();
+    assert_that(&socket_path).does_not_exist();
+    assert_that(&poll_processes("http.server", "3040", 1)).is_none();
+    assert_that(&poll_processes("http.server", "3042", 1)).is_none();
+}
@@ -5,8 +5,10 @@ extern crate spectral;
 extern crate straw_boss;
 extern crate sysinfo;
 
+use assert_cmd::prelude::*;
 use spectral::assert_that;
 use spectral::prelude::*;
+use std::process::Command;
 use std::thread;
 use std::time::Duration;
 
@@ -28,3 +30,34 @@ fn test_daemon() {
     assert_that(&status.success()).is_true();
     thread::sleep(Duration::from_secs(1));
 }
+
+#[test]
+fn test_daemon_reports_startup_errors() {
+    let socket_path = format!("/tmp/straw-boss.{}.sock", "x".repeat(120));
+    let output = Command::main_binary()
+        .unwrap()
+        .env("STRAWBOSS_SOCKET_PATH", &socket_path)
+        .env("STRAWBOSS_PID_FILE", "/tmp/straw-boss.test-daemon-startup-errors.pid")
+        .args(&["start", "--procfile", "./fixtures/Procfile.python", "--daemon"])
+        .output()
+        .unwrap();
+
+    assert_that(&output.status.success()).is_false();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_that(&stderr.as_str()).contains("Unable to open socket");
+    assert_that(&poll_processes("http.server", "3040", 1)).is_none();
+}
+
+#[test]
+fn test_daemon_reports_services_that_fail_to_start() {
+    let mut server = StopServer::new("test-daemon-fail-to-start");
+    server.daemonize("./fixtures/Procfile.missing").unwrap();
+
+    let output = server.stop().unwrap();
+    assert_that(&output.status.success()).is_false();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_that(&stderr.as_str()).contains("Unable to start 1 of 2 services.");
+    assert_that(&stderr.as_str()).contains("missing: Unable to run `straw-boss-no-such-command`");
+    thread::sleep(Duration::from_secs(1));
+    assert_that(&poll_processes("http.server", "3043", 1)).is_none();
+}
@@ -0,0 +1,26 @@
+extern crate assert_cmd;
+extern crate spectral;
+extern crate straw_boss;
+
+use assert_cmd::prelude::*;
+use spectral::assert_that;
+use spectral::prelude::*;
+use std::process::Command;
+
+#[test]
+fn test_writes_procfile_and_warnings() {
+    let command = Command::main_binary()
+        .unwrap()
+        .arg("import")
+        .arg("docker-compose")
+        .arg("./fixtures/import/docker-compose.yml")
+        .unwrap();
+
+    let output = String::from_utf8(command.stdout.clone()).unwrap();
+    let errors = String::from_utf8(command.stderr.clone()).unwrap();
+    command.assert().success();
+
+    assert_that(&output).contains("web: python3 -m http.server 3040");
+    assert_that(&output).contains("env.MODE=development");
+    assert_that(&errors).contains("warning: web: ignoring `ports`");
+}
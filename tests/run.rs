@@ -0,0 +1,81 @@
+extern crate assert_cmd;
+extern crate libc;
+extern crate spectral;
+extern crate straw_boss;
+
+use assert_cmd::prelude::*;
+use spectral::assert_that;
+use spectral::prelude::*;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_propagates_exit_code() {
+    let output = Command::main_binary()
+        .unwrap()
+        .arg("run")
+        .arg("--procfile")
+        .arg("./fixtures/env/Procfile")
+        .arg("--")
+        .arg("exit-with")
+        .arg("7")
+        .output()
+        .unwrap();
+
+    assert_that(&output.status.code()).is_equal_to(Some(7));
+}
+
+#[test]
+fn test_runs_with_env_file() {
+    let command = Command::main_binary()
+        .unwrap()
+        .arg("run")
+        .arg("--procfile")
+        .arg("./fixtures/env/Procfile")
+        .arg("--env")
+        .arg("./fixtures/env/other.env")
+        .arg("--")
+        .arg("sh")
+        .arg("-c")
+        .arg("echo $GREETING")
+        .unwrap();
+
+    let output = String::from_utf8(command.stdout.clone()).unwrap();
+    command.assert().success();
+
+    assert_that(&output).contains("hello from other.env");
+}
+
+#[test]
+fn test_waits_for_command_after_ctrl_c() {
+    let mut command = Command::main_binary().unwrap();
+    command
+        .arg("run")
+        .arg("--procfile")
+        .arg("./fixtures/env/Procfile")
+        .arg("--")
+        .arg("sh")
+        .arg("-c")
+        .arg("trap 'kill $!; sleep 0.5; exit 42' INT; sleep 5 & wait");
+    // Like a terminal, Ctrl-C goes to the whole process group, which is only straw boss and the
+    // command here.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut run = command.spawn().unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    unsafe {
+        libc::kill(-(run.id() as libc::pid_t), libc::SIGINT);
+    }
+
+    assert_that(&run.wait().unwrap().code()).is_equal_to(Some(42));
+}
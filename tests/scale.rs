@@ -0,0 +1,46 @@
+extern crate assert_cmd;
+#[macro_use]
+extern crate failure;
+extern crate spectral;
+extern crate straw_boss;
+extern crate sysinfo;
+
+use assert_cmd::prelude::*;
+use spectral::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+mod utils;
+
+use utils::command::StopServer;
+use utils::poll::poll_processes;
+
+#[test]
+fn test_scale() {
+    let mut server = StopServer::new("test-scale");
+    server.daemonize("./fixtures/Procfile.scale").unwrap();
+    assert_that(&poll_processes("http.server", "3046", 10)).is_some();
+
+    server
+        .build_client()
+        .unwrap()
+        .args(&["scale", "web=2"])
+        .assert()
+        .success();
+    assert_that(&poll_processes("http.server", "3047", 10)).is_some();
+
+    let output = server.build_client().unwrap().arg("status").output().unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert_that(&output).contains("web.2: python3 -m http.server $PORT");
+    assert_that(&output).contains("    web: 2 running, 2 wanted\n");
+
+    server
+        .build_client()
+        .unwrap()
+        .args(&["scale", "web=1"])
+        .assert()
+        .success();
+    thread::sleep(Duration::from_secs(1));
+    assert_that(&poll_processes("http.server", "3047", 1)).is_none();
+    assert_that(&poll_processes("http.server", "3046", 1)).is_some();
+}
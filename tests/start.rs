@@ -5,8 +5,10 @@ extern crate spectral;
 extern crate straw_boss;
 extern crate sysinfo;
 
+use assert_cmd::prelude::*;
 use spectral::assert_that;
 use spectral::prelude::*;
+use std::process::Command;
 use std::thread;
 use std::time::Duration;
 
@@ -35,3 +37,34 @@ fn test_pipe_commands() {
 
     // assert_that(&text_output).contains("2");
 }
+
+#[test]
+fn test_exit_on_first_exit() {
+    let output = Command::main_binary()
+        .unwrap()
+        .env("STRAWBOSS_SOCKET_PATH", "/tmp/straw-boss.test-exit-on-first-exit.sock")
+        .args(&["start", "--procfile", "./fixtures/Procfile.exit", "--exit-on-first-exit"])
+        .output()
+        .unwrap();
+
+    assert_that(&output.status.code()).is_equal_to(Some(7));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_that(&stderr.as_str()).contains("job exited with code 7");
+    assert_that(&poll_processes("http.server", "3044", 1)).is_none();
+}
+
+#[test]
+fn test_failed_oneshot_stops_start() {
+    let output = Command::main_binary()
+        .unwrap()
+        .env("STRAWBOSS_SOCKET_PATH", "/tmp/straw-boss.test-failed-oneshot.sock")
+        .args(&["start", "--procfile", "./fixtures/Procfile.oneshot"])
+        .output()
+        .unwrap();
+
+    assert_that(&output.status.success()).is_false();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_that(&stderr.as_str()).contains("One-shot task migrate exited with code 3");
+    assert_that(&stderr.as_str()).contains("    no database");
+    assert_that(&poll_processes("http.server", "3045", 1)).is_none();
+}
@@ -31,6 +31,8 @@ fn test_status() {
 
     assert_that(&output).contains("python: python3 -m http.server 3040");
     assert_that(&output).contains("ls: ls fixtures");
+    assert_that(&output).contains("    cwd: ");
+    assert_that(&output).contains("fixtures\n");
 
     let process_info = poll_processes("http.server", "3040", 10);
     assert_that(&process_info).is_some();